@@ -0,0 +1,58 @@
+//! Build the opening book from the records of finished games saved by the server
+//!
+//! ```text
+//! cargo run --release -p ygame-ai --example book -- records server/static/book.ybk
+//! ```
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use ygame_ai::{OpeningBook, BOOK_PLIES};
+use ygame_game::GameRecord;
+
+fn main() {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	if args.len() != 2 {
+		fail("Usage: book RECORDS_DIR BOOK_FILE");
+	}
+	let entries = fs::read_dir(&args[0]).unwrap_or_else(|e| fail(&format!("{}: {}", args[0], e)));
+	let mut book = OpeningBook::new();
+	let (mut added, mut skipped) = (0, 0);
+	for entry in entries.flatten() {
+		let path = entry.path();
+		if path.extension() != Some(OsStr::new("ygn")) {
+			continue;
+		}
+		match read_record(&path) {
+			Ok(record) if book.add_game(&record) => added += 1,
+			Ok(_) => skipped += 1,
+			Err(e) => {
+				eprintln!("{}: {}", path.display(), e);
+				skipped += 1;
+			}
+		}
+	}
+	let bytes = book.to_bytes();
+	fs::write(&args[1], &bytes).unwrap_or_else(|e| fail(&format!("{}: {}", args[1], e)));
+	println!(
+		"{} games added ({} skipped), {} positions in the first {} plies, {} bytes written to {}",
+		added,
+		skipped,
+		book.len(),
+		BOOK_PLIES,
+		bytes.len(),
+		args[1]
+	);
+}
+
+fn read_record(path: &Path) -> Result<GameRecord, String> {
+	let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+	text.parse().map_err(|e: ygame_game::ParseError| e.to_string())
+}
+
+fn fail(message: &str) -> ! {
+	eprintln!("{}", message);
+	process::exit(1);
+}
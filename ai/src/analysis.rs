@@ -0,0 +1,101 @@
+use ygame_game::{Action, GameState, Move};
+
+use crate::search::WIN_THRESHOLD;
+use crate::{AlphaBeta, Evaluator, WIN_SCORE};
+
+/// What the engine thinks of a position, for hints and game reviews
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+	/// None only when the game is over
+	pub best_move: Option<Move>,
+	/// From the point of view of the player to move
+	pub score: i32,
+	pub depth: u32,
+	/// Expected moves of both players, starting with the best move
+	pub principal_variation: Vec<Move>,
+}
+
+impl Analysis {
+	/// Plies before the end of the game when the search found it forced: positive when the player to move wins,
+	/// negative when they lose
+	pub fn forced_end(&self) -> Option<i32> {
+		if self.score >= WIN_THRESHOLD {
+			Some(WIN_SCORE - self.score)
+		} else if self.score <= -WIN_THRESHOLD {
+			Some(-(WIN_SCORE + self.score))
+		} else {
+			None
+		}
+	}
+}
+
+/// Analysis of the position before each move of a game, along with the move played
+///
+/// Actions other than moves are skipped, and the analysis stops at the first move which can not be played.
+pub fn analyse_game<E: Evaluator>(
+	engine: &mut AlphaBeta<E>,
+	start: &GameState,
+	actions: &[Action],
+) -> Vec<(Move, Analysis)> {
+	let mut state = start.clone();
+	let mut analyses = Vec::new();
+	for action in actions {
+		if let Action::Move(mv) = action {
+			let analysis = engine.analyse(&state);
+			if state.play(mv).is_err() {
+				break;
+			}
+			analyses.push((*mv, analysis));
+		}
+	}
+	analyses
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Limits, MaterialEvaluator};
+	use ygame_game::notation;
+
+	#[test]
+	fn variation_starts_with_the_best_move() {
+		let state = GameState::new();
+		let mut engine = AlphaBeta::new(MaterialEvaluator, Limits::depth(4));
+		let analysis = engine.analyse(&state);
+		assert_eq!(analysis.principal_variation.first(), analysis.best_move.as_ref());
+		assert!(analysis.principal_variation.len() <= 4);
+		// Every move of the variation can be played in turn
+		let mut line = state;
+		for mv in analysis.principal_variation.iter() {
+			line.play(mv).unwrap();
+		}
+		assert_eq!(analysis.forced_end(), None);
+	}
+
+	#[test]
+	fn forced_wins() {
+		let state = notation::parse_position("4o/5/4o/2x2/ox2x o 3 7").unwrap();
+		let mut engine = AlphaBeta::new(MaterialEvaluator, Limits::depth(5));
+		let analysis = engine.analyse(&state);
+		assert_eq!(analysis.forced_end(), Some(3));
+		assert_eq!(analysis.principal_variation.len(), 3);
+		assert_eq!(analysis.best_move, Some("e3c1".parse().unwrap()));
+	}
+
+	#[test]
+	fn one_analysis_per_move() {
+		let start = GameState::new();
+		let actions = vec![
+			Action::Init,
+			Action::Move("b6".parse().unwrap()),
+			Action::OfferDraw,
+			Action::Move("b2".parse().unwrap()),
+			Action::DeclineDraw,
+		];
+		let mut engine = AlphaBeta::new(MaterialEvaluator, Limits::depth(2));
+		let analyses = analyse_game(&mut engine, &start, &actions);
+		assert_eq!(analyses.len(), 2);
+		assert_eq!(analyses[0].0, "b6".parse().unwrap());
+		assert!(analyses.iter().all(|(_, analysis)| analysis.best_move.is_some()));
+	}
+}
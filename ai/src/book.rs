@@ -0,0 +1,326 @@
+//! Opening book built from finished games
+//!
+//! Positions are keyed by their hash, and for each one the book keeps the moves played from it with how many
+//! games went on with them and how those games ended. Only the first [`BOOK_PLIES`] plies of games played with the
+//! standard rule set are recorded.
+//!
+//! The book file is little endian: the `YBK1` magic and the number of positions, then for each position its hash, the
+//! number of moves and the moves. A move is its kind (0 for a pass, 1 for a grow, 2 for a jump), its origin and
+//! destination squares as `col << 4 | row`, and the number of games, wins and draws of the player who played it.
+
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fmt::{self, Display};
+
+use ygame_game::{GameRecord, GameState, Move, Square, STANDARD_VARIANT};
+
+use crate::Engine;
+
+/// Plies of each game added to the book
+pub const BOOK_PLIES: usize = 16;
+/// Games a move needs before the engine trusts it
+pub const MIN_BOOK_GAMES: u32 = 3;
+
+const MAGIC: &[u8] = b"YBK1";
+const MOVE_SIZE: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookMove {
+	pub mv: Move,
+	pub games: u32,
+	/// Games won by the player who played the move
+	pub wins: u32,
+	pub draws: u32,
+}
+
+impl BookMove {
+	/// Share of the games won by the player who played the move, draws counting for half
+	pub fn win_rate(&self) -> f64 {
+		if self.games == 0 {
+			return 0.0;
+		}
+		(self.wins as f64 + self.draws as f64 / 2.0) / self.games as f64
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BookError {
+	BadMagic,
+	Truncated,
+	BadMove(u8),
+	TrailingData(usize),
+}
+
+impl Display for BookError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			BookError::BadMagic => write!(f, "Not an opening book"),
+			BookError::Truncated => write!(f, "The opening book is truncated"),
+			BookError::BadMove(kind) => write!(f, "Unknown move kind {} in the opening book", kind),
+			BookError::TrailingData(len) => write!(f, "{} unexpected bytes after the opening book", len),
+		}
+	}
+}
+
+impl std::error::Error for BookError {}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpeningBook {
+	// Moves are kept sorted, the most played first
+	positions: BTreeMap<u64, Vec<BookMove>>,
+}
+
+impl OpeningBook {
+	pub fn new() -> OpeningBook {
+		OpeningBook::default()
+	}
+
+	/// Number of positions in the book
+	pub fn len(&self) -> usize {
+		self.positions.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.positions.is_empty()
+	}
+
+	/// Add the opening of a game, returns false when the game is skipped: still running, played with other rules, or
+	/// with an illegal move
+	pub fn add_game(&mut self, record: &GameRecord) -> bool {
+		let result = match record.result {
+			Some(result) => result,
+			None => return false,
+		};
+		if record.variant != STANDARD_VARIANT || record.final_state().is_err() {
+			return false;
+		}
+		let mut state = record.position.clone().unwrap_or_default();
+		for mv in record.moves.iter().take(BOOK_PLIES) {
+			let mover = state.side_to_move();
+			let moves = self.positions.entry(state.hash()).or_default();
+			let index = match moves.iter().position(|book_move| book_move.mv == *mv) {
+				Some(index) => index,
+				None => {
+					moves.push(BookMove {
+						mv: *mv,
+						games: 0,
+						wins: 0,
+						draws: 0,
+					});
+					moves.len() - 1
+				}
+			};
+			let book_move = &mut moves[index];
+			book_move.games += 1;
+			match result.winner {
+				Some(winner) if winner == mover => book_move.wins += 1,
+				Some(_) => (),
+				None => book_move.draws += 1,
+			}
+			moves.sort_by_key(|book_move| std::cmp::Reverse(book_move.games));
+			state.play(mv).expect("record moves were checked");
+		}
+		true
+	}
+
+	/// Moves played from the position, the most played first
+	pub fn moves(&self, state: &GameState) -> &[BookMove] {
+		self.positions.get(&state.hash()).map(Vec::as_slice).unwrap_or(&[])
+	}
+
+	/// Legal book move with the best win rate among those played in at least [`MIN_BOOK_GAMES`] games
+	pub fn best_move(&self, state: &GameState) -> Option<Move> {
+		self.moves(state)
+			.iter()
+			.filter(|book_move| book_move.games >= MIN_BOOK_GAMES && state.check_move(&book_move.mv).is_ok())
+			.max_by(|a, b| {
+				a.win_rate()
+					.partial_cmp(&b.win_rate())
+					.unwrap_or(std::cmp::Ordering::Equal)
+					.then(a.games.cmp(&b.games))
+			})
+			.map(|book_move| book_move.mv)
+	}
+
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = MAGIC.to_vec();
+		bytes.extend_from_slice(&(self.positions.len() as u32).to_le_bytes());
+		for (hash, moves) in self.positions.iter() {
+			bytes.extend_from_slice(&hash.to_le_bytes());
+			bytes.extend_from_slice(&(moves.len() as u16).to_le_bytes());
+			for book_move in moves {
+				let (kind, from, to) = match book_move.mv {
+					Move::Pass => (0, 0, 0),
+					Move::Grow { to } => (1, 0, square_byte(to)),
+					Move::Jump { from, to } => (2, square_byte(from), square_byte(to)),
+				};
+				bytes.extend_from_slice(&[kind, from, to]);
+				bytes.extend_from_slice(&book_move.games.to_le_bytes());
+				bytes.extend_from_slice(&book_move.wins.to_le_bytes());
+				bytes.extend_from_slice(&book_move.draws.to_le_bytes());
+			}
+		}
+		bytes
+	}
+
+	pub fn from_bytes(bytes: &[u8]) -> Result<OpeningBook, BookError> {
+		let mut reader = Reader { bytes };
+		if reader.take(MAGIC.len()).map_err(|_| BookError::BadMagic)? != MAGIC {
+			return Err(BookError::BadMagic);
+		}
+		let mut book = OpeningBook::new();
+		for _ in 0..reader.u32()? {
+			let hash = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+			let count = u16::from_le_bytes(reader.take(2)?.try_into().unwrap()) as usize;
+			let mut moves = Vec::with_capacity(count.min(reader.bytes.len() / MOVE_SIZE));
+			for _ in 0..count {
+				let header = reader.take(3)?;
+				let mv = match header[0] {
+					0 => Move::Pass,
+					1 => Move::Grow {
+						to: byte_square(header[2]),
+					},
+					2 => Move::Jump {
+						from: byte_square(header[1]),
+						to: byte_square(header[2]),
+					},
+					kind => return Err(BookError::BadMove(kind)),
+				};
+				moves.push(BookMove {
+					mv,
+					games: reader.u32()?,
+					wins: reader.u32()?,
+					draws: reader.u32()?,
+				});
+			}
+			book.positions.insert(hash, moves);
+		}
+		if !reader.bytes.is_empty() {
+			return Err(BookError::TrailingData(reader.bytes.len()));
+		}
+		Ok(book)
+	}
+}
+
+/// Engine playing from the book while the game is in it, and asking another engine afterwards
+pub struct WithBook<E: Engine> {
+	book: OpeningBook,
+	engine: E,
+}
+
+impl<E: Engine> WithBook<E> {
+	pub fn new(book: OpeningBook, engine: E) -> WithBook<E> {
+		WithBook { book, engine }
+	}
+
+	pub fn book(&self) -> &OpeningBook {
+		&self.book
+	}
+
+	pub fn engine_mut(&mut self) -> &mut E {
+		&mut self.engine
+	}
+}
+
+impl<E: Engine> Engine for WithBook<E> {
+	fn best_move(&mut self, state: &GameState) -> Option<Move> {
+		self.book.best_move(state).or_else(|| self.engine.best_move(state))
+	}
+}
+
+fn square_byte(square: Square) -> u8 {
+	square.col << 4 | square.row
+}
+
+fn byte_square(byte: u8) -> Square {
+	Square {
+		col: byte >> 4,
+		row: byte & 0x0f,
+	}
+}
+
+struct Reader<'a> {
+	bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+	fn take(&mut self, len: usize) -> Result<&'a [u8], BookError> {
+		if self.bytes.len() < len {
+			return Err(BookError::Truncated);
+		}
+		let (taken, rest) = self.bytes.split_at(len);
+		self.bytes = rest;
+		Ok(taken)
+	}
+
+	fn u32(&mut self) -> Result<u32, BookError> {
+		Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{AlphaBeta, Limits, MaterialEvaluator};
+
+	const WON_GAME: &str = "[Game \"Brave Otter\"]\n[Seat1 \"alice\"]\n[Seat2 \"bob\"]\n[Date \"2021.09.30\"]\n\
+		[Variant \"standard\"]\n[Result \"1-0\"]\n[Termination \"resignation\"]\n\n1. b6 b2 2. f2 f6 1-0\n";
+
+	fn book(games: usize) -> OpeningBook {
+		let record: GameRecord = WON_GAME.parse().unwrap();
+		let mut book = OpeningBook::new();
+		for _ in 0..games {
+			assert!(book.add_game(&record));
+		}
+		book
+	}
+
+	#[test]
+	fn statistics_from_the_mover_view() {
+		let book = book(3);
+		let mut state = GameState::new();
+		assert_eq!(
+			book.moves(&state),
+			&[BookMove {
+				mv: "b6".parse().unwrap(),
+				games: 3,
+				wins: 3,
+				draws: 0,
+			}]
+		);
+		state.play(&"b6".parse().unwrap()).unwrap();
+		assert_eq!(book.moves(&state)[0].wins, 0);
+		assert_eq!(book.len(), 4);
+
+		let mut running: GameRecord = WON_GAME.parse().unwrap();
+		running.result = None;
+		assert!(!OpeningBook::new().add_game(&running));
+		let mut larger: GameRecord = WON_GAME.parse().unwrap();
+		larger.variant = format!("{} 9x9", STANDARD_VARIANT);
+		assert!(!OpeningBook::new().add_game(&larger));
+	}
+
+	#[test]
+	fn round_trip() {
+		let book = book(2);
+		let bytes = book.to_bytes();
+		assert_eq!(OpeningBook::from_bytes(&bytes), Ok(book));
+		assert_eq!(
+			OpeningBook::from_bytes(&bytes[..bytes.len() - 1]),
+			Err(BookError::Truncated)
+		);
+		assert_eq!(OpeningBook::from_bytes(b"PGN"), Err(BookError::BadMagic));
+	}
+
+	#[test]
+	fn engine_leaves_the_book() {
+		let mut engine = WithBook::new(book(3), AlphaBeta::new(MaterialEvaluator, Limits::depth(1)));
+		let mut state = GameState::new();
+		assert_eq!(engine.best_move(&state), Some("b6".parse().unwrap()));
+		for mv in ["b6", "b2", "f2", "f6"].iter() {
+			state.play(&mv.parse().unwrap()).unwrap();
+		}
+		assert!(engine.book().moves(&state).is_empty());
+		assert!(engine.best_move(&state).is_some());
+	}
+}
@@ -0,0 +1,31 @@
+/// Source of time for the search budget
+///
+/// `std::time::Instant` panics on `wasm32-unknown-unknown`, so the client has to provide its own clock,
+/// typically based on `performance.now()`. Any `Fn() -> f64` returning milliseconds is a clock.
+pub trait Clock {
+	/// Current time in milliseconds, from any fixed origin
+	fn now_ms(&self) -> f64;
+}
+
+impl<F: Fn() -> f64> Clock for F {
+	fn now_ms(&self) -> f64 {
+		self()
+	}
+}
+
+/// Clock of the operating system, only available natively
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clock for SystemClock {
+	fn now_ms(&self) -> f64 {
+		use std::time::{SystemTime, UNIX_EPOCH};
+
+		match SystemTime::now().duration_since(UNIX_EPOCH) {
+			Ok(elapsed) => elapsed.as_secs_f64() * 1000.0,
+			Err(_) => 0.0,
+		}
+	}
+}
@@ -0,0 +1,25 @@
+use ygame_game::GameState;
+
+/// Static evaluation of a position
+pub trait Evaluator {
+	/// Score of the position, from the point of view of the player to move.
+	/// It should stay far below [`WIN_SCORE`](crate::WIN_SCORE) in absolute value.
+	fn evaluate(&self, state: &GameState) -> i32;
+}
+
+impl<F: Fn(&GameState) -> i32> Evaluator for F {
+	fn evaluate(&self, state: &GameState) -> i32 {
+		self(state)
+	}
+}
+
+/// Counts the stones of each player
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaterialEvaluator;
+
+impl Evaluator for MaterialEvaluator {
+	fn evaluate(&self, state: &GameState) -> i32 {
+		let player = state.side_to_move();
+		state.board().count(player) as i32 - state.board().count(player.opponent()) as i32
+	}
+}
@@ -0,0 +1,30 @@
+//! Computer opponents for the game.
+//!
+//! Nothing in here relies on the operating system, so the crate builds natively for the server bots as well as
+//! for wasm32 for the client. The only platform dependent part is the clock used for the time budget, see [`Clock`].
+
+mod analysis;
+mod book;
+mod clock;
+mod eval;
+mod mcts;
+mod rng;
+mod search;
+mod table;
+
+use ygame_game::{GameState, Move};
+
+pub use analysis::{analyse_game, Analysis};
+pub use book::{BookError, BookMove, OpeningBook, WithBook, BOOK_PLIES, MIN_BOOK_GAMES};
+pub use clock::Clock;
+#[cfg(not(target_arch = "wasm32"))]
+pub use clock::SystemClock;
+pub use eval::{Evaluator, MaterialEvaluator};
+pub use mcts::{Mcts, MctsResult, DEFAULT_EXPLORATION};
+pub use search::{AlphaBeta, Limits, SearchResult, DEFAULT_TABLE_SIZE, MAX_DEPTH, WIN_SCORE};
+
+/// Anything able to choose a move, so engines can be swapped and compared
+pub trait Engine {
+	/// The move to play, None when the game is over
+	fn best_move(&mut self, state: &GameState) -> Option<Move>;
+}
@@ -0,0 +1,219 @@
+use ygame_game::{GameState, Move, Player};
+
+use crate::rng::Rng;
+use crate::Engine;
+
+/// Default exploration constant of the UCT formula
+pub const DEFAULT_EXPLORATION: f64 = 1.4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MctsResult {
+	/// None only when the game is over
+	pub best_move: Option<Move>,
+	/// Number of playouts which went through the best move
+	pub visits: u32,
+	/// Share of those playouts won by the player to move, draws counting for half
+	pub win_rate: f64,
+	pub playouts: u32,
+}
+
+/// Monte Carlo tree search, using random playouts instead of an evaluation function
+///
+/// The generator is seeded once, so replaying the same positions with the same seed gives the same moves.
+pub struct Mcts {
+	playouts: u32,
+	exploration: f64,
+	rng: Rng,
+}
+
+struct Node {
+	mv: Option<Move>,
+	parent: Option<usize>,
+	children: Vec<usize>,
+	untried: Vec<Move>,
+	player: Player, // the player who played `mv`
+	visits: u32,
+	wins: f64, // from the point of view of `player`
+}
+
+impl Mcts {
+	pub fn new(playouts: u32, seed: u64) -> Mcts {
+		Mcts {
+			playouts,
+			exploration: DEFAULT_EXPLORATION,
+			rng: Rng::new(seed),
+		}
+	}
+
+	pub fn with_exploration(mut self, exploration: f64) -> Mcts {
+		self.exploration = exploration;
+		self
+	}
+
+	pub fn playouts(&self) -> u32 {
+		self.playouts
+	}
+
+	pub fn set_playouts(&mut self, playouts: u32) {
+		self.playouts = playouts;
+	}
+
+	pub fn search(&mut self, state: &GameState) -> MctsResult {
+		let mut tree = vec![Node {
+			mv: None,
+			parent: None,
+			children: Vec::new(),
+			untried: state.legal_moves(),
+			player: state.side_to_move().opponent(),
+			visits: 0,
+			wins: 0.0,
+		}];
+		if tree[0].untried.is_empty() {
+			return MctsResult {
+				best_move: None,
+				visits: 0,
+				win_rate: 0.0,
+				playouts: 0,
+			};
+		}
+
+		for _ in 0..self.playouts.max(1) {
+			let mut current = state.clone();
+
+			// Selection
+			let mut node = 0;
+			while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+				node = self.select_child(&tree, node);
+				play(&mut current, tree[node].mv);
+			}
+
+			// Expansion
+			if !tree[node].untried.is_empty() {
+				let index = self.rng.below(tree[node].untried.len());
+				let mv = tree[node].untried.swap_remove(index);
+				let player = current.side_to_move();
+				play(&mut current, Some(mv));
+				tree.push(Node {
+					mv: Some(mv),
+					parent: Some(node),
+					children: Vec::new(),
+					untried: current.legal_moves(),
+					player,
+					visits: 0,
+					wins: 0.0,
+				});
+				let child = tree.len() - 1;
+				tree[node].children.push(child);
+				node = child;
+			}
+
+			// Playout
+			let winner = self.playout(&mut current);
+
+			// Backpropagation
+			let mut next = Some(node);
+			while let Some(index) = next {
+				let node = &mut tree[index];
+				node.visits += 1;
+				node.wins += match winner {
+					Some(player) if player == node.player => 1.0,
+					Some(_) => 0.0,
+					None => 0.5,
+				};
+				next = node.parent;
+			}
+		}
+
+		let best = tree[0]
+			.children
+			.iter()
+			.copied()
+			.max_by_key(|child| tree[*child].visits)
+			.expect("The root has been expanded");
+		MctsResult {
+			best_move: tree[best].mv,
+			visits: tree[best].visits,
+			win_rate: tree[best].wins / f64::from(tree[best].visits),
+			playouts: tree[0].visits,
+		}
+	}
+
+	fn select_child(&self, tree: &[Node], node: usize) -> usize {
+		let log_visits = f64::from(tree[node].visits).ln();
+		let uct = |child: usize| {
+			let child = &tree[child];
+			let visits = f64::from(child.visits);
+			child.wins / visits + self.exploration * (log_visits / visits).sqrt()
+		};
+		let mut best = tree[node].children[0];
+		let mut best_value = uct(best);
+		for child in tree[node].children.iter().copied().skip(1) {
+			let value = uct(child);
+			if value > best_value {
+				best = child;
+				best_value = value;
+			}
+		}
+		best
+	}
+
+	// Plays random moves until the end of the game, the rules move limit guarantees it ends
+	fn playout(&mut self, state: &mut GameState) -> Option<Player> {
+		loop {
+			if let Some(result) = state.outcome() {
+				return result.winner;
+			}
+			let moves = state.legal_moves();
+			let mv = moves[self.rng.below(moves.len())];
+			play(state, Some(mv));
+		}
+	}
+}
+
+impl Engine for Mcts {
+	fn best_move(&mut self, state: &GameState) -> Option<Move> {
+		self.search(state).best_move
+	}
+}
+
+fn play(state: &mut GameState, mv: Option<Move>) {
+	if let Some(mv) = mv {
+		state.play(&mv).expect("Legal moves should be playable");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ygame_game::{Board, Cell, Square};
+
+	fn sq(name: &str) -> Square {
+		let bytes = name.as_bytes();
+		Square::new(bytes[0] - b'a', bytes[1] - b'1')
+	}
+
+	#[test]
+	fn same_seed_same_result() {
+		let state = GameState::new();
+		let first = Mcts::new(50, 42).search(&state);
+		let second = Mcts::new(50, 42).search(&state);
+		assert_eq!(first, second);
+		assert_eq!(first.playouts, 50);
+		assert!(state
+			.legal_moves()
+			.contains(&first.best_move.expect("A move should be found")));
+	}
+
+	#[test]
+	fn takes_the_winning_capture() {
+		let mut board = Board::empty(5);
+		board.set(sq("a1"), Cell::Stone(Player::One));
+		board.set(sq("b1"), Cell::Stone(Player::Two));
+		board.set(sq("c3"), Cell::Stone(Player::Two));
+		// Both b2 and a1c2 capture the last stones of the opponent
+		let mut state = GameState::from_board(board, Player::One);
+		let result = Mcts::new(2000, 7).search(&state);
+		state.play(&result.best_move.expect("A move should be found")).unwrap();
+		assert_eq!(state.outcome().and_then(|result| result.winner), Some(Player::One));
+	}
+}
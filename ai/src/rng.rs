@@ -0,0 +1,26 @@
+/// Small deterministic random generator (SplitMix64)
+///
+/// Good enough for playouts, and unlike `rand` it needs no entropy source, which keeps the crate wasm friendly.
+#[derive(Debug, Clone)]
+pub struct Rng {
+	state: u64,
+}
+
+impl Rng {
+	pub fn new(seed: u64) -> Rng {
+		Rng { state: seed }
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^ (z >> 31)
+	}
+
+	/// Random number in `0..bound`, `bound` must not be 0
+	pub fn below(&mut self, bound: usize) -> usize {
+		(self.next_u64() % bound as u64) as usize
+	}
+}
@@ -0,0 +1,405 @@
+use std::cmp::Reverse;
+use std::time::Duration;
+
+use ygame_game::{Cell, GameResult, GameState, Move};
+
+use crate::table::{Bound, Entry, Table};
+use crate::{Analysis, Clock, Engine, Evaluator};
+
+/// Score of a won position, reduced by the number of plies needed to win
+pub const WIN_SCORE: i32 = 1_000_000;
+
+/// Depth used when the search is only limited by time
+pub const MAX_DEPTH: u32 = 64;
+
+const INFINITY: i32 = WIN_SCORE + 1;
+
+/// Default number of entries of the transposition table
+pub const DEFAULT_TABLE_SIZE: usize = 1 << 16;
+
+// Scores beyond this one are wins or losses in a known number of plies
+pub(crate) const WIN_THRESHOLD: i32 = WIN_SCORE - MAX_DEPTH as i32;
+
+// The clock is only read every so many nodes (a power of two), it may be slow (specially from wasm)
+const CLOCK_CHECK_INTERVAL: u64 = 1024;
+
+/// Budget of a search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+	pub max_depth: u32,
+	pub max_time: Option<Duration>,
+}
+
+impl Limits {
+	pub fn depth(max_depth: u32) -> Limits {
+		Limits {
+			max_depth,
+			max_time: None,
+		}
+	}
+
+	pub fn time(max_time: Duration) -> Limits {
+		Limits {
+			max_depth: MAX_DEPTH,
+			max_time: Some(max_time),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+	/// None only when the game is over
+	pub best_move: Option<Move>,
+	/// From the point of view of the player to move
+	pub score: i32,
+	/// Last fully searched depth
+	pub depth: u32,
+	pub nodes: u64,
+}
+
+/// Iterative deepening alpha-beta (negamax) search
+///
+/// The search always completes depth 1, so a move is found even with a tiny time budget.
+/// Without any clock, the time budget is ignored and only the depth limits the search.
+/// The transposition table is kept between searches, call `clear` when starting a new game.
+pub struct AlphaBeta<E: Evaluator> {
+	evaluator: E,
+	limits: Limits,
+	clock: Option<Box<dyn Clock>>,
+	table: Table,
+}
+
+struct Context {
+	nodes: u64,
+	deadline: Option<f64>,
+	aborted: bool,
+	can_abort: bool,
+	root_ply: u32,
+}
+
+impl<E: Evaluator> AlphaBeta<E> {
+	pub fn new(evaluator: E, limits: Limits) -> AlphaBeta<E> {
+		AlphaBeta {
+			evaluator,
+			limits,
+			#[cfg(not(target_arch = "wasm32"))]
+			clock: Some(Box::new(crate::SystemClock)),
+			#[cfg(target_arch = "wasm32")]
+			clock: None,
+			table: Table::new(DEFAULT_TABLE_SIZE),
+		}
+	}
+
+	pub fn with_table_size(mut self, size: usize) -> AlphaBeta<E> {
+		self.table = Table::new(size);
+		self
+	}
+
+	/// Forget every position searched so far
+	pub fn clear(&mut self) {
+		self.table.clear();
+	}
+
+	pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> AlphaBeta<E> {
+		self.clock = Some(Box::new(clock));
+		self
+	}
+
+	pub fn limits(&self) -> Limits {
+		self.limits
+	}
+
+	pub fn set_limits(&mut self, limits: Limits) {
+		self.limits = limits;
+	}
+
+	pub fn search(&mut self, state: &GameState) -> SearchResult {
+		let mut state = state.clone();
+		let mut ctx = Context {
+			nodes: 0,
+			deadline: match (&self.clock, self.limits.max_time) {
+				(Some(clock), Some(max_time)) => Some(clock.now_ms() + max_time.as_secs_f64() * 1000.0),
+				_ => None,
+			},
+			aborted: false,
+			can_abort: false,
+			root_ply: state.ply(),
+		};
+		let mut result = SearchResult {
+			best_move: None,
+			score: self.evaluator.evaluate(&state),
+			depth: 0,
+			nodes: 0,
+		};
+		if state.outcome().is_some() {
+			return result;
+		}
+
+		let mut moves = ordered_moves(&state);
+		for depth in 1..=self.limits.max_depth.max(1) {
+			// The first depth is never aborted, to always have a move to play
+			ctx.can_abort = depth > 1;
+			let mut alpha = -INFINITY;
+			let mut best = 0;
+			for (i, mv) in moves.iter().enumerate() {
+				let undo = state.play(mv).expect("Legal moves should be playable");
+				let score = self.negamax(&mut ctx, &mut state, depth - 1, -INFINITY, -alpha);
+				state.undo(undo);
+				if ctx.aborted {
+					break;
+				}
+				if -score > alpha {
+					alpha = -score;
+					best = i;
+				}
+			}
+			if ctx.aborted {
+				break;
+			}
+
+			// The best move is tried first on the next iteration
+			moves[..=best].rotate_right(1);
+			result.best_move = Some(moves[0]);
+			result.score = alpha;
+			result.depth = depth;
+			if alpha.abs() >= WIN_THRESHOLD {
+				break; // The game is solved, no need to go deeper
+			}
+		}
+		result.nodes = ctx.nodes;
+		result
+	}
+
+	/// Search the position, then follow the best moves stored in the table to get the expected continuation
+	pub fn analyse(&mut self, state: &GameState) -> Analysis {
+		let result = self.search(state);
+		let mut variation = Vec::new();
+		let mut line = state.clone();
+		let mut next = result.best_move;
+		// The variation is not longer than the search, which also stops any cycle
+		while let Some(mv) = next {
+			if variation.len() as u32 >= result.depth || line.play(&mv).is_err() {
+				break;
+			}
+			variation.push(mv);
+			next = self.table.probe(line.hash()).and_then(|entry| entry.best_move);
+		}
+		Analysis {
+			best_move: result.best_move,
+			score: result.score,
+			depth: result.depth,
+			principal_variation: variation,
+		}
+	}
+
+	// Score of the position for the player to move. When aborted, the returned value is meaningless.
+	fn negamax(&mut self, ctx: &mut Context, state: &mut GameState, depth: u32, mut alpha: i32, mut beta: i32) -> i32 {
+		ctx.nodes += 1;
+		if ctx.can_abort && ctx.nodes & (CLOCK_CHECK_INTERVAL - 1) == 0 {
+			self.check_time(ctx);
+		}
+		if ctx.aborted {
+			return 0;
+		}
+		if let Some(result) = state.outcome() {
+			return terminal_score(state, ctx.root_ply, &result);
+		}
+		if depth == 0 {
+			return self.evaluator.evaluate(state);
+		}
+
+		// The move counters are not part of the hash, so a stored score may ignore an upcoming move limit draw
+		let hash = state.hash();
+		let height = state.ply() - ctx.root_ply;
+		let mut moves = ordered_moves(state);
+		if let Some(entry) = self.table.probe(hash) {
+			if entry.depth >= depth {
+				let score = score_from_table(entry.score, height);
+				match entry.bound {
+					Bound::Exact => return score,
+					Bound::Lower => alpha = alpha.max(score),
+					Bound::Upper => beta = beta.min(score),
+				}
+				if alpha >= beta {
+					return score;
+				}
+			}
+			if let Some(position) = entry.best_move.and_then(|mv| moves.iter().position(|m| *m == mv)) {
+				moves[..=position].rotate_right(1);
+			}
+		}
+
+		let original_alpha = alpha;
+		let mut best = -INFINITY;
+		let mut best_move = None;
+		for mv in moves {
+			let undo = state.play(&mv).expect("Legal moves should be playable");
+			let score = -self.negamax(ctx, state, depth - 1, -beta, -alpha);
+			state.undo(undo);
+			if ctx.aborted {
+				return 0;
+			}
+			if score > best {
+				best = score;
+				best_move = Some(mv);
+			}
+			if best > alpha {
+				alpha = best;
+			}
+			if alpha >= beta {
+				break;
+			}
+		}
+
+		let bound = if best <= original_alpha {
+			Bound::Upper
+		} else if best >= beta {
+			Bound::Lower
+		} else {
+			Bound::Exact
+		};
+		self.table.store(Entry {
+			hash,
+			depth,
+			score: score_to_table(best, height),
+			bound,
+			best_move,
+		});
+		best
+	}
+
+	fn check_time(&self, ctx: &mut Context) {
+		if let (Some(clock), Some(deadline)) = (&self.clock, ctx.deadline) {
+			if clock.now_ms() >= deadline {
+				ctx.aborted = true;
+			}
+		}
+	}
+}
+
+impl<E: Evaluator> Engine for AlphaBeta<E> {
+	fn best_move(&mut self, state: &GameState) -> Option<Move> {
+		self.search(state).best_move
+	}
+}
+
+// Win scores depend on the distance from the root, the table stores them relative to the position instead
+fn score_to_table(score: i32, height: u32) -> i32 {
+	if score >= WIN_THRESHOLD {
+		score + height as i32
+	} else if score <= -WIN_THRESHOLD {
+		score - height as i32
+	} else {
+		score
+	}
+}
+
+fn score_from_table(score: i32, height: u32) -> i32 {
+	if score >= WIN_THRESHOLD {
+		score - height as i32
+	} else if score <= -WIN_THRESHOLD {
+		score + height as i32
+	} else {
+		score
+	}
+}
+
+fn terminal_score(state: &GameState, root_ply: u32, result: &GameResult) -> i32 {
+	// Quicker wins and slower losses are preferred
+	let distance = (state.ply() - root_ply) as i32;
+	match result.winner {
+		None => 0,
+		Some(winner) if winner == state.side_to_move() => WIN_SCORE - distance,
+		Some(_) => -WIN_SCORE + distance,
+	}
+}
+
+// Legal moves, the most promising first: captures, then grow moves before jumps
+fn ordered_moves(state: &GameState) -> Vec<Move> {
+	let board = state.board();
+	let opponent = Cell::Stone(state.side_to_move().opponent());
+	let mut moves: Vec<(i32, Move)> = state
+		.legal_moves()
+		.into_iter()
+		.map(|mv| {
+			let captures = match mv.to() {
+				Some(to) => board
+					.ring(to, 1)
+					.filter(|square| board.get(*square) == Some(opponent))
+					.count() as i32,
+				None => 0,
+			};
+			let grow = if let Move::Grow { .. } = mv { 1 } else { 0 };
+			(captures * 2 + grow, mv)
+		})
+		.collect();
+	moves.sort_by_key(|(priority, _)| Reverse(*priority));
+	moves.into_iter().map(|(_, mv)| mv).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::MaterialEvaluator;
+	use ygame_game::{Board, Player, Square};
+
+	fn sq(name: &str) -> Square {
+		let bytes = name.as_bytes();
+		Square::new(bytes[0] - b'a', bytes[1] - b'1')
+	}
+
+	#[test]
+	fn finds_a_move_in_the_initial_position() {
+		let mut ai = AlphaBeta::new(MaterialEvaluator, Limits::depth(3));
+		let state = GameState::new();
+		let result = ai.search(&state);
+		assert_eq!(result.depth, 3);
+		let mv = result.best_move.expect("A move should be found");
+		assert!(state.legal_moves().contains(&mv));
+	}
+
+	#[test]
+	fn takes_the_winning_capture() {
+		// Growing to b2 captures the last stones of the opponent
+		let mut board = Board::empty(5);
+		board.set(sq("a1"), Cell::Stone(Player::One));
+		board.set(sq("b1"), Cell::Stone(Player::Two));
+		board.set(sq("c3"), Cell::Stone(Player::Two));
+		let state = GameState::from_board(board, Player::One);
+		let mut ai = AlphaBeta::new(MaterialEvaluator, Limits::depth(4));
+		let result = ai.search(&state);
+		assert_eq!(result.best_move, Some(Move::Grow { to: sq("b2") }));
+		assert_eq!(result.score, WIN_SCORE - 1);
+	}
+
+	#[test]
+	fn table_does_not_change_the_score() {
+		let mut state = GameState::new();
+		state.play(&Move::Grow { to: sq("b6") }).unwrap();
+		let mut with_table = AlphaBeta::new(MaterialEvaluator, Limits::depth(4));
+		let mut without_table = AlphaBeta::new(MaterialEvaluator, Limits::depth(4)).with_table_size(1);
+		let first = with_table.search(&state);
+		assert_eq!(first.score, without_table.search(&state).score);
+		// The second search mostly reads the table
+		let second = with_table.search(&state);
+		assert_eq!(second.score, first.score);
+		assert!(second.nodes < first.nodes);
+	}
+
+	#[test]
+	fn time_budget_still_gives_a_move() {
+		let mut ai = AlphaBeta::new(MaterialEvaluator, Limits::time(Duration::from_millis(0)));
+		let result = ai.search(&GameState::new());
+		assert!(result.best_move.is_some());
+		assert!(result.depth >= 1);
+	}
+
+	#[test]
+	fn no_move_when_the_game_is_over() {
+		let mut board = Board::empty(5);
+		board.set(sq("a1"), Cell::Stone(Player::One));
+		let state = GameState::from_board(board, Player::Two);
+		let result = AlphaBeta::new(MaterialEvaluator, Limits::depth(2)).search(&state);
+		assert_eq!(result.best_move, None);
+	}
+}
@@ -0,0 +1,54 @@
+use ygame_game::Move;
+
+/// Meaning of a stored score, depending on the alpha-beta window it was found with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+	Exact,
+	Lower, // the real score is at least this one (beta cut-off)
+	Upper, // the real score is at most this one (no move raised alpha)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+	pub hash: u64,
+	pub depth: u32,
+	pub score: i32,
+	pub bound: Bound,
+	pub best_move: Option<Move>,
+}
+
+/// Transposition table: fixed number of slots indexed by the position hash, newer entries replace older ones
+pub struct Table {
+	slots: Vec<Option<Entry>>,
+}
+
+impl Table {
+	/// `size` is rounded up to a power of two
+	pub fn new(size: usize) -> Table {
+		Table {
+			slots: vec![None; size.max(1).next_power_of_two()],
+		}
+	}
+
+	pub fn probe(&self, hash: u64) -> Option<&Entry> {
+		match &self.slots[self.index(hash)] {
+			Some(entry) if entry.hash == hash => Some(entry),
+			_ => None,
+		}
+	}
+
+	pub fn store(&mut self, entry: Entry) {
+		let index = self.index(entry.hash);
+		self.slots[index] = Some(entry);
+	}
+
+	pub fn clear(&mut self) {
+		for slot in self.slots.iter_mut() {
+			*slot = None;
+		}
+	}
+
+	fn index(&self, hash: u64) -> usize {
+		(hash & (self.slots.len() as u64 - 1)) as usize
+	}
+}
@@ -1,12 +1,16 @@
 pub mod game;
 pub mod lobby;
 pub mod login;
+pub mod puzzle;
 pub mod state;
 
+use crate::app::game::local::LocalGame;
+use crate::app::game::net::NetGame;
 use crate::app::game::GameSection;
 use crate::app::lobby::form::LobbyForm;
 use crate::app::login::form::LoginForm;
 use crate::app::login::LoginInfo;
+use crate::app::puzzle::PuzzleSection;
 use crate::app::state::AppState;
 use crate::app::state::LoginState;
 use crate::conn;
@@ -14,9 +18,15 @@ use crate::conn::loader;
 use crate::conn::Connection;
 use crate::ep::AppEntryPoint;
 use crate::ep::EpRef;
+use crate::err;
 use crate::utils::browser;
 use crate::utils::console;
+use crate::utils::dom;
 use crate::utils::history;
+use ygame_ai as ai;
+use ygame_game as rules;
+use ygame_game::puzzle::Attempt;
+use ygame_protocol::handshake::Capability;
 
 use std::collections::BTreeMap;
 
@@ -26,6 +36,11 @@ use web_sys::ErrorEvent;
 use web_sys::Window;
 use web_sys::{Document, PopStateEvent};
 
+// Depth of the search for hints, the client has no clock to limit it by time
+const HINT_DEPTH: u32 = 4;
+// Built by `cargo make book` in the static files
+const BOOK_FILE: &str = "book.ybk";
+
 /// This object represent the main application state
 ///
 /// It manage the html, the history and the current game
@@ -33,6 +48,8 @@ pub struct Application {
 	entry_point: EpRef,
 	login_form: LoginForm,
 	lobby_form: LobbyForm,
+	game_section: GameSection,
+	puzzle_section: PuzzleSection,
 	subfolder: Option<String>,
 	login: LoginInfo,
 	current_state: AppState,
@@ -40,6 +57,12 @@ pub struct Application {
 	rolling_back_history: bool,
 	is_fully_connected: bool,
 	lobby_games: BTreeMap<String, lobby::GameOverview>,
+	local_game: Option<LocalGame>,
+	net_game: Option<NetGame>,
+	puzzles: Vec<rules::puzzle::Puzzle>,
+	puzzle_attempt: Option<Attempt>,
+	book: Option<ai::OpeningBook>,
+	book_request: Option<browser::BytesRequest>,
 	on_back_cb: Closure<dyn FnMut(PopStateEvent)>,
 }
 
@@ -79,10 +102,25 @@ impl Application {
 			false
 		};
 
+		let book_ep = entry_point.clone();
+		let book_url = browser::to_full_url(
+			document,
+			&format!("{}/{}", subfolder.as_deref().unwrap_or(""), BOOK_FILE),
+		);
+		let book_request = browser::fetch_bytes(&book_url, move |bytes| {
+			book_ep.on_book_loaded(bytes);
+		})
+		.map_err(|e| {
+			console::error!("Opening book not loaded: {}", e);
+		})
+		.ok();
+
 		Application {
 			entry_point: entry_point.clone(),
 			login_form: LoginForm::new(entry_point.clone(), &document),
 			lobby_form: LobbyForm::new(entry_point.clone(), &document),
+			game_section: GameSection::new(entry_point.clone(), &document),
+			puzzle_section: PuzzleSection::new(entry_point.clone(), &document),
 			subfolder,
 			current_state: AppState::Loading,
 			desired_state,
@@ -90,6 +128,12 @@ impl Application {
 			login: LoginInfo::load(&window),
 			is_fully_connected: false,
 			lobby_games: BTreeMap::new(),
+			local_game: None,
+			net_game: None,
+			puzzles: rules::puzzle::bundled(),
+			puzzle_attempt: None,
+			book: None,
+			book_request,
 			on_back_cb: closure,
 		}
 	}
@@ -149,8 +193,15 @@ impl Application {
 		self.login.clone()
 	}
 
-	pub fn create_game(&mut self) {
-		//self.conn.create_game()
+	pub fn create_game(&mut self, conn: &Connection) {
+		let window = web_sys::window().expect("no global `window` exists");
+		let document = window.document().expect("window has not document");
+		let config = LobbyForm::read_config(&document);
+		if config.time_control.is_some() && !conn.has_capability(Capability::Clocks) {
+			err::render_error_text("Le serveur ne gère pas les parties à la pendule");
+			return;
+		}
+		conn.create_game(config, LobbyForm::read_position(&document));
 	}
 
 	pub fn on_lobby_game_list(&mut self, game_list: Vec<lobby::GameOverview>) {
@@ -168,6 +219,12 @@ impl Application {
 		self.lobby_games = new_map;
 	}
 
+	pub fn on_rule_sets(&mut self, list: Vec<rules::RuleSet>) {
+		let window = web_sys::window().expect("no global `window` exists");
+		let document = window.document().expect("window has not document");
+		LobbyForm::show_rule_sets(&document, &list);
+	}
+
 	pub fn on_lobby_new_game(&mut self, info: lobby::GameOverview) {
 		if self.current_state == AppState::Lobby {
 			let window = web_sys::window().expect("no global `window` exists");
@@ -196,12 +253,280 @@ impl Application {
 		self.lobby_games.remove(&game_id);
 	}
 
+	pub fn send_game_action(&mut self, action: rules::Action, conn: &Connection) {
+		if let AppState::InNetGame { game_uid } = &self.current_state {
+			conn.send_game_action(game_uid, action);
+		}
+	}
+
+	pub fn play_move(&mut self, text: String, conn: &Connection) {
+		let window = web_sys::window().expect("no global `window` exists");
+		let document = window.document().expect("window has not document");
+		let mv: rules::Move = match text.trim().parse() {
+			Ok(mv) => mv,
+			Err(_) => {
+				let error = format!("Coup {} invalide", text.trim());
+				match self.current_state {
+					AppState::Puzzle { .. } => PuzzleSection::show_error(&document, &error),
+					_ => GameSection::show_move_error(&document, &error),
+				}
+				return;
+			}
+		};
+		match &self.current_state {
+			AppState::InNetGame { game_uid } => conn.send_game_action(game_uid, rules::Action::Move(mv)),
+			AppState::InLocalGame => {
+				if let Some(game) = &mut self.local_game {
+					match game.play(mv) {
+						Ok(()) => GameSection::show_position(&document, game.state(), game.outcome().as_ref()),
+						Err(_) => GameSection::show_move_error(&document, &format!("Coup {} impossible", mv)),
+					}
+				}
+			}
+			AppState::Puzzle { .. } => {
+				if let Some(attempt) = &mut self.puzzle_attempt {
+					match attempt.play(mv) {
+						Ok(step) => {
+							PuzzleSection::show_attempt(&document, attempt);
+							if let rules::puzzle::Step::Reply(reply) = step {
+								PuzzleSection::show_reply(&document, &reply);
+							}
+						}
+						Err(e) => PuzzleSection::show_wrong_move(&document, &e),
+					}
+				}
+			}
+			_ => (),
+		}
+	}
+
+	pub fn restart_puzzle(&mut self) {
+		let window = web_sys::window().expect("no global `window` exists");
+		let document = window.document().expect("window has not document");
+		if let Some(attempt) = &mut self.puzzle_attempt {
+			*attempt = Attempt::new(attempt.puzzle().clone());
+			PuzzleSection::show_attempt(&document, attempt);
+		}
+	}
+
+	/// Go to the puzzle after the current one, or to the first one from the other screens
+	pub fn next_puzzle(&mut self, conn: &Connection) {
+		let next = match &self.current_state {
+			AppState::Puzzle { puzzle_id } => self
+				.puzzles
+				.iter()
+				.position(|puzzle| &puzzle.id == puzzle_id)
+				.map_or(0, |index| (index + 1) % self.puzzles.len()),
+			_ => 0,
+		};
+		if let Some(puzzle) = self.puzzles.get(next) {
+			let puzzle_id = puzzle.id.clone();
+			self.set_state(AppState::Puzzle { puzzle_id }, conn);
+		}
+	}
+
+	fn start_puzzle(&mut self, document: &Document, puzzle_id: &str) {
+		self.puzzle_attempt = self
+			.puzzles
+			.iter()
+			.find(|puzzle| puzzle.id == puzzle_id)
+			.map(|puzzle| Attempt::new(puzzle.clone()));
+		match &self.puzzle_attempt {
+			Some(attempt) => PuzzleSection::show_attempt(document, attempt),
+			None => {
+				dom::set_inner_text_by_id(document, "puzzle_board_view", "");
+				PuzzleSection::show_error(document, &format!("Le problème {} n'existe pas", puzzle_id));
+			}
+		}
+	}
+
+	// Untimed game between two players, from the position given with the name of the opponent if any
+	fn start_local_game(&mut self, document: &Document) {
+		let text = dom::read_field_by_id(document, "local_position_field");
+		let game = match text.trim() {
+			"" => LocalGame::new(local_config(None), None).map_err(|e| e.to_string()),
+			text => rules::notation::parse_position(text)
+				.map_err(|e| e.to_string())
+				.and_then(|state| LocalGame::new(local_config(Some(&state)), Some(state)).map_err(|e| e.to_string())),
+		};
+		let (game, error) = match game {
+			Ok(game) => (game, None),
+			Err(e) => {
+				let game = LocalGame::new(local_config(None), None).expect("default configuration is valid");
+				(game, Some(e))
+			}
+		};
+		GameSection::show_position(document, game.state(), game.outcome().as_ref());
+		if let Some(e) = error {
+			console::error!("Invalid starting position: {}", e);
+			GameSection::show_move_error(document, "Position de départ refusée, la partie commence normalement");
+		}
+		self.local_game = Some(game);
+	}
+
+	pub fn download_game_record(&self, conn: &Connection) {
+		if let AppState::InNetGame { game_uid } = &self.current_state {
+			conn.ask_game_record(game_uid);
+		}
+	}
+
+	pub fn on_game_record(&mut self, game_id: String, record: String) {
+		let window = web_sys::window().expect("no global `window` exists");
+		let document = window.document().expect("window has not document");
+		let name = match self.lobby_games.get(&game_id) {
+			Some(game) => game.name.clone(),
+			None => game_id,
+		};
+		if let Err(e) = browser::download_text(&document, &format!("{}.ygn", name), &record) {
+			err::render_error(&e);
+		}
+	}
+
+	pub fn ask_hint(&self, conn: &Connection) {
+		let window = web_sys::window().expect("no global `window` exists");
+		let document = window.document().expect("window has not document");
+		match &self.current_state {
+			AppState::InLocalGame => {
+				if let Some(game) = &self.local_game {
+					let mut engine = ai::AlphaBeta::new(ai::MaterialEvaluator, ai::Limits::depth(HINT_DEPTH));
+					let book_moves = self.book.as_ref().map(|book| book.moves(game.state())).unwrap_or(&[]);
+					GameSection::show_hint(&document, &engine.analyse(game.state()), book_moves);
+				}
+			}
+			// The server refuses it to the players of a game in progress
+			AppState::InNetGame { game_uid } => conn.ask_game_analysis(game_uid),
+			_ => (),
+		}
+	}
+
+	pub fn on_book_loaded(&mut self, bytes: Vec<u8>) {
+		match ai::OpeningBook::from_bytes(&bytes) {
+			Ok(book) => {
+				console::log!("Opening book loaded: {} positions", book.len());
+				self.book = Some(book);
+			}
+			Err(e) => {
+				console::error!("Invalid opening book: {}", e);
+			}
+		}
+	}
+
+	pub fn on_game_analysis(&mut self, game_id: String, plies: Vec<(rules::Move, ai::Analysis)>) {
+		if self.current_state == (AppState::InNetGame { game_uid: game_id }) {
+			let window = web_sys::window().expect("no global `window` exists");
+			let document = window.document().expect("window has not document");
+			GameSection::show_game_analysis(&document, &plies);
+		}
+	}
+
+	pub fn on_draw_offered(&mut self, game_id: String, role: game::UserRole) {
+		if self.current_state == (AppState::InNetGame { game_uid: game_id }) {
+			let window = web_sys::window().expect("no global `window` exists");
+			let document = window.document().expect("window has not document");
+			GameSection::show_draw_offer(&document, &role);
+		}
+	}
+
+	pub fn on_draw_declined(&mut self, game_id: String) {
+		if self.current_state == (AppState::InNetGame { game_uid: game_id }) {
+			let window = web_sys::window().expect("no global `window` exists");
+			let document = window.document().expect("window has not document");
+			GameSection::hide_draw_offer(&document);
+		}
+	}
+
+	pub fn on_takeback_requested(&mut self, game_id: String, role: game::UserRole) {
+		if self.current_state == (AppState::InNetGame { game_uid: game_id }) {
+			let window = web_sys::window().expect("no global `window` exists");
+			let document = window.document().expect("window has not document");
+			GameSection::show_takeback_request(&document, &role);
+		}
+	}
+
+	pub fn on_takeback_declined(&mut self, game_id: String) {
+		if self.current_state == (AppState::InNetGame { game_uid: game_id }) {
+			let window = web_sys::window().expect("no global `window` exists");
+			let document = window.document().expect("window has not document");
+			GameSection::hide_takeback_request(&document);
+		}
+	}
+
+	pub fn on_rollback(&mut self, game_id: String, plies: u32) {
+		if self.current_state
+			== (AppState::InNetGame {
+				game_uid: game_id.clone(),
+			}) {
+			console::log!("{} plies taken back", plies);
+			let window = web_sys::window().expect("no global `window` exists");
+			let document = window.document().expect("window has not document");
+			GameSection::hide_takeback_request(&document);
+			if let Some(game) = self.shown_net_game(&game_id) {
+				if let Err(e) = game.rollback(plies) {
+					console::error!("Unable to replay the moves of game {}: {}", game_id, e);
+				}
+				self.show_net_game(&document);
+			}
+		}
+	}
+
+	pub fn on_game_started(&mut self, game_id: String) {
+		if self.shown_net_game(&game_id).is_some() {
+			let window = web_sys::window().expect("no global `window` exists");
+			let document = window.document().expect("window has not document");
+			self.show_net_game(&document);
+		}
+	}
+
+	pub fn on_move_played(&mut self, game_id: String, mv: rules::Move) {
+		if let Some(game) = self.shown_net_game(&game_id) {
+			if let Err(e) = game.play(mv) {
+				console::error!("Move {} of game {} refused: {}", mv, game_id, e);
+			}
+			let window = web_sys::window().expect("no global `window` exists");
+			let document = window.document().expect("window has not document");
+			self.show_net_game(&document);
+		}
+	}
+
+	pub fn on_game_over(&mut self, game_id: String, result: rules::GameResult) {
+		if let Some(game) = self.shown_net_game(&game_id) {
+			game.finish(result);
+			let window = web_sys::window().expect("no global `window` exists");
+			let document = window.document().expect("window has not document");
+			self.show_net_game(&document);
+		}
+	}
+
+	pub fn on_clocks(&mut self, game_id: String, seat_1_ms: u64, seat_2_ms: u64, running: Option<game::UserRole>) {
+		if self.current_state == (AppState::InNetGame { game_uid: game_id }) {
+			let window = web_sys::window().expect("no global `window` exists");
+			let document = window.document().expect("window has not document");
+			GameSection::show_clocks(&document, seat_1_ms, seat_2_ms, running);
+		}
+	}
+
 	pub fn on_game_created(&mut self, info: game::GameDetails, role: game::UserRole) {
-		unimplemented!()
+		self.on_game_joined(info, role, Vec::new());
 	}
 
+	/// Show the game at the position reached after the moves already played
 	pub fn on_game_joined(&mut self, info: game::GameDetails, role: game::UserRole, moves: Vec<game::GameAction>) {
-		unimplemented!()
+		console::log!("Joined game {} as {:?}", info.id, role);
+		let mut net_game = match NetGame::new(&info) {
+			Ok(net_game) => net_game,
+			Err(e) => {
+				err::render_error_text(&format!("Impossible d'afficher la partie {} : {}", info.name, e));
+				return;
+			}
+		};
+		for action in &moves {
+			if let Err(e) = net_game.replay(action) {
+				console::error!("Unable to replay the moves of game {}: {}", info.id, e);
+				break;
+			}
+		}
+		self.net_game = Some(net_game);
+		self.enter_net_game(info.id);
 	}
 
 	pub fn on_connected(&mut self) {
@@ -215,7 +540,10 @@ impl Application {
 			}
 			AppState::InNetGame { game_uid } => {
 				loader::hide(&document);
-				unimplemented!("coucou")
+				// Until the game is joined, the server has not sent its moves
+				if self.net_game.as_ref().map_or(false, |game| game.id() == game_uid) {
+					self.show_net_game(&document);
+				}
 			}
 			_ => (),
 		}
@@ -273,34 +601,72 @@ impl Application {
 		self.on_enter_state(&old_state, &next_state, conn);
 	}
 
+	// The game is entered from the lobby, already connected: only the shown sections change
+	fn enter_net_game(&mut self, game_uid: String) {
+		let window = web_sys::window().expect("no global `window` exists");
+		let document = window.document().expect("no `document` in window");
+		let next_state = AppState::InNetGame { game_uid };
+		if next_state != self.current_state {
+			console::log!("going to state {:?}", next_state);
+			self.push_state(next_state.clone());
+			let old_state = std::mem::replace(&mut self.current_state, next_state);
+			Application::hide_state(&document, &old_state, &self.current_state);
+			GameSection::show(&document);
+		}
+		self.show_net_game(&document);
+	}
+
+	// The game of a message, when it is the one shown
+	fn shown_net_game(&mut self, game_id: &str) -> Option<&mut NetGame> {
+		match &self.current_state {
+			AppState::InNetGame { game_uid } if game_uid == game_id => {
+				self.net_game.as_mut().filter(|game| game.id() == game_id)
+			}
+			_ => None,
+		}
+	}
+
+	fn show_net_game(&self, document: &Document) {
+		if let Some(game) = &self.net_game {
+			GameSection::show_position(document, game.state(), game.result());
+		}
+	}
+
 	fn on_leave_state(&mut self, old_state: &AppState, next_state: &AppState, conn: &Connection) {
 		let window = web_sys::window().expect("no global `window` exists");
 		let document = window.document().expect("no `document` in window");
+		Application::hide_state(&document, old_state, next_state);
+		match next_state {
+			AppState::Loading | AppState::Login(..) | AppState::InLocalGame | AppState::Puzzle { .. } => {
+				self.disconnect(conn);
+			}
+			_ => {}
+		}
+	}
+
+	fn hide_state(document: &Document, old_state: &AppState, next_state: &AppState) {
 		match old_state {
 			AppState::Loading => {
-				conn::loader::hide(&document);
+				conn::loader::hide(document);
 			}
 			AppState::Login(..) => match next_state {
 				AppState::Login(..) => {}
 				_ => {
-					LoginForm::hide(&document);
+					LoginForm::hide(document);
 				}
 			},
 			AppState::ConnectingToLobby | AppState::ConnectingToGame { .. } => {
-				conn::loader::hide(&document);
+				conn::loader::hide(document);
 			}
 			AppState::Lobby => {
-				LobbyForm::hide(&document);
+				LobbyForm::hide(document);
 			}
 			AppState::InNetGame { .. } | AppState::InLocalGame => {
-				GameSection::hide(&document);
+				GameSection::hide(document);
 			}
-		}
-		match next_state {
-			AppState::Loading | AppState::Login(..) | AppState::InLocalGame => {
-				self.disconnect(conn);
+			AppState::Puzzle { .. } => {
+				PuzzleSection::hide(document);
 			}
-			_ => {}
 		}
 	}
 
@@ -309,7 +675,7 @@ impl Application {
 		let document = window.document().expect("no `document` in window");
 
 		match next_state {
-			AppState::Loading | AppState::Login(..) | AppState::InLocalGame => {}
+			AppState::Loading | AppState::Login(..) | AppState::InLocalGame | AppState::Puzzle { .. } => {}
 			_ => {
 				self.connect_to_server(conn, &document);
 			}
@@ -329,7 +695,14 @@ impl Application {
 				self.lobby_form.show(&document);
 			}
 			AppState::InNetGame { .. } => GameSection::show(&document),
-			AppState::InLocalGame => GameSection::show(&document),
+			AppState::InLocalGame => {
+				self.start_local_game(&document);
+				GameSection::show(&document);
+			}
+			AppState::Puzzle { puzzle_id } => {
+				self.start_puzzle(&document, puzzle_id);
+				PuzzleSection::show(&document);
+			}
 		}
 	}
 
@@ -357,3 +730,19 @@ impl Application {
 		history.back();
 	}
 }
+
+// Local games use the default options without clocks, a custom position sets the board size and the first player
+fn local_config(position: Option<&rules::GameState>) -> rules::GameConfig {
+	let config = rules::GameConfig {
+		time_control: None,
+		..rules::GameConfig::default()
+	};
+	match position {
+		Some(state) => rules::GameConfig {
+			board_size: state.board().size(),
+			first_player: state.side_to_move(),
+			..config
+		},
+		None => config,
+	}
+}
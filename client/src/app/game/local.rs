@@ -0,0 +1,66 @@
+use ygame_game as rules;
+use ygame_game::Rules;
+
+/// Game played by two players on the same device, without the server
+///
+/// Only the built-in rule sets are available, plugins are only loaded by the server.
+#[derive(Debug, Clone)]
+pub struct LocalGame {
+	config: rules::GameConfig,
+	rules: &'static dyn Rules,
+	start: rules::GameState,
+	state: rules::GameState,
+	moves: Vec<rules::Action>,
+}
+
+impl LocalGame {
+	/// New game from the initial position of the rules, or from a custom one
+	pub fn new(config: rules::GameConfig, position: Option<rules::GameState>) -> Result<LocalGame, rules::ConfigError> {
+		let rules = config
+			.rule_set
+			.rules()
+			.ok_or(rules::ConfigError::UnknownRuleSet(config.rule_set))?;
+		config.validate()?;
+		rules.validate(&config)?;
+		let start = match position {
+			Some(position) => {
+				rules.validate_position(&config, &position)?;
+				position
+			}
+			None => rules.initial_state(&config),
+		};
+		Ok(LocalGame {
+			config,
+			rules,
+			state: start.clone(),
+			start,
+			moves: Vec::new(),
+		})
+	}
+
+	pub fn config(&self) -> &rules::GameConfig {
+		&self.config
+	}
+
+	pub fn start(&self) -> &rules::GameState {
+		&self.start
+	}
+
+	pub fn state(&self) -> &rules::GameState {
+		&self.state
+	}
+
+	pub fn moves(&self) -> &[rules::Action] {
+		&self.moves
+	}
+
+	pub fn outcome(&self) -> Option<rules::GameResult> {
+		self.rules.outcome(&self.state)
+	}
+
+	pub fn play(&mut self, mv: rules::Move) -> Result<(), rules::IllegalMove> {
+		self.rules.apply(&mut self.state, &mv)?;
+		self.moves.push(rules::Action::Move(mv));
+		Ok(())
+	}
+}
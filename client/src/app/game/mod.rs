@@ -1,26 +1,245 @@
-use web_sys::Document;
+pub mod local;
+pub mod net;
 
+use crate::ep::AppEntryPoint;
+use crate::ep::EpRef;
 use crate::utils::dom;
+use ygame_ai as ai;
+use ygame_game as rules;
 
-pub struct GameSection {}
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Document, Event, HtmlElement, HtmlFormElement};
+
+pub struct GameSection {
+	on_resign_cb: Closure<dyn FnMut()>,
+	on_offer_draw_cb: Closure<dyn FnMut()>,
+	on_accept_draw_cb: Closure<dyn FnMut()>,
+	on_decline_draw_cb: Closure<dyn FnMut()>,
+	on_request_takeback_cb: Closure<dyn FnMut()>,
+	on_accept_takeback_cb: Closure<dyn FnMut()>,
+	on_decline_takeback_cb: Closure<dyn FnMut()>,
+	on_download_record_cb: Closure<dyn FnMut()>,
+	on_play_move_cb: Closure<dyn FnMut(Event)>,
+	on_hint_cb: Closure<dyn FnMut()>,
+}
 
 impl GameSection {
+	pub fn new(ep: EpRef, document: &Document) -> GameSection {
+		GameSection {
+			on_resign_cb: GameSection::add_action_event_listener(
+				ep.clone(),
+				document,
+				"resign_btn",
+				rules::Action::Resign,
+			),
+			on_offer_draw_cb: GameSection::add_action_event_listener(
+				ep.clone(),
+				document,
+				"offer_draw_btn",
+				rules::Action::OfferDraw,
+			),
+			on_accept_draw_cb: GameSection::add_action_event_listener(
+				ep.clone(),
+				document,
+				"accept_draw_btn",
+				rules::Action::AcceptDraw,
+			),
+			on_decline_draw_cb: GameSection::add_action_event_listener(
+				ep.clone(),
+				document,
+				"decline_draw_btn",
+				rules::Action::DeclineDraw,
+			),
+			on_request_takeback_cb: GameSection::add_action_event_listener(
+				ep.clone(),
+				document,
+				"request_takeback_btn",
+				rules::Action::RequestTakeback,
+			),
+			on_accept_takeback_cb: GameSection::add_action_event_listener(
+				ep.clone(),
+				document,
+				"accept_takeback_btn",
+				rules::Action::AcceptTakeback,
+			),
+			on_decline_takeback_cb: GameSection::add_action_event_listener(
+				ep.clone(),
+				document,
+				"decline_takeback_btn",
+				rules::Action::DeclineTakeback,
+			),
+			on_download_record_cb: GameSection::add_download_event_listener(ep.clone(), document),
+			on_play_move_cb: GameSection::add_play_move_event_listener(ep.clone(), document),
+			on_hint_cb: GameSection::add_hint_event_listener(ep, document),
+		}
+	}
+
 	pub fn hide(document: &Document) {
 		dom::hide_element_by_id(document, "game_section");
 	}
 
 	pub fn show(document: &Document) {
+		dom::set_inner_text_by_id(document, "analysis_view", "");
 		dom::show_element_by_id(document, "game_section");
 	}
+
+	pub fn show_draw_offer(document: &Document, role: &UserRole) {
+		let label = match role {
+			UserRole::Seat1 => "Le joueur 1 propose un match nul",
+			UserRole::Seat2 => "Le joueur 2 propose un match nul",
+			UserRole::Observer => "Match nul proposé",
+		};
+		dom::set_inner_text_by_id(document, "draw_offer_label", label);
+		dom::show_element_by_id(document, "draw_offer_section");
+	}
+
+	pub fn hide_draw_offer(document: &Document) {
+		dom::hide_element_by_id(document, "draw_offer_section");
+	}
+
+	pub fn show_takeback_request(document: &Document, role: &UserRole) {
+		let label = match role {
+			UserRole::Seat1 => "Le joueur 1 demande à reprendre son coup",
+			UserRole::Seat2 => "Le joueur 2 demande à reprendre son coup",
+			UserRole::Observer => "Reprise de coup demandée",
+		};
+		dom::set_inner_text_by_id(document, "takeback_label", label);
+		dom::show_element_by_id(document, "takeback_section");
+	}
+
+	pub fn hide_takeback_request(document: &Document) {
+		dom::hide_element_by_id(document, "takeback_section");
+	}
+
+	pub fn show_clocks(document: &Document, seat_1_ms: u64, seat_2_ms: u64, running: Option<UserRole>) {
+		let marker = |role| if running == Some(role) { "▶ " } else { "" };
+		let seat_1 = format!("{}{}", marker(UserRole::Seat1), format_clock(seat_1_ms));
+		let seat_2 = format!("{}{}", marker(UserRole::Seat2), format_clock(seat_2_ms));
+		dom::set_inner_text_by_id(document, "seat_1_clock", &seat_1);
+		dom::set_inner_text_by_id(document, "seat_2_clock", &seat_2);
+	}
+
+	pub fn show_position(document: &Document, state: &rules::GameState, result: Option<&rules::GameResult>) {
+		dom::set_inner_text_by_id(
+			document,
+			"board_view",
+			&rules::render::board(state.board(), &rules::render::UNICODE),
+		);
+		let player_label = |player| match player {
+			rules::Player::One => "joueur 1",
+			rules::Player::Two => "joueur 2",
+		};
+		let status = match result {
+			Some(result) => match result.winner {
+				Some(player) => format!("Victoire du {}", player_label(player)),
+				None => "Match nul".to_owned(),
+			},
+			None => format!("Au tour du {}", player_label(state.side_to_move())),
+		};
+		dom::set_inner_text_by_id(document, "game_status_label", &status);
+	}
+
+	pub fn show_move_error(document: &Document, error: &str) {
+		dom::set_inner_text_by_id(document, "game_status_label", error);
+	}
+
+	/// Hint of the engine, after the statistics of the opening book if the position is in it
+	pub fn show_hint(document: &Document, analysis: &ai::Analysis, book_moves: &[ai::BookMove]) {
+		let mut lines: Vec<String> = book_moves.iter().map(describe_book_move).collect();
+		lines.push(describe_analysis(analysis));
+		dom::set_inner_text_by_id(document, "analysis_view", &lines.join("\n"));
+	}
+
+	/// Analysis of every move of the game, from the server
+	pub fn show_game_analysis(document: &Document, plies: &[(rules::Move, ai::Analysis)]) {
+		let lines: Vec<String> = plies
+			.iter()
+			.enumerate()
+			.map(|(index, (played, analysis))| format!("{}. {} : {}", index + 1, played, describe_analysis(analysis)))
+			.collect();
+		dom::set_inner_text_by_id(document, "analysis_view", &lines.join("\n"));
+	}
+
+	fn add_action_event_listener(
+		ep: EpRef,
+		document: &Document,
+		button_id: &str,
+		action: rules::Action,
+	) -> Closure<dyn FnMut()> {
+		let handler = move || {
+			ep.send_game_action(action.clone());
+		};
+		let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut()>);
+		dom::by_id!(document, button_id, HtmlElement).set_onclick(Some(closure.as_ref().unchecked_ref()));
+		closure
+	}
+
+	fn add_download_event_listener(ep: EpRef, document: &Document) -> Closure<dyn FnMut()> {
+		let handler = move || {
+			ep.download_game_record();
+		};
+		let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut()>);
+		dom::by_id!(document, "download_record_btn", HtmlElement).set_onclick(Some(closure.as_ref().unchecked_ref()));
+		closure
+	}
+
+	fn add_play_move_event_listener(ep: EpRef, document: &Document) -> Closure<dyn FnMut(Event)> {
+		let handler = move |event: Event| {
+			event.prevent_default();
+			let window = web_sys::window().expect("no global `window` exists");
+			let document = window.document().expect("window has not document");
+			let text = dom::read_field_by_id(&document, "move_field");
+			dom::set_field_value_by_id(&document, "move_field", "");
+			ep.play_move(text);
+		};
+		let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(Event)>);
+		dom::by_id!(document, "game_form", HtmlFormElement).set_onsubmit(Some(closure.as_ref().unchecked_ref()));
+		closure
+	}
+
+	fn add_hint_event_listener(ep: EpRef, document: &Document) -> Closure<dyn FnMut()> {
+		let handler = move || {
+			ep.ask_hint();
+		};
+		let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut()>);
+		dom::by_id!(document, "hint_btn", HtmlElement).set_onclick(Some(closure.as_ref().unchecked_ref()));
+		closure
+	}
+
+	fn clean_callbacks(&mut self) {
+		if let Some(window) = web_sys::window() {
+			if let Some(document) = window.document() {
+				dom::by_id!(document, "resign_btn", HtmlElement).set_onclick(None);
+				dom::by_id!(document, "offer_draw_btn", HtmlElement).set_onclick(None);
+				dom::by_id!(document, "accept_draw_btn", HtmlElement).set_onclick(None);
+				dom::by_id!(document, "decline_draw_btn", HtmlElement).set_onclick(None);
+				dom::by_id!(document, "request_takeback_btn", HtmlElement).set_onclick(None);
+				dom::by_id!(document, "accept_takeback_btn", HtmlElement).set_onclick(None);
+				dom::by_id!(document, "decline_takeback_btn", HtmlElement).set_onclick(None);
+				dom::by_id!(document, "download_record_btn", HtmlElement).set_onclick(None);
+				dom::by_id!(document, "game_form", HtmlFormElement).set_onsubmit(None);
+				dom::by_id!(document, "hint_btn", HtmlElement).set_onclick(None);
+			}
+		}
+	}
+}
+
+impl Drop for GameSection {
+	fn drop(&mut self) {
+		self.clean_callbacks();
+	}
 }
 
 #[derive(Debug, Clone)]
 pub struct GameDetails {
 	pub id: String,
 	pub name: String,
-	pub is_finished: bool,
+	pub result: Option<rules::GameResult>,
 	pub seat_1_username: Option<String>,
 	pub seat_2_username: Option<String>,
+	pub config: rules::GameConfig,
+	pub position: Option<rules::GameState>, // custom starting position
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -33,6 +252,55 @@ pub enum UserRole {
 #[derive(Debug, Clone)]
 pub enum GameAction {
 	Init,
-	Move,
-	Finished,
+	Move(rules::Move),
+	Resign,
+	OfferDraw,
+	AcceptDraw,
+	DeclineDraw,
+	RequestTakeback,
+	AcceptTakeback,
+	DeclineTakeback,
+	Finished(rules::GameResult),
+}
+
+// Remaining time as minutes and seconds, tenths under ten seconds
+fn format_clock(ms: u64) -> String {
+	if ms < 10_000 {
+		format!("{}.{}", ms / 1000, ms % 1000 / 100)
+	} else {
+		let secs = ms / 1000;
+		format!("{:02}:{:02}", secs / 60, secs % 60)
+	}
+}
+
+// Best move with the score, or the number of plies when the end of the game is forced
+fn describe_analysis(analysis: &ai::Analysis) -> String {
+	let best_move = match analysis.best_move {
+		Some(mv) => mv,
+		None => return "partie terminée".to_owned(),
+	};
+	let variation: Vec<String> = analysis.principal_variation.iter().map(|mv| mv.to_string()).collect();
+	match analysis.forced_end() {
+		Some(plies) if plies > 0 => format!("{} gagne en {} demi-coups ({})", best_move, plies, variation.join(" ")),
+		Some(plies) => format!("{} perd en {} demi-coups ({})", best_move, -plies, variation.join(" ")),
+		None => format!(
+			"{} évaluation {:+} ({})",
+			best_move,
+			analysis.score,
+			variation.join(" ")
+		),
+	}
+}
+
+fn describe_book_move(book_move: &ai::BookMove) -> String {
+	let games = match book_move.games {
+		1 => "1 partie".to_owned(),
+		games => format!("{} parties", games),
+	};
+	format!(
+		"{} : {}, {:.0} % de réussite",
+		book_move.mv,
+		games,
+		book_move.win_rate() * 100.0
+	)
 }
@@ -0,0 +1,80 @@
+use crate::app::game::{GameAction, GameDetails};
+use ygame_game as rules;
+use ygame_game::Rules;
+
+/// Game hosted by the server, played or watched by the user
+///
+/// The server checks the moves, they are only replayed here to show the position.
+#[derive(Debug, Clone)]
+pub struct NetGame {
+	id: String,
+	rules: &'static dyn Rules,
+	start: rules::GameState,
+	state: rules::GameState,
+	moves: Vec<rules::Action>,
+	result: Option<rules::GameResult>,
+}
+
+impl NetGame {
+	/// Game at its starting position, the rule set must be one of the built-in ones
+	pub fn new(info: &GameDetails) -> Result<NetGame, rules::ConfigError> {
+		let rules = info
+			.config
+			.rule_set
+			.rules()
+			.ok_or(rules::ConfigError::UnknownRuleSet(info.config.rule_set))?;
+		let start = match &info.position {
+			Some(position) => position.clone(),
+			None => rules.initial_state(&info.config),
+		};
+		Ok(NetGame {
+			id: info.id.clone(),
+			rules,
+			state: start.clone(),
+			start,
+			moves: Vec::new(),
+			result: info.result,
+		})
+	}
+
+	pub fn id(&self) -> &str {
+		&self.id
+	}
+
+	pub fn state(&self) -> &rules::GameState {
+		&self.state
+	}
+
+	pub fn result(&self) -> Option<&rules::GameResult> {
+		self.result.as_ref()
+	}
+
+	/// Replay an action of the game log, only the moves and the end of the game matter
+	pub fn replay(&mut self, action: &GameAction) -> Result<(), rules::IllegalMove> {
+		match action {
+			GameAction::Move(mv) => self.play(*mv),
+			GameAction::Finished(result) => {
+				self.finish(*result);
+				Ok(())
+			}
+			_ => Ok(()),
+		}
+	}
+
+	pub fn play(&mut self, mv: rules::Move) -> Result<(), rules::IllegalMove> {
+		self.rules.apply(&mut self.state, &mv)?;
+		self.moves.push(rules::Action::Move(mv));
+		Ok(())
+	}
+
+	pub fn finish(&mut self, result: rules::GameResult) {
+		self.result = Some(result);
+	}
+
+	/// Take back the last plies, once the opponent accepted it
+	pub fn rollback(&mut self, plies: u32) -> Result<(), rules::IllegalMove> {
+		self.moves.truncate(self.moves.len().saturating_sub(plies as usize));
+		self.state = self.rules.replay(&self.start, &self.moves)?;
+		Ok(())
+	}
+}
@@ -2,12 +2,13 @@ use crate::app::lobby::GameOverview;
 use crate::ep::AppEntryPoint;
 use crate::ep::EpRef;
 use crate::utils::dom;
+use ygame_game as rules;
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::Element;
 use web_sys::HtmlElement;
-use web_sys::{Document, Event, HtmlFormElement};
+use web_sys::{Document, Event, HtmlFormElement, HtmlSelectElement};
 
 pub struct LobbyForm {
 	on_create_cb: Closure<dyn FnMut(Event)>,
@@ -66,19 +67,72 @@ impl LobbyForm {
 		}
 	}
 
+	/// Add the rule sets loaded by the server which are not built in the page
+	pub fn show_rule_sets(document: &Document, list: &[rules::RuleSet]) {
+		let select = dom::by_id!(document, "rule_set_field", HtmlSelectElement).clone();
+		for rule_set in list {
+			let id = rule_set.id();
+			let option_id = format!("rule_set_{}", id);
+			if rules::RuleSet::BUILTIN.contains(rule_set) || document.get_element_by_id(&option_id).is_some() {
+				continue;
+			}
+			let option = document.create_element("option").unwrap();
+			option.set_id(&option_id);
+			option.set_attribute("value", id).ok();
+			option.set_text_content(Some(id));
+			select.append_child(&option).unwrap();
+		}
+	}
+
+	/// Game options chosen in the creation form
+	pub fn read_config(document: &Document) -> rules::GameConfig {
+		let default = rules::GameConfig::default();
+		rules::GameConfig {
+			rule_set: dom::read_select_by_id(document, "rule_set_field")
+				.parse()
+				.unwrap_or(default.rule_set),
+			board_size: dom::read_select_by_id(document, "board_size_field")
+				.parse()
+				.unwrap_or(default.board_size),
+			time_control: dom::read_select_by_id(document, "time_control_field").parse().ok(),
+			first_player: match dom::read_select_by_id(document, "first_player_field").as_str() {
+				"2" => rules::Player::Two,
+				_ => rules::Player::One,
+			},
+			rated: dom::is_checked_by_id(document, "rated_field"),
+			private: dom::is_checked_by_id(document, "private_field"),
+		}
+	}
+
+	/// Starting position typed in the creation form, checked by the server
+	pub fn read_position(document: &Document) -> Option<String> {
+		let text = dom::read_field_by_id(document, "start_position_field");
+		match text.trim() {
+			"" => None,
+			text => Some(text.to_owned()),
+		}
+	}
+
 	fn create_game_element(&self, document: &Document, game: &GameOverview) -> Element {
 		let element = document.create_element("li").unwrap();
 		element.set_id(format!("lobby_game_{}", game.id).as_str());
 		element.set_class_name("game");
-
-		//FIXME: implement this
-		element.set_inner_html(game.name.as_str());
+		self.update_game_element(&element, game);
 		element
 	}
 
 	fn update_game_element(&self, element: &Element, game: &GameOverview) {
-		//FIXME: implement this
-		element.set_inner_html(game.name.as_str());
+		let label = format!("{} ({})", game.name, describe_config(&game.config));
+		element.set_text_content(Some(label.as_str()));
+		if let Some(document) = element.owner_document() {
+			let thumbnail = document.create_element("img").unwrap();
+			thumbnail.set_class_name("thumbnail");
+			// The time in the url makes the browser load the position again each time the game changes
+			let url = format!("games/{}/board.svg?t={}", game.id, js_sys::Date::now() as u64);
+			thumbnail.set_attribute("src", &url).ok();
+			thumbnail.set_attribute("alt", "").ok();
+			element.insert_before(&thumbnail, element.first_child().as_ref()).ok();
+		}
 	}
 
 	fn add_create_event_listener(ep: EpRef, document: &Document) -> Closure<dyn FnMut(Event)> {
@@ -105,3 +159,22 @@ impl Drop for LobbyForm {
 		self.clean_callbacks();
 	}
 }
+
+// Short summary of the game options, like `7x7, 300+5, classée`
+fn describe_config(config: &rules::GameConfig) -> String {
+	let mut parts = vec![format!("{}x{}", config.board_size, config.board_size)];
+	if config.rule_set != rules::RuleSet::Standard {
+		parts.push(config.rule_set.to_string());
+	}
+	match config.time_control {
+		Some(control) => parts.push(control.to_string()),
+		None => parts.push("sans pendule".to_owned()),
+	}
+	if config.first_player == rules::Player::Two {
+		parts.push("joueur 2 commence".to_owned());
+	}
+	if config.rated {
+		parts.push("classée".to_owned());
+	}
+	parts.join(", ")
+}
@@ -1,5 +1,7 @@
 pub mod form;
 
+use ygame_game as rules;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum GameStatus {
 	Joinable = 1,
@@ -13,4 +15,5 @@ pub struct GameOverview {
 	pub id: String,
 	pub name: String,
 	pub status: GameStatus,
+	pub config: rules::GameConfig,
 }
@@ -16,6 +16,8 @@ pub struct LoginForm {
 	on_existing_user_cb: Closure<dyn FnMut(Event)>,
 	on_local_game_cb: Closure<dyn FnMut()>,
 	on_net_game_cb: Closure<dyn FnMut()>,
+	on_puzzle_cb: Closure<dyn FnMut()>,
+	on_opponent_cb: Closure<dyn FnMut(Event)>,
 }
 
 impl LoginForm {
@@ -25,7 +27,9 @@ impl LoginForm {
 			on_forget_user_cb: LoginForm::add_forget_user_event_listener(ep.clone(), document),
 			on_existing_user_cb: LoginForm::add_existing_user_login_event_listener(ep.clone(), document),
 			on_local_game_cb: LoginForm::add_local_game_event_listener(ep.clone(), document),
-			on_net_game_cb: LoginForm::add_net_game_event_listener(ep, document),
+			on_net_game_cb: LoginForm::add_net_game_event_listener(ep.clone(), document),
+			on_puzzle_cb: LoginForm::add_puzzle_event_listener(ep.clone(), document),
+			on_opponent_cb: LoginForm::add_opponent_event_listener(ep, document),
 		}
 	}
 
@@ -87,6 +91,8 @@ impl LoginForm {
 				dom::by_id!(document, "existing_user_form", HtmlFormElement).set_onsubmit(None);
 				dom::by_id!(document, "local_game_btn", HtmlElement).set_onclick(None);
 				dom::by_id!(document, "online_game_btn", HtmlElement).set_onclick(None);
+				dom::by_id!(document, "puzzle_btn", HtmlElement).set_onclick(None);
+				dom::by_id!(document, "opponent_form", HtmlFormElement).set_onsubmit(None);
 			}
 		}
 	}
@@ -149,6 +155,25 @@ impl LoginForm {
 		dom::by_id!(document, "online_game_btn", HtmlElement).set_onclick(Some(closure.as_ref().unchecked_ref()));
 		closure
 	}
+
+	fn add_puzzle_event_listener(ep: EpRef, document: &Document) -> Closure<dyn FnMut()> {
+		let handler = move || {
+			ep.next_puzzle();
+		};
+		let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut()>);
+		dom::by_id!(document, "puzzle_btn", HtmlElement).set_onclick(Some(closure.as_ref().unchecked_ref()));
+		closure
+	}
+
+	fn add_opponent_event_listener(ep: EpRef, document: &Document) -> Closure<dyn FnMut(Event)> {
+		let handler = move |event: Event| {
+			event.prevent_default();
+			ep.set_state(AppState::InLocalGame);
+		};
+		let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(Event)>);
+		dom::by_id!(document, "opponent_form", HtmlFormElement).set_onsubmit(Some(closure.as_ref().unchecked_ref()));
+		closure
+	}
 }
 
 impl Drop for LoginForm {
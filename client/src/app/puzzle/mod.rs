@@ -0,0 +1,124 @@
+use crate::ep::AppEntryPoint;
+use crate::ep::EpRef;
+use crate::utils::dom;
+use ygame_game as rules;
+use ygame_game::puzzle::{Attempt, WrongMove};
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Document, Event, HtmlElement, HtmlFormElement};
+
+/// Puzzle screen, played without the server
+pub struct PuzzleSection {
+	on_play_move_cb: Closure<dyn FnMut(Event)>,
+	on_retry_cb: Closure<dyn FnMut()>,
+	on_next_cb: Closure<dyn FnMut()>,
+}
+
+impl PuzzleSection {
+	pub fn new(ep: EpRef, document: &Document) -> PuzzleSection {
+		PuzzleSection {
+			on_play_move_cb: PuzzleSection::add_play_move_event_listener(ep.clone(), document),
+			on_retry_cb: PuzzleSection::add_retry_event_listener(ep.clone(), document),
+			on_next_cb: PuzzleSection::add_next_event_listener(ep, document),
+		}
+	}
+
+	pub fn hide(document: &Document) {
+		dom::hide_element_by_id(document, "puzzle_section");
+	}
+
+	pub fn show(document: &Document) {
+		dom::show_element_by_id(document, "puzzle_section");
+	}
+
+	pub fn show_attempt(document: &Document, attempt: &Attempt) {
+		let puzzle = attempt.puzzle();
+		let player = match puzzle.solver() {
+			rules::Player::One => "joueur 1",
+			rules::Player::Two => "joueur 2",
+		};
+		let title = match puzzle.moves_to_find() {
+			1 => format!("Problème {} : le {} gagne en un coup", puzzle.id, player),
+			moves => format!("Problème {} : le {} gagne en {} coups", puzzle.id, player, moves),
+		};
+		dom::set_inner_text_by_id(document, "puzzle_title", &title);
+		dom::set_inner_text_by_id(
+			document,
+			"puzzle_board_view",
+			&rules::render::board(attempt.state().board(), &rules::render::UNICODE),
+		);
+		let status = if attempt.is_solved() {
+			"Bravo, problème résolu !"
+		} else {
+			"À vous de jouer"
+		};
+		dom::set_inner_text_by_id(document, "puzzle_status_label", status);
+	}
+
+	pub fn show_reply(document: &Document, reply: &rules::Move) {
+		let status = format!("Votre adversaire répond {}, à vous de jouer", reply);
+		dom::set_inner_text_by_id(document, "puzzle_status_label", &status);
+	}
+
+	pub fn show_wrong_move(document: &Document, error: &WrongMove) {
+		let status = match error {
+			WrongMove::Illegal(_) => "Ce coup n'est pas possible".to_owned(),
+			WrongMove::NotSolution(mv) => format!("{} n'est pas le bon coup, essayez encore", mv),
+			WrongMove::Solved => "Le problème est déjà résolu".to_owned(),
+		};
+		dom::set_inner_text_by_id(document, "puzzle_status_label", &status);
+	}
+
+	pub fn show_error(document: &Document, error: &str) {
+		dom::set_inner_text_by_id(document, "puzzle_status_label", error);
+	}
+
+	fn add_play_move_event_listener(ep: EpRef, document: &Document) -> Closure<dyn FnMut(Event)> {
+		let handler = move |event: Event| {
+			event.prevent_default();
+			let window = web_sys::window().expect("no global `window` exists");
+			let document = window.document().expect("window has not document");
+			let text = dom::read_field_by_id(&document, "puzzle_move_field");
+			dom::set_field_value_by_id(&document, "puzzle_move_field", "");
+			ep.play_move(text);
+		};
+		let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(Event)>);
+		dom::by_id!(document, "puzzle_form", HtmlFormElement).set_onsubmit(Some(closure.as_ref().unchecked_ref()));
+		closure
+	}
+
+	fn add_retry_event_listener(ep: EpRef, document: &Document) -> Closure<dyn FnMut()> {
+		let handler = move || {
+			ep.restart_puzzle();
+		};
+		let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut()>);
+		dom::by_id!(document, "puzzle_retry_btn", HtmlElement).set_onclick(Some(closure.as_ref().unchecked_ref()));
+		closure
+	}
+
+	fn add_next_event_listener(ep: EpRef, document: &Document) -> Closure<dyn FnMut()> {
+		let handler = move || {
+			ep.next_puzzle();
+		};
+		let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut()>);
+		dom::by_id!(document, "puzzle_next_btn", HtmlElement).set_onclick(Some(closure.as_ref().unchecked_ref()));
+		closure
+	}
+
+	fn clean_callbacks(&mut self) {
+		if let Some(window) = web_sys::window() {
+			if let Some(document) = window.document() {
+				dom::by_id!(document, "puzzle_form", HtmlFormElement).set_onsubmit(None);
+				dom::by_id!(document, "puzzle_retry_btn", HtmlElement).set_onclick(None);
+				dom::by_id!(document, "puzzle_next_btn", HtmlElement).set_onclick(None);
+			}
+		}
+	}
+}
+
+impl Drop for PuzzleSection {
+	fn drop(&mut self) {
+		self.clean_callbacks();
+	}
+}
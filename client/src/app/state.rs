@@ -9,6 +9,7 @@ pub enum AppState {
 	ConnectingToGame { game_uid: String },
 	InNetGame { game_uid: String },
 	InLocalGame,
+	Puzzle { puzzle_id: String },
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -39,6 +40,10 @@ impl AppState {
 			Some(AppState::InNetGame {
 				game_uid: url[5..].to_owned(),
 			})
+		} else if url.starts_with("puzzle/") && url.len() > 7 {
+			Some(AppState::Puzzle {
+				puzzle_id: url[7..].to_owned(),
+			})
 		} else {
 			None
 		}
@@ -73,6 +78,15 @@ impl AppState {
 				)
 			}
 			AppState::InLocalGame => format!("{}{}{}", history::HIST_GAME_PREFIX, first_prefix, "in_local_game"),
+			AppState::Puzzle { puzzle_id } => {
+				format!(
+					"{}{}{}{}",
+					history::HIST_GAME_PREFIX,
+					first_prefix,
+					"puzzle_",
+					puzzle_id
+				)
+			}
 		}
 	}
 
@@ -97,6 +111,10 @@ impl AppState {
 					Some(AppState::InNetGame {
 						game_uid: hist_state["in_game_".len()..].to_owned(),
 					})
+				} else if hist_state.starts_with("puzzle_") && hist_state.len() > "puzzle_".len() {
+					Some(AppState::Puzzle {
+						puzzle_id: hist_state["puzzle_".len()..].to_owned(),
+					})
 				} else {
 					None
 				}
@@ -114,6 +132,7 @@ impl AppState {
 			AppState::Lobby | AppState::ConnectingToLobby => "Choix de la partie",
 			AppState::InNetGame { .. } | AppState::ConnectingToGame { .. } => "Partie en ligne",
 			AppState::InLocalGame => "Partie locale",
+			AppState::Puzzle { .. } => "Problème",
 		}
 	}
 
@@ -127,6 +146,7 @@ impl AppState {
 			AppState::Lobby | AppState::ConnectingToLobby => "/lobby".to_owned(),
 			AppState::InNetGame { game_uid } | AppState::ConnectingToGame { game_uid } => format!("/game/{}", game_uid),
 			AppState::InLocalGame => "/local".to_owned(),
+			AppState::Puzzle { puzzle_id } => format!("/puzzle/{}", puzzle_id),
 		}
 	}
 }
@@ -11,7 +11,10 @@ use crate::err;
 use crate::utils::console;
 use crate::utils::time;
 use crate::Result;
+use ygame_game as rules;
 use ygame_protocol as msg;
+use ygame_protocol::handshake::Capability;
+use ygame_protocol::handshake::Encoding;
 
 use std::cell::Cell;
 use std::cell::RefCell;
@@ -158,9 +161,49 @@ impl Connection {
 		}
 	}
 
-	pub fn create_game(&self) {
+	/// Whether an optional feature was agreed with the server, always false while disconnected
+	pub fn has_capability(&self, capability: Capability) -> bool {
+		match self.websocket.borrow().as_ref() {
+			Some(ws) => ws.has_capability(capability),
+			None => false,
+		}
+	}
+
+	pub fn create_game(&self, config: rules::GameConfig, position: Option<String>) {
+		if let Some(ws) = self.websocket.borrow().as_ref() {
+			if let Err(e) = self.handler.borrow_mut().create_game(ws, config, position) {
+				err::render_error(&e);
+			}
+		} else {
+			panic!("No websocket available")
+		}
+	}
+
+	pub fn send_game_action(&self, game_id: &str, action: rules::Action) {
 		if let Some(ws) = self.websocket.borrow().as_ref() {
-			self.handler.borrow_mut().create_game(ws);
+			if let Err(e) = self.handler.borrow_mut().send_game_action(ws, game_id, action) {
+				err::render_error(&e);
+			}
+		} else {
+			panic!("No websocket available")
+		}
+	}
+
+	pub fn ask_game_record(&self, game_id: &str) {
+		if let Some(ws) = self.websocket.borrow().as_ref() {
+			if let Err(e) = self.handler.borrow_mut().ask_game_record(ws, game_id) {
+				err::render_error(&e);
+			}
+		} else {
+			panic!("No websocket available")
+		}
+	}
+
+	pub fn ask_game_analysis(&self, game_id: &str) {
+		if let Some(ws) = self.websocket.borrow().as_ref() {
+			if let Err(e) = self.handler.borrow_mut().ask_game_analysis(ws, game_id) {
+				err::render_error(&e);
+			}
 		} else {
 			panic!("No websocket available")
 		}
@@ -169,7 +212,13 @@ impl Connection {
 	fn handle_message(&self, e: MessageEvent, app: &mut Application) -> Result<NextHandler> {
 		if let Some(ws) = self.websocket.borrow().as_ref() {
 			if let Ok(abuf) = e.data().dyn_into::<js_sys::ArrayBuffer>() {
-				self.handler.borrow().on_array_buf(&ws, app, &abuf)
+				match ws.encoding() {
+					Encoding::MessagePack => {
+						let array = js_sys::Uint8Array::new(&abuf);
+						self.handler.borrow().on_message(&ws, app, &array.to_vec())
+					}
+					Encoding::Json | Encoding::Unknown => self.handler.borrow().on_array_buf(&ws, app, &abuf),
+				}
 			} else if let Ok(blob) = e.data().dyn_into::<web_sys::Blob>() {
 				self.handler.borrow().on_blob(&ws, app, &blob)
 			} else if let Ok(txt) = e.data().dyn_into::<js_sys::JsString>() {
@@ -195,7 +244,12 @@ impl Connection {
 								source: None,
 							})
 						}
-						Err(_) => self.handler.borrow().on_text(&ws, app, String::from(txt).as_str()),
+						Err(_) => match ws.encoding() {
+							Encoding::Json => self.handler.borrow().on_message(&ws, app, String::from(txt).as_bytes()),
+							Encoding::MessagePack | Encoding::Unknown => {
+								self.handler.borrow().on_text(&ws, app, String::from(txt).as_str())
+							}
+						},
 					}
 				}
 			} else {
@@ -1,5 +1,6 @@
 use crate::app::Application;
-use crate::conn::handler::v1::login::LoginHandler;
+use crate::conn::handler::v1;
+use crate::conn::handler::v2;
 use crate::conn::handler::MsgHandler;
 use crate::conn::handler::NextHandler;
 use crate::conn::socket::Socket;
@@ -9,14 +10,34 @@ use crate::utils::console;
 use crate::Result;
 use ygame_protocol::handshake as msg;
 use ygame_protocol::v1 as msg_v1;
+use ygame_protocol::v2 as msg_v2;
 
 #[derive(Debug)]
 pub struct HandshakeHandler {}
 
+impl HandshakeHandler {
+	/// JSON is only asked when set in the local storage, e.g. `localStorage.setItem("encoding", "json")`,
+	/// to read the traffic in the browser dev tools
+	fn preferred_encodings() -> Vec<msg::Encoding> {
+		let storage = web_sys::window().and_then(|window| window.local_storage().ok().flatten());
+		match storage.and_then(|storage| storage.get_item("encoding").ok().flatten()) {
+			Some(encoding) if encoding == "json" => vec![msg::Encoding::Json, msg::Encoding::MessagePack],
+			_ => vec![msg::Encoding::MessagePack],
+		}
+	}
+
+	/// Optional features this client implements
+	fn supported_capabilities() -> Vec<msg::Capability> {
+		vec![msg::Capability::Clocks, msg::Capability::Spectate]
+	}
+}
+
 impl MsgHandler for HandshakeHandler {
 	fn on_start(&self, ws: &Socket, _app: &mut Application) -> Result<()> {
 		let hello_msg = msg::HelloMessage {
-			known_protocols: vec![1],
+			known_protocols: vec![msg_v1::VERSION, msg_v2::VERSION],
+			encodings: HandshakeHandler::preferred_encodings(),
+			capabilities: HandshakeHandler::supported_capabilities(),
 		};
 		let hello_msg = match serde_json::to_string(&hello_msg) {
 			Ok(hello_msg) => hello_msg,
@@ -36,13 +57,30 @@ impl MsgHandler for HandshakeHandler {
 		}
 	}
 
-	fn on_text(&self, _socket: &Socket, _app: &mut Application, txt: &str) -> Result<NextHandler> {
+	fn on_text(&self, socket: &Socket, _app: &mut Application, txt: &str) -> Result<NextHandler> {
 		console::log!("received: {}", txt);
 		let resp: serde_json::Result<msg::HelloResponseMessage> = serde_json::from_str(&String::from(txt));
 		match resp {
-			Ok(msg::HelloResponseMessage::Success { protocol_version }) => {
-				if protocol_version == msg_v1::VERSION {
-					Ok(Some(Box::new(LoginHandler {})))
+			Ok(msg::HelloResponseMessage::Success {
+				encoding: msg::Encoding::Unknown,
+				..
+			}) => Err(err::Error::ProtocolError {
+				details: "Unknown encoding chosen by the server".to_owned(),
+				should_reload: true,
+				source: None,
+			}),
+			Ok(msg::HelloResponseMessage::Success {
+				protocol_version,
+				encoding,
+				capabilities,
+			}) => {
+				console::log!("Agreed capabilities: {:?}", capabilities);
+				socket.set_encoding(encoding);
+				socket.set_capabilities(capabilities);
+				if protocol_version == msg_v2::VERSION {
+					Ok(Some(Box::new(v2::login::LoginHandler::new())))
+				} else if protocol_version == msg_v1::VERSION {
+					Ok(Some(Box::new(v1::login::LoginHandler {})))
 				} else {
 					Err(err::Error::ProtocolError {
 						details: "No compatible protocol version".to_owned(),
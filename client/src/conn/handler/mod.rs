@@ -1,10 +1,12 @@
 pub mod handshake;
 pub mod v1;
+pub mod v2;
 
 use crate::app::Application;
 use crate::conn::socket::Socket;
 use crate::err::Error;
 use crate::Result;
+use ygame_game as rules;
 
 pub type NextHandler = Option<Box<dyn MsgHandler>>;
 
@@ -30,6 +32,15 @@ pub trait MsgHandler: std::fmt::Debug {
 		})
 	}
 
+	/// Login or running message, in the encoding chosen during the handshake
+	fn on_message(&self, _socket: &Socket, _app: &mut Application, _msg: &[u8]) -> Result<NextHandler> {
+		Err(Error::ProtocolError {
+			details: "Unexpected message".to_owned(),
+			should_reload: false,
+			source: None,
+		})
+	}
+
 	fn on_blob(&self, _socket: &Socket, _app: &mut Application, _blob: &web_sys::Blob) -> Result<NextHandler> {
 		Err(Error::ProtocolError {
 			details: "Unexpected blob message".to_owned(),
@@ -42,11 +53,23 @@ pub trait MsgHandler: std::fmt::Debug {
 		Ok(())
 	}
 
-	fn create_game(&mut self, _ws: &Socket) -> Result<()> {
+	fn create_game(&mut self, _ws: &Socket, _config: rules::GameConfig, _position: Option<String>) -> Result<()> {
 		panic!("Network is not ready to create game");
 	}
 
-	fn join_game(&self, _ws: &Socket, _game_id: &str) -> Result<()> {
+	fn join_game(&mut self, _ws: &Socket, _game_id: &str) -> Result<()> {
 		panic!("Network is not ready to join game");
 	}
+
+	fn send_game_action(&mut self, _ws: &Socket, _game_id: &str, _action: rules::Action) -> Result<()> {
+		panic!("Network is not ready to play");
+	}
+
+	fn ask_game_record(&mut self, _ws: &Socket, _game_id: &str) -> Result<()> {
+		panic!("Network is not ready to export games");
+	}
+
+	fn ask_game_analysis(&mut self, _ws: &Socket, _game_id: &str) -> Result<()> {
+		panic!("Network is not ready to analyse games");
+	}
 }
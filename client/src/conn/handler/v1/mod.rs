@@ -3,14 +3,24 @@ pub mod running;
 
 use crate::app::game;
 use crate::app::lobby;
+use ygame_game as rules;
 use ygame_protocol::v1 as msg;
 
+// Protocol 1 servers only create games with the default configuration, without clocks
+fn untimed_config() -> rules::GameConfig {
+	rules::GameConfig {
+		time_control: None,
+		..rules::GameConfig::default()
+	}
+}
+
 impl From<msg::GameOverview> for lobby::GameOverview {
 	fn from(v: msg::GameOverview) -> lobby::GameOverview {
 		lobby::GameOverview {
 			id: v.id,
 			name: v.name,
 			status: v.status.into(),
+			config: untimed_config(),
 		}
 	}
 }
@@ -22,6 +32,7 @@ impl From<&msg::GameOverview> for lobby::GameOverview {
 			id: v.id.clone(),
 			name: v.name.clone(),
 			status: status.into(),
+			config: untimed_config(),
 		}
 	}
 }
@@ -48,14 +59,17 @@ impl From<&msg::GameStatus> for lobby::GameStatus {
 	}
 }
 
+// The result of a finished game is unknown with protocol 1
 impl From<msg::GameDetails> for game::GameDetails {
 	fn from(v: msg::GameDetails) -> game::GameDetails {
 		game::GameDetails {
 			id: v.id,
 			name: v.name,
-			is_finished: v.is_finished,
+			result: None,
 			seat_1_username: v.seat_1_username,
 			seat_2_username: v.seat_2_username,
+			config: untimed_config(),
+			position: None,
 		}
 	}
 }
@@ -65,9 +79,11 @@ impl From<&msg::GameDetails> for game::GameDetails {
 		game::GameDetails {
 			id: v.id.clone(),
 			name: v.name.clone(),
-			is_finished: v.is_finished,
+			result: None,
 			seat_1_username: v.seat_1_username.clone(),
 			seat_2_username: v.seat_2_username.clone(),
+			config: untimed_config(),
+			position: None,
 		}
 	}
 }
@@ -82,22 +98,10 @@ impl From<msg::UserRole> for game::UserRole {
 	}
 }
 
-impl From<msg::GameAction> for game::GameAction {
-	fn from(v: msg::GameAction) -> game::GameAction {
-		match v {
-			msg::GameAction::Init => game::GameAction::Init,
-			msg::GameAction::Move => game::GameAction::Move,
-			msg::GameAction::Finished => game::GameAction::Finished,
-		}
-	}
-}
-
-impl From<&msg::GameAction> for game::GameAction {
-	fn from(v: &msg::GameAction) -> game::GameAction {
-		match v {
-			msg::GameAction::Init => game::GameAction::Init,
-			msg::GameAction::Move => game::GameAction::Move,
-			msg::GameAction::Finished => game::GameAction::Finished,
-		}
+// Moves and results can not be replayed without their payload, only the start of the game is known
+pub fn game_action(v: &msg::GameAction) -> Option<game::GameAction> {
+	match v {
+		msg::GameAction::Init => Some(game::GameAction::Init),
+		msg::GameAction::Move | msg::GameAction::Finished => None,
 	}
 }
@@ -1,16 +1,16 @@
 use crate::app::Application;
+use crate::conn::handler::v1::game_action;
+use crate::conn::handler::v1::untimed_config;
 use crate::conn::handler::MsgHandler;
 use crate::conn::handler::NextHandler;
 use crate::conn::socket::Socket;
 use crate::ep::EpRef;
 use crate::err::Error;
-use crate::err::JsException;
 use crate::utils::console;
 use crate::Result;
+use ygame_game as rules;
 use ygame_protocol::v1 as msg;
 
-use js_sys::ArrayBuffer;
-
 #[derive(Debug)]
 pub struct RunningHandler {
 	request_index: i32,
@@ -35,92 +35,82 @@ impl RunningHandler {
 				role,
 			} => app.on_game_created(info.into(), role.into()),
 			msg::LobbyServerMessages::GameJoined { info, role, moves } => {
-				app.on_game_joined(info.into(), role.into(), moves.iter().map(|v| v.into()).collect())
+				app.on_game_joined(info.into(), role.into(), moves.iter().filter_map(game_action).collect())
 			}
 		};
 	}
 
-	fn on_game_message(&self, game_msg: msg::GameAction, ep: EpRef) {}
+	fn on_game_message(&self, game_id: String, game_msg: msg::GameServerMessage, app: &mut Application) {
+		match game_msg {
+			msg::GameServerMessage::Action(action) => (),
+			msg::GameServerMessage::GameActionResponse { request_id, response } => (),
+			msg::GameServerMessage::UserJoin {
+				user_uid,
+				username,
+				role,
+			} => (),
+			msg::GameServerMessage::UserQuit { user_uid, role } => (),
+		}
+	}
+
+	fn send_message(&self, ws: &Socket, msg: &msg::RunningClientMessage) -> Result<()> {
+		ws.send_message(msg)
+	}
+
+	fn next_request_id(&mut self) -> String {
+		let request_id = self.request_index;
+		self.request_index += 1;
+		request_id.to_string()
+	}
 }
 
 impl MsgHandler for RunningHandler {
 	fn on_start(&self, ws: &Socket, _app: &mut Application) -> Result<()> {
 		let msg = msg::RunningClientMessage::Lobby(msg::LobbyClientMessage::AskGameList);
-		let msg = match rmp_serde::encode::to_vec(&msg) {
-			Ok(msg) => msg,
-			Err(e) => {
-				return Err(Error::ImplError {
-					details: "Serialisation failed".to_owned(),
-					source: Some(Box::new(e)),
-				});
-			}
-		};
-		let msg_ref: &[u8] = &msg;
-		match ws.send_buffer(msg_ref) {
-			Err(e) => Err(Error::ConnectionError {
-				details: "Unable to send message to the server".to_owned(),
-				source: Some(Box::new(JsException::from(e))),
-			}),
-			Ok(_) => Ok(()),
-		}
+		self.send_message(ws, &msg)
 	}
 
-	fn on_array_buf(&self, _ws: &Socket, app: &mut Application, buf: &ArrayBuffer) -> Result<NextHandler> {
-		console::log!("running received: {:?}", buf);
-		let array = js_sys::Uint8Array::new(buf);
-		let resp: std::result::Result<msg::RunningServerMessage, rmp_serde::decode::Error> =
-			rmp_serde::from_read_ref(&array.to_vec());
-		match resp {
-			Ok(message) => {
-				match message {
-					msg::RunningServerMessage::Lobby(lobby_msg) => self.on_lobby_message(lobby_msg, app),
-					msg::RunningServerMessage::Game { game_id, message } => match message {
-						msg::GameServerMessage::Action(action) => (),
-						msg::GameServerMessage::GameActionResponse { request_id, response } => (),
-						msg::GameServerMessage::UserJoin {
-							user_uid,
-							username,
-							role,
-						} => (),
-						msg::GameServerMessage::UserQuit { user_uid, role } => (),
-					}, //) => self.on_game_message(game_msg, app),
-				};
-				Ok(None)
-			}
-			Err(e) => Err(Error::ProtocolError {
-				details: "Desserialisation error".to_owned(),
-				should_reload: false,
-				source: Some(Box::new(e)),
-			}),
-		}
+	fn on_message(&self, ws: &Socket, app: &mut Application, msg: &[u8]) -> Result<NextHandler> {
+		console::log!("running received: {:?}", msg);
+		match ws.decode_message(msg)? {
+			msg::RunningServerMessage::Lobby(lobby_msg) => self.on_lobby_message(lobby_msg, app),
+			msg::RunningServerMessage::Game { game_id, message } => self.on_game_message(game_id, message, app),
+		};
+		Ok(None)
 	}
 
-	fn create_game(&mut self, ws: &Socket) -> Result<()> {
-		let request_id = self.request_index;
-		self.request_index += 1;
+	// Protocol 1 servers only know the default configuration, without clocks
+	fn create_game(&mut self, ws: &Socket, config: rules::GameConfig, position: Option<String>) -> Result<()> {
+		if config != untimed_config() || position.is_some() {
+			return Err(not_in_protocol_1("Game configuration"));
+		}
 		let msg = msg::RunningClientMessage::Lobby(msg::LobbyClientMessage::CreateGame {
-			request_uid: request_id.to_string(),
+			request_uid: self.next_request_id(),
 		});
-		let msg = match rmp_serde::encode::to_vec(&msg) {
-			Ok(msg) => msg,
-			Err(e) => {
-				return Err(Error::ImplError {
-					details: "Serialisation failed".to_owned(),
-					source: Some(Box::new(e)),
-				});
-			}
-		};
-		let msg_ref: &[u8] = &msg;
-		match ws.send_buffer(msg_ref) {
-			Err(e) => Err(Error::ConnectionError {
-				details: "Unable to send message to the server".to_owned(),
-				source: Some(Box::new(JsException::from(e))),
-			}),
-			Ok(_) => Ok(()),
-		}
+		self.send_message(ws, &msg)
+	}
+
+	fn send_game_action(&mut self, _ws: &Socket, _game_id: &str, _action: rules::Action) -> Result<()> {
+		Err(not_in_protocol_1("Playing"))
 	}
 
-	fn join_game(&self, ws: &Socket, game_id: &str) -> Result<()> {
+	fn ask_game_record(&mut self, _ws: &Socket, _game_id: &str) -> Result<()> {
+		Err(not_in_protocol_1("Game export"))
+	}
+
+	fn ask_game_analysis(&mut self, _ws: &Socket, _game_id: &str) -> Result<()> {
+		Err(not_in_protocol_1("Game analysis"))
+	}
+
+	fn join_game(&mut self, ws: &Socket, game_id: &str) -> Result<()> {
 		panic!("Network is not ready to join game");
 	}
 }
+
+fn not_in_protocol_1(feature: &str) -> Error {
+	Error::ProtocolError {
+		details: format!("{} is not available with protocol 1", feature),
+		should_reload: false,
+		source: None,
+	}
+}
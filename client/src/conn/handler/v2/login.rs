@@ -0,0 +1,34 @@
+use crate::app::Application;
+use crate::conn::handler::v1;
+use crate::conn::handler::v2::running::RunningHandler;
+use crate::conn::handler::MsgHandler;
+use crate::conn::handler::NextHandler;
+use crate::conn::socket::Socket;
+use crate::Result;
+
+/// Login messages did not change since protocol 1, only the handler which follows
+#[derive(Debug)]
+pub struct LoginHandler {
+	v1: v1::login::LoginHandler,
+}
+
+impl LoginHandler {
+	pub fn new() -> Self {
+		LoginHandler {
+			v1: v1::login::LoginHandler {},
+		}
+	}
+}
+
+impl MsgHandler for LoginHandler {
+	fn on_start(&self, ws: &Socket, app: &mut Application) -> Result<()> {
+		self.v1.on_start(ws, app)
+	}
+
+	fn on_message(&self, ws: &Socket, app: &mut Application, msg: &[u8]) -> Result<NextHandler> {
+		match self.v1.on_message(ws, app, msg)? {
+			Some(_) => Ok(Some(Box::new(RunningHandler::new()))),
+			None => Ok(None),
+		}
+	}
+}
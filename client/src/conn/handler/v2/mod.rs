@@ -0,0 +1,88 @@
+pub mod login;
+pub mod running;
+
+use crate::app::game;
+use crate::app::lobby;
+use ygame_ai as ai;
+use ygame_game as rules;
+use ygame_protocol::v2 as msg;
+
+use std::convert::TryFrom;
+
+// Game statuses and roles did not change since protocol 1, their conversions are in the v1 module
+
+// The server only sends the rule sets it has loaded, which are always valid ids
+fn game_config(config: msg::GameConfig) -> rules::GameConfig {
+	rules::GameConfig::try_from(config).unwrap_or_default()
+}
+
+impl From<msg::GameOverview> for lobby::GameOverview {
+	fn from(v: msg::GameOverview) -> lobby::GameOverview {
+		lobby::GameOverview {
+			id: v.id,
+			name: v.name,
+			status: v.status.into(),
+			config: game_config(v.config),
+		}
+	}
+}
+
+impl From<&msg::GameOverview> for lobby::GameOverview {
+	fn from(v: &msg::GameOverview) -> lobby::GameOverview {
+		let status = &v.status;
+		lobby::GameOverview {
+			id: v.id.clone(),
+			name: v.name.clone(),
+			status: status.into(),
+			config: game_config(v.config.clone()),
+		}
+	}
+}
+
+// Played move and analysis of the position before it
+pub fn ply_analysis(v: &msg::PlyAnalysis) -> (rules::Move, ai::Analysis) {
+	let analysis = ai::Analysis {
+		best_move: v.best_move.map(|mv| mv.into()),
+		score: v.score,
+		depth: v.depth,
+		principal_variation: v.principal_variation.iter().map(|mv| mv.into()).collect(),
+	};
+	(v.played.into(), analysis)
+}
+
+impl From<msg::GameDetails> for game::GameDetails {
+	fn from(v: msg::GameDetails) -> game::GameDetails {
+		game::GameDetails {
+			id: v.id,
+			name: v.name,
+			result: v.result.map(|result| result.into()),
+			seat_1_username: v.seat_1_username,
+			seat_2_username: v.seat_2_username,
+			config: game_config(v.config),
+			position: v.position.and_then(|p| rules::notation::parse_position(&p).ok()),
+		}
+	}
+}
+
+impl From<&msg::GameDetails> for game::GameDetails {
+	fn from(v: &msg::GameDetails) -> game::GameDetails {
+		game::GameDetails {
+			id: v.id.clone(),
+			name: v.name.clone(),
+			result: v.result.map(|result| result.into()),
+			seat_1_username: v.seat_1_username.clone(),
+			seat_2_username: v.seat_2_username.clone(),
+			config: game_config(v.config.clone()),
+			position: v
+				.position
+				.as_ref()
+				.and_then(|p| rules::notation::parse_position(p).ok()),
+		}
+	}
+}
+
+impl From<&msg::PlayedMove> for game::GameAction {
+	fn from(v: &msg::PlayedMove) -> game::GameAction {
+		game::GameAction::Move(v.mv.into())
+	}
+}
@@ -0,0 +1,153 @@
+use crate::app::Application;
+use crate::conn::handler::v2::ply_analysis;
+use crate::conn::handler::MsgHandler;
+use crate::conn::handler::NextHandler;
+use crate::conn::socket::Socket;
+use crate::err::Error;
+use crate::utils::console;
+use crate::Result;
+use ygame_game as rules;
+use ygame_protocol::v2 as msg;
+
+use std::convert::TryFrom;
+
+#[derive(Debug)]
+pub struct RunningHandler {
+	request_index: msg::RequestId,
+}
+
+impl RunningHandler {
+	pub fn new() -> Self {
+		RunningHandler { request_index: 1 }
+	}
+
+	fn on_lobby_message(&self, lobby_msg: msg::LobbyServerMessage, app: &mut Application) {
+		match lobby_msg {
+			msg::LobbyServerMessage::GameList { list } => {
+				app.on_lobby_game_list(list.iter().map(|v| v.into()).collect())
+			}
+			msg::LobbyServerMessage::RuleSets { list } => app.on_rule_sets(
+				list.into_iter()
+					.filter_map(|v| rules::RuleSet::try_from(v).ok())
+					.collect(),
+			),
+			msg::LobbyServerMessage::NewGame(info) => app.on_lobby_new_game(info.into()),
+			msg::LobbyServerMessage::GameInfoChanged(info) => app.on_lobby_game_changed(info.into()),
+			msg::LobbyServerMessage::GameRemoved { id } => app.on_lobby_game_removed(id),
+			msg::LobbyServerMessage::GameCreated { info, role, .. } => app.on_game_created(info.into(), role.into()),
+			msg::LobbyServerMessage::GameJoined { info, role, moves, .. } => {
+				app.on_game_joined(info.into(), role.into(), moves.iter().map(|v| v.into()).collect())
+			}
+			msg::LobbyServerMessage::GameRecord { game_id, record, .. } => app.on_game_record(game_id, record),
+			msg::LobbyServerMessage::GameAnalysis { game_id, plies, .. } => {
+				app.on_game_analysis(game_id, plies.iter().map(ply_analysis).collect())
+			}
+		};
+	}
+
+	fn on_game_message(&self, game_id: String, game_msg: msg::GameServerMessage, app: &mut Application) {
+		match game_msg {
+			msg::GameServerMessage::Started => app.on_game_started(game_id),
+			msg::GameServerMessage::MovePlayed(played) => {
+				console::log!("ply {}: {:?} played {:?}", played.ply, played.role, played.mv);
+				app.on_move_played(game_id, played.mv.into())
+			}
+			msg::GameServerMessage::GameOver(result) => app.on_game_over(game_id, result.into()),
+			msg::GameServerMessage::ActionResponse { request_id, response } => {
+				console::log!("request {}: {:?}", request_id, response)
+			}
+			msg::GameServerMessage::UserJoin { .. } => (),
+			msg::GameServerMessage::UserQuit { .. } => (),
+			msg::GameServerMessage::DrawOffered { role } => app.on_draw_offered(game_id, role.into()),
+			msg::GameServerMessage::DrawDeclined { .. } => app.on_draw_declined(game_id),
+			msg::GameServerMessage::TakebackRequested { role } => app.on_takeback_requested(game_id, role.into()),
+			msg::GameServerMessage::TakebackDeclined { .. } => app.on_takeback_declined(game_id),
+			msg::GameServerMessage::Rollback { plies } => app.on_rollback(game_id, plies),
+			msg::GameServerMessage::Clocks(clocks) => app.on_clocks(
+				game_id,
+				clocks.seat_1_ms,
+				clocks.seat_2_ms,
+				clocks.running.map(|role| role.into()),
+			),
+		}
+	}
+
+	fn send_message(&self, ws: &Socket, msg: &msg::RunningClientMessage) -> Result<()> {
+		ws.send_message(msg)
+	}
+
+	fn next_request_id(&mut self) -> msg::RequestId {
+		let request_id = self.request_index;
+		self.request_index = self.request_index.wrapping_add(1);
+		request_id
+	}
+}
+
+impl MsgHandler for RunningHandler {
+	fn on_start(&self, ws: &Socket, _app: &mut Application) -> Result<()> {
+		let msg = msg::RunningClientMessage::Lobby(msg::LobbyClientMessage::AskGameList);
+		self.send_message(ws, &msg)?;
+		let msg = msg::RunningClientMessage::Lobby(msg::LobbyClientMessage::AskRuleSets);
+		self.send_message(ws, &msg)
+	}
+
+	fn on_message(&self, ws: &Socket, app: &mut Application, msg: &[u8]) -> Result<NextHandler> {
+		console::log!("running received: {:?}", msg);
+		match ws.decode_message(msg)? {
+			msg::RunningServerMessage::Lobby(lobby_msg) => self.on_lobby_message(lobby_msg, app),
+			msg::RunningServerMessage::Game { game_id, message } => self.on_game_message(game_id, message, app),
+		};
+		Ok(None)
+	}
+
+	fn create_game(&mut self, ws: &Socket, config: rules::GameConfig, position: Option<String>) -> Result<()> {
+		let msg = msg::RunningClientMessage::Lobby(msg::LobbyClientMessage::CreateGame {
+			request_id: self.next_request_id(),
+			config: config.into(),
+			position,
+		});
+		self.send_message(ws, &msg)
+	}
+
+	fn join_game(&mut self, ws: &Socket, game_id: &str) -> Result<()> {
+		let msg = msg::RunningClientMessage::Lobby(msg::LobbyClientMessage::JoinGame {
+			request_id: self.next_request_id(),
+			game_id: game_id.to_owned(),
+		});
+		self.send_message(ws, &msg)
+	}
+
+	fn send_game_action(&mut self, ws: &Socket, game_id: &str, action: rules::Action) -> Result<()> {
+		let action = match msg::GameAction::try_from(action) {
+			Ok(action) => action,
+			Err(action) => {
+				return Err(Error::ImplError {
+					details: format!("{} is not a player action", action),
+					source: None,
+				});
+			}
+		};
+		let msg = msg::RunningClientMessage::Game {
+			game_id: game_id.to_owned(),
+			request_id: self.next_request_id(),
+			action,
+		};
+		self.send_message(ws, &msg)
+	}
+
+	fn ask_game_record(&mut self, ws: &Socket, game_id: &str) -> Result<()> {
+		let msg = msg::RunningClientMessage::Lobby(msg::LobbyClientMessage::AskGameRecord {
+			request_id: self.next_request_id(),
+			game_id: game_id.to_owned(),
+		});
+		self.send_message(ws, &msg)
+	}
+
+	fn ask_game_analysis(&mut self, ws: &Socket, game_id: &str) -> Result<()> {
+		let msg = msg::RunningClientMessage::Lobby(msg::LobbyClientMessage::AskGameAnalysis {
+			request_id: self.next_request_id(),
+			game_id: game_id.to_owned(),
+		});
+		self.send_message(ws, &msg)
+	}
+}
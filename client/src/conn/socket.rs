@@ -1,13 +1,23 @@
 use crate::ep::AppEntryPoint;
 use crate::ep::EpRef;
 use crate::ep::SocketEntryPoint;
+use crate::err::Error;
+use crate::err::JsException;
+use ygame_protocol::handshake::Capability;
+use ygame_protocol::handshake::Encoding;
 
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::Cell;
+use std::cell::RefCell;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::{ErrorEvent, MessageEvent, WebSocket};
 
 pub struct Socket {
 	websocket: WebSocket,
+	encoding: Cell<Encoding>, // of the login and running messages, chosen during the handshake
+	capabilities: RefCell<Vec<Capability>>, // agreed during the handshake
 	on_error_cb: Closure<dyn FnMut(ErrorEvent)>,
 	on_open_cb: Closure<dyn FnMut(JsValue)>,
 	on_msg_cb: Closure<dyn FnMut(MessageEvent)>,
@@ -49,6 +59,8 @@ impl Socket {
 
 		Socket {
 			websocket: ws,
+			encoding: Cell::new(Encoding::default()),
+			capabilities: RefCell::new(Vec::new()),
 			on_error_cb: onerror_callback,
 			on_open_cb: onopen_callback,
 			on_msg_cb: onmessage_callback,
@@ -64,6 +76,72 @@ impl Socket {
 		self.websocket.send_with_str(txt)
 	}
 
+	pub fn encoding(&self) -> Encoding {
+		self.encoding.get()
+	}
+
+	pub fn set_encoding(&self, encoding: Encoding) {
+		self.encoding.set(encoding)
+	}
+
+	pub fn has_capability(&self, capability: Capability) -> bool {
+		self.capabilities.borrow().contains(&capability)
+	}
+
+	pub fn set_capabilities(&self, capabilities: Vec<Capability>) {
+		self.capabilities.replace(capabilities);
+	}
+
+	/// Send a login or running message, in a binary frame for MessagePack and a text one for JSON
+	pub fn send_message<T: Serialize>(&self, msg: &T) -> crate::Result<()> {
+		let sent = match self.encoding() {
+			Encoding::MessagePack => match rmp_serde::encode::to_vec(msg) {
+				Ok(msg) => self.send_buffer(&msg),
+				Err(e) => {
+					return Err(Error::ImplError {
+						details: "Serialisation failed".to_owned(),
+						source: Some(Box::new(e)),
+					});
+				}
+			},
+			Encoding::Json => match serde_json::to_string(msg) {
+				Ok(msg) => self.send_text(&msg),
+				Err(e) => {
+					return Err(Error::ImplError {
+						details: "Serialisation failed".to_owned(),
+						source: Some(Box::new(e)),
+					});
+				}
+			},
+			Encoding::Unknown => {
+				return Err(Error::ImplError {
+					details: "Unknown encoding".to_owned(),
+					source: None,
+				});
+			}
+		};
+		match sent {
+			Err(e) => Err(Error::ConnectionError {
+				details: "Unable to send message to the server".to_owned(),
+				source: Some(Box::new(JsException::from(e))),
+			}),
+			Ok(_) => Ok(()),
+		}
+	}
+
+	pub fn decode_message<T: DeserializeOwned>(&self, msg: &[u8]) -> crate::Result<T> {
+		let decoded: std::result::Result<T, Box<dyn std::error::Error>> = match self.encoding() {
+			Encoding::MessagePack => rmp_serde::from_read_ref(msg).map_err(Box::from),
+			Encoding::Json => serde_json::from_slice(msg).map_err(Box::from),
+			Encoding::Unknown => Err(Box::from("unknown encoding")),
+		};
+		decoded.map_err(|e| Error::ProtocolError {
+			details: "Desserialisation error".to_owned(),
+			should_reload: false,
+			source: Some(e),
+		})
+	}
+
 	fn close(&self) {
 		self.clean_callbacks();
 		self.websocket.close().ok();
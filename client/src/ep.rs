@@ -7,6 +7,7 @@ use crate::conn::Connection;
 use crate::err::Error;
 use crate::utils::console;
 use crate::Result;
+use ygame_game as rules;
 
 use std::cell::Ref;
 use std::cell::RefCell;
@@ -43,6 +44,13 @@ pub trait AppEntryPoint {
 	fn on_websocket_error(&self, e: ErrorEvent) -> Result<()>;
 	fn set_state(&self, state: AppState) -> Result<()>;
 	fn create_game(&self) -> Result<()>;
+	fn send_game_action(&self, action: rules::Action) -> Result<()>;
+	fn play_move(&self, text: String) -> Result<()>;
+	fn restart_puzzle(&self) -> Result<()>;
+	fn next_puzzle(&self) -> Result<()>;
+	fn download_game_record(&self) -> Result<()>;
+	fn ask_hint(&self) -> Result<()>;
+	fn on_book_loaded(&self, bytes: Vec<u8>) -> Result<()>;
 	fn on_connected(&self) -> Result<()>;
 	fn on_back_event(&self, state: String) -> Result<()>;
 }
@@ -193,7 +201,35 @@ impl AppEntryPoint for EpRef {
 	}
 
 	fn create_game(&self) -> Result<()> {
-		borrow_mut_app!(self, |mut app| app.create_game())
+		borrow_mut_app_and_conn!(self, |mut app, conn| app.create_game(conn))
+	}
+
+	fn send_game_action(&self, action: rules::Action) -> Result<()> {
+		borrow_mut_app_and_conn!(self, |mut app, conn| app.send_game_action(action, conn))
+	}
+
+	fn play_move(&self, text: String) -> Result<()> {
+		borrow_mut_app_and_conn!(self, |mut app, conn| app.play_move(text, conn))
+	}
+
+	fn restart_puzzle(&self) -> Result<()> {
+		borrow_mut_app!(self, |mut app| app.restart_puzzle())
+	}
+
+	fn next_puzzle(&self) -> Result<()> {
+		borrow_mut_app_and_conn!(self, |mut app, conn| app.next_puzzle(conn))
+	}
+
+	fn download_game_record(&self) -> Result<()> {
+		borrow_mut_app_and_conn!(self, |app, conn| app.download_game_record(conn))
+	}
+
+	fn ask_hint(&self) -> Result<()> {
+		borrow_mut_app_and_conn!(self, |app, conn| app.ask_hint(conn))
+	}
+
+	fn on_book_loaded(&self, bytes: Vec<u8>) -> Result<()> {
+		borrow_mut_app!(self, |mut app| app.on_book_loaded(bytes))
 	}
 
 	fn on_connected(&self) -> Result<()> {
@@ -1,5 +1,9 @@
+use crate::err::{Error, JsException};
+use crate::Result;
+
 use wasm_bindgen::prelude::*;
-use web_sys::Document;
+use wasm_bindgen::JsCast;
+use web_sys::{Blob, BlobPropertyBag, Document, HtmlAnchorElement, Url, XmlHttpRequest, XmlHttpRequestResponseType};
 
 #[wasm_bindgen]
 extern "C" {
@@ -58,3 +62,64 @@ pub fn to_full_url(document: &Document, path: &str) -> String {
 	let origin = location.origin().expect("No origin for location");
 	format!("{}/{}", origin.trim_end_matches("/"), path.trim_start_matches("/"))
 }
+
+/// Make the browser save the given text as a file
+pub fn download_text(document: &Document, filename: &str, text: &str) -> Result<()> {
+	let to_error = |e: JsValue| Error::ImplError {
+		details: "Unable to download file".to_owned(),
+		source: Some(Box::new(JsException::from(e))),
+	};
+	let parts = js_sys::Array::of1(&JsValue::from_str(text));
+	let mut options = BlobPropertyBag::new();
+	options.type_("text/plain;charset=utf-8");
+	let blob = Blob::new_with_str_sequence_and_options(&parts, &options).map_err(to_error)?;
+	let url = Url::create_object_url_with_blob(&blob).map_err(to_error)?;
+
+	let link: HtmlAnchorElement = document
+		.create_element("a")
+		.map_err(to_error)?
+		.dyn_into()
+		.map_err(|_| to_error(JsValue::from_str("not an anchor")))?;
+	link.set_href(&url);
+	link.set_download(filename);
+	link.click();
+	Url::revoke_object_url(&url).map_err(to_error)
+}
+
+/// Download of a binary file, the callback is only called if it succeeds
+///
+/// Dropping the request cancels it.
+pub struct BytesRequest {
+	request: XmlHttpRequest,
+	on_load_cb: Closure<dyn FnMut()>,
+}
+
+pub fn fetch_bytes<F: FnMut(Vec<u8>) + 'static>(url: &str, mut callback: F) -> Result<BytesRequest> {
+	let to_error = |e: JsValue| Error::ImplError {
+		details: format!("Unable to download {}", url),
+		source: Some(Box::new(JsException::from(e))),
+	};
+	let request = XmlHttpRequest::new().map_err(to_error)?;
+	request.open("GET", url).map_err(to_error)?;
+	request.set_response_type(XmlHttpRequestResponseType::Arraybuffer);
+	let loaded_request = request.clone();
+	let handler = move || {
+		if loaded_request.status() != Ok(200) {
+			return;
+		}
+		if let Ok(buffer) = loaded_request.response() {
+			callback(js_sys::Uint8Array::new(&buffer).to_vec());
+		}
+	};
+	let on_load_cb = Closure::wrap(Box::new(handler) as Box<dyn FnMut()>);
+	request.set_onload(Some(on_load_cb.as_ref().unchecked_ref()));
+	request.send().map_err(to_error)?;
+	Ok(BytesRequest { request, on_load_cb })
+}
+
+impl Drop for BytesRequest {
+	fn drop(&mut self) {
+		self.request.set_onload(None);
+		self.request.abort().ok();
+	}
+}
@@ -1,5 +1,5 @@
 use wasm_bindgen::JsCast;
-use web_sys::{Document, HtmlElement, HtmlInputElement};
+use web_sys::{Document, HtmlElement, HtmlInputElement, HtmlSelectElement};
 
 #[macro_export]
 macro_rules! by_id {
@@ -49,6 +49,14 @@ pub fn read_field_by_id(document: &Document, id: &str) -> String {
 	by_id!(document, id, HtmlInputElement).value()
 }
 
+pub fn read_select_by_id(document: &Document, id: &str) -> String {
+	by_id!(document, id, HtmlSelectElement).value()
+}
+
+pub fn is_checked_by_id(document: &Document, id: &str) -> bool {
+	by_id!(document, id, HtmlInputElement).checked()
+}
+
 pub fn set_inner_text_by_class(document: &Document, classname: &str, content: &str) {
 	let elements = document.get_elements_by_class_name(classname);
 	for i in 0..elements.length() {
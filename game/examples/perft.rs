@@ -0,0 +1,69 @@
+//! Count the move sequences from a position, or check the whole reference corpus
+//!
+//! ```text
+//! cargo run --release -p ygame-game --example perft                                  # check the corpus
+//! cargo run --release -p ygame-game --example perft -- 5 "x5o/7/7/7/7/7/o5x x 0 0"   # count of each first move
+//! ```
+
+use std::process;
+use std::time::Instant;
+
+use ygame_game::notation;
+use ygame_game::perft;
+
+fn main() {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	match args.len() {
+		0 => check_corpus(),
+		1 | 2 => {
+			let depth = args[0]
+				.parse()
+				.unwrap_or_else(|_| fail(&format!("Invalid depth '{}'", args[0])));
+			let position = args.get(1).map(String::as_str).unwrap_or("x5o/7/7/7/7/7/o5x x 0 0");
+			let mut state = notation::parse_position(position).unwrap_or_else(|e| fail(&e.to_string()));
+			let start = Instant::now();
+			let counts = perft::divide(&mut state, depth);
+			for (mv, count) in counts.iter() {
+				println!("{}: {}", mv, count);
+			}
+			let total: u64 = counts.iter().map(|(_, count)| count).sum();
+			println!("\n{} moves, {} nodes in {:?}", counts.len(), total, start.elapsed());
+		}
+		_ => fail("Usage: perft [DEPTH [POSITION]]"),
+	}
+}
+
+fn check_corpus() {
+	let references = perft::parse_corpus(perft::CORPUS).unwrap_or_else(|e| fail(&e.to_string()));
+	let mut failures = 0;
+	for reference in references {
+		let mut state = reference.position.clone();
+		let position = notation::format_position(&state);
+		for (depth, expected) in reference.counts.iter().enumerate() {
+			let depth = depth as u32 + 1;
+			let start = Instant::now();
+			let count = perft::perft(&mut state, depth);
+			let status = if count == *expected { "ok" } else { "FAILED" };
+			println!(
+				"{} depth {}: {} (expected {}) in {:?} {}",
+				position,
+				depth,
+				count,
+				expected,
+				start.elapsed(),
+				status
+			);
+			if count != *expected {
+				failures += 1;
+			}
+		}
+	}
+	if failures > 0 {
+		fail(&format!("{} wrong counts", failures));
+	}
+}
+
+fn fail(message: &str) -> ! {
+	eprintln!("{}", message);
+	process::exit(1);
+}
@@ -0,0 +1,53 @@
+//! Play a game in the terminal, both sides typing their moves
+//!
+//! ```text
+//! cargo run -p ygame-game --example play                                  # from the standard position
+//! cargo run -p ygame-game --example play -- "x3o/5/5/5/o3x x 0 0" ascii  # plain ASCII drawing
+//! ```
+
+use std::io::{self, BufRead};
+use std::process;
+
+use ygame_game::render::{self, Style};
+use ygame_game::{notation, GameState, Move};
+
+fn main() {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	let mut state = match args.first() {
+		Some(position) => notation::parse_position(position).unwrap_or_else(|e| fail(&e.to_string())),
+		None => GameState::new(),
+	};
+	let style: &Style = match args.get(1).map(String::as_str) {
+		None | Some("unicode") => &render::UNICODE,
+		Some("ascii") => &render::ASCII,
+		Some(_) => fail("Usage: play [POSITION [unicode|ascii]]"),
+	};
+
+	println!("{}", render::position(&state, style));
+	if state.outcome().is_some() {
+		return;
+	}
+	let stdin = io::stdin();
+	for line in stdin.lock().lines() {
+		let line = line.unwrap_or_else(|e| fail(&e.to_string()));
+		let mv = match line.trim().parse::<Move>() {
+			Ok(mv) => mv,
+			Err(e) => {
+				println!("{}", e);
+				continue;
+			}
+		};
+		match state.play(&mv) {
+			Ok(_) => println!("{}", render::position(&state, style)),
+			Err(e) => println!("{}", e),
+		}
+		if state.outcome().is_some() {
+			break;
+		}
+	}
+}
+
+fn fail(message: &str) -> ! {
+	eprintln!("{}", message);
+	process::exit(1);
+}
@@ -0,0 +1,123 @@
+use std::fmt::{self, Display};
+
+use crate::Player;
+
+pub const DEFAULT_BOARD_SIZE: u8 = 7;
+
+/// A square of the board, `col` 0 is the leftmost file and `row` 0 the bottom rank
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square {
+	pub col: u8,
+	pub row: u8,
+}
+
+impl Square {
+	pub fn new(col: u8, row: u8) -> Square {
+		Square { col, row }
+	}
+
+	/// Number of king steps between two squares
+	pub fn distance(&self, other: &Square) -> u8 {
+		let col_dist = (self.col as i16 - other.col as i16).unsigned_abs() as u8;
+		let row_dist = (self.row as i16 - other.row as i16).unsigned_abs() as u8;
+		std::cmp::max(col_dist, row_dist)
+	}
+}
+
+impl Display for Square {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}{}", (b'a' + self.col) as char, self.row + 1)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cell {
+	Empty,
+	Blocked,
+	Stone(Player),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Board {
+	size: u8,
+	cells: Vec<Cell>, // row major, starting from the bottom left corner
+}
+
+impl Board {
+	pub fn empty(size: u8) -> Board {
+		Board {
+			size,
+			cells: vec![Cell::Empty; size as usize * size as usize],
+		}
+	}
+
+	/// Empty board with the two stones of each player in opposite corners
+	pub fn with_corners(size: u8) -> Board {
+		let mut board = Board::empty(size);
+		let last = size - 1;
+		board.set(Square::new(0, last), Cell::Stone(Player::One));
+		board.set(Square::new(last, 0), Cell::Stone(Player::One));
+		board.set(Square::new(0, 0), Cell::Stone(Player::Two));
+		board.set(Square::new(last, last), Cell::Stone(Player::Two));
+		board
+	}
+
+	pub fn size(&self) -> u8 {
+		self.size
+	}
+
+	pub fn contains(&self, square: Square) -> bool {
+		square.col < self.size && square.row < self.size
+	}
+
+	pub fn get(&self, square: Square) -> Option<Cell> {
+		if self.contains(square) {
+			Some(self.cells[self.index(square)])
+		} else {
+			None
+		}
+	}
+
+	/// Panics if the square is outside of the board
+	pub fn set(&mut self, square: Square, cell: Cell) {
+		assert!(self.contains(square), "square {} is outside of the board", square);
+		let index = self.index(square);
+		self.cells[index] = cell;
+	}
+
+	pub fn squares(&self) -> impl Iterator<Item = Square> {
+		let size = self.size;
+		(0..size).flat_map(move |row| (0..size).map(move |col| Square::new(col, row)))
+	}
+
+	pub fn count(&self, player: Player) -> usize {
+		self.cells.iter().filter(|cell| **cell == Cell::Stone(player)).count()
+	}
+
+	pub fn count_empty(&self) -> usize {
+		self.cells.iter().filter(|cell| **cell == Cell::Empty).count()
+	}
+
+	/// Squares of the board located exactly `distance` king steps away from `square`
+	pub fn ring(&self, square: Square, distance: u8) -> impl Iterator<Item = Square> {
+		let size = self.size as i16;
+		let dist = distance as i16;
+		let (col, row) = (square.col as i16, square.row as i16);
+		(-dist..=dist)
+			.flat_map(move |d_row| (-dist..=dist).map(move |d_col| (d_col, d_row)))
+			.filter(move |(d_col, d_row)| std::cmp::max(d_col.abs(), d_row.abs()) == dist)
+			.map(move |(d_col, d_row)| (col + d_col, row + d_row))
+			.filter(move |(c, r)| *c >= 0 && *r >= 0 && *c < size && *r < size)
+			.map(|(c, r)| Square::new(c as u8, r as u8))
+	}
+
+	fn index(&self, square: Square) -> usize {
+		square.row as usize * self.size as usize + square.col as usize
+	}
+}
+
+impl Default for Board {
+	fn default() -> Board {
+		Board::with_corners(DEFAULT_BOARD_SIZE)
+	}
+}
@@ -0,0 +1,220 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::{ParseError, Player};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeControl {
+	/// Each player starts with `base` and gets `increment` back after each of their moves
+	Increment { base: Duration, increment: Duration },
+	/// Each move must be played within the given time, unused time is lost
+	PerMove(Duration),
+}
+
+impl TimeControl {
+	/// Time available to each player at the start of the game
+	pub fn initial_time(&self) -> Duration {
+		match self {
+			TimeControl::Increment { base, .. } => *base,
+			TimeControl::PerMove(time) => *time,
+		}
+	}
+}
+
+impl Default for TimeControl {
+	/// Five minutes plus five seconds per move
+	fn default() -> TimeControl {
+		TimeControl::Increment {
+			base: Duration::from_secs(300),
+			increment: Duration::from_secs(5),
+		}
+	}
+}
+
+impl Display for TimeControl {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			TimeControl::Increment { base, increment } => {
+				write_secs(f, *base)?;
+				f.write_str("+")?;
+				write_secs(f, *increment)
+			}
+			TimeControl::PerMove(time) => {
+				write_secs(f, *time)?;
+				f.write_str("/move")
+			}
+		}
+	}
+}
+
+// Whole seconds, followed by the milliseconds when there are some: `1.5` for 1500 ms
+fn write_secs(f: &mut fmt::Formatter, time: Duration) -> fmt::Result {
+	match time.subsec_millis() {
+		0 => write!(f, "{}", time.as_secs()),
+		millis => write!(
+			f,
+			"{}.{}",
+			time.as_secs(),
+			format!("{:03}", millis).trim_end_matches('0')
+		),
+	}
+}
+
+impl FromStr for TimeControl {
+	type Err = ParseError;
+
+	/// Parse the `Display` form of a time control, in seconds with up to three decimals
+	fn from_str(text: &str) -> Result<TimeControl, ParseError> {
+		let secs = |field: &str| {
+			let (whole, millis) = match field.find('.') {
+				Some(dot) => (&field[..dot], &field[dot + 1..]),
+				None => (field, "0"),
+			};
+			if millis.is_empty() || millis.len() > 3 || !millis.bytes().all(|digit| digit.is_ascii_digit()) {
+				return Err(ParseError::BadTimeControl(text.to_owned()));
+			}
+			let millis: u64 = format!("{:0<3}", millis).parse().unwrap_or(0);
+			whole
+				.parse()
+				.map(|secs| Duration::from_secs(secs) + Duration::from_millis(millis))
+				.map_err(|_| ParseError::BadTimeControl(text.to_owned()))
+		};
+		if let Some(time) = text.strip_suffix("/move") {
+			return Ok(TimeControl::PerMove(secs(time)?));
+		}
+		match text.find('+') {
+			Some(sep) => Ok(TimeControl::Increment {
+				base: secs(&text[..sep])?,
+				increment: secs(&text[sep + 1..])?,
+			}),
+			None => Err(ParseError::BadTimeControl(text.to_owned())),
+		}
+	}
+}
+
+/// Remaining time of both players
+///
+/// Clocks do not measure time themselves, the caller reports the time spent by each player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clocks {
+	control: TimeControl,
+	remaining: [Duration; 2],
+}
+
+impl Clocks {
+	pub fn new(control: TimeControl) -> Clocks {
+		let initial = control.initial_time();
+		Clocks {
+			control,
+			remaining: [initial, initial],
+		}
+	}
+
+	pub fn control(&self) -> TimeControl {
+		self.control
+	}
+
+	pub fn remaining(&self, player: Player) -> Duration {
+		self.remaining[Clocks::index(player)]
+	}
+
+	/// Remove the time spent by the player, returns false if they ran out of time
+	pub fn spend(&mut self, player: Player, elapsed: Duration) -> bool {
+		let remaining = &mut self.remaining[Clocks::index(player)];
+		match remaining.checked_sub(elapsed) {
+			Some(left) if left > Duration::from_secs(0) => {
+				*remaining = left;
+				true
+			}
+			_ => {
+				*remaining = Duration::from_secs(0);
+				false
+			}
+		}
+	}
+
+	/// The player has completed their move: add the increment or reset the move time
+	pub fn end_turn(&mut self, player: Player) {
+		let remaining = &mut self.remaining[Clocks::index(player)];
+		match self.control {
+			TimeControl::Increment { increment, .. } => *remaining += increment,
+			TimeControl::PerMove(time) => *remaining = time,
+		}
+	}
+
+	fn index(player: Player) -> usize {
+		match player {
+			Player::One => 0,
+			Player::Two => 1,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn increment_and_flag_fall() {
+		let mut clocks = Clocks::new(TimeControl::Increment {
+			base: Duration::from_secs(10),
+			increment: Duration::from_secs(2),
+		});
+		assert!(clocks.spend(Player::One, Duration::from_secs(4)));
+		clocks.end_turn(Player::One);
+		assert_eq!(clocks.remaining(Player::One), Duration::from_secs(8));
+		assert_eq!(clocks.remaining(Player::Two), Duration::from_secs(10));
+		assert!(!clocks.spend(Player::Two, Duration::from_secs(10)));
+		assert_eq!(clocks.remaining(Player::Two), Duration::from_secs(0));
+	}
+
+	#[test]
+	fn per_move_resets() {
+		let mut clocks = Clocks::new(TimeControl::PerMove(Duration::from_secs(30)));
+		assert!(clocks.spend(Player::One, Duration::from_secs(25)));
+		clocks.end_turn(Player::One);
+		assert_eq!(clocks.remaining(Player::One), Duration::from_secs(30));
+		assert!(!clocks.spend(Player::One, Duration::from_secs(31)));
+	}
+
+	#[test]
+	fn time_controls_round_trip() {
+		for text in ["300+5", "60+0", "30/move", "180+0.5", "1.5/move", "0.25+0.125"].iter() {
+			assert_eq!(text.parse::<TimeControl>().unwrap().to_string(), *text);
+		}
+		for text in [
+			"",
+			"300",
+			"+5",
+			"5+",
+			"a/move",
+			"300+5+1",
+			"1./move",
+			"1.2345/move",
+			"60+.5",
+			"60+0.-5",
+		]
+		.iter()
+		{
+			assert!(text.parse::<TimeControl>().is_err(), "'{}' should not parse", text);
+		}
+	}
+
+	#[test]
+	fn fractional_seconds() {
+		let control = TimeControl::Increment {
+			base: Duration::from_secs(60),
+			increment: Duration::from_millis(500),
+		};
+		assert_eq!(control.to_string(), "60+0.5");
+		assert_eq!(
+			TimeControl::PerMove(Duration::from_millis(1500)).to_string(),
+			"1.5/move"
+		);
+		assert_eq!(
+			"1.5/move".parse(),
+			Ok(TimeControl::PerMove(Duration::from_millis(1500)))
+		);
+	}
+}
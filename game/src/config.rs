@@ -0,0 +1,143 @@
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+use crate::{Player, RuleSet, TimeControl, UserRole, DEFAULT_BOARD_SIZE};
+
+/// Smallest board a game can be created with
+pub const MIN_BOARD_SIZE: u8 = 5;
+/// Biggest board a game can be created with
+pub const MAX_BOARD_SIZE: u8 = 11;
+
+// Bounds of the time controls, to reject games that could never end or never start
+const MAX_BASE_TIME: Duration = Duration::from_secs(3 * 3600);
+const MAX_INCREMENT: Duration = Duration::from_secs(60);
+const MIN_MOVE_TIME: Duration = Duration::from_secs(5);
+const MAX_MOVE_TIME: Duration = Duration::from_secs(24 * 3600);
+
+/// Options chosen when a game is created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GameConfig {
+	pub rule_set: RuleSet,
+	pub board_size: u8,
+	/// None for untimed games
+	pub time_control: Option<TimeControl>,
+	pub first_player: Player,
+	pub rated: bool,
+	/// Private games are not listed in the lobby, they can only be joined with their id
+	pub private: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+	BoardSize(u8),
+	TimeControl(TimeControl),
+	UnknownRuleSet(RuleSet),
+	Refused(RuleSet),
+	PositionSize(u8),
+	FinishedPosition,
+}
+
+impl Display for ConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ConfigError::BoardSize(size) => write!(
+				f,
+				"Board size {} is not between {} and {}",
+				size, MIN_BOARD_SIZE, MAX_BOARD_SIZE
+			),
+			ConfigError::TimeControl(control) => write!(f, "Time control {} is not allowed", control),
+			ConfigError::UnknownRuleSet(rule_set) => write!(f, "Rule set {} is not available", rule_set),
+			ConfigError::Refused(rule_set) => write!(f, "Rule set {} does not accept this configuration", rule_set),
+			ConfigError::PositionSize(size) => write!(f, "The starting position is for a {}x{} board", size, size),
+			ConfigError::FinishedPosition => write!(f, "The game is already over in the starting position"),
+		}
+	}
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Default for GameConfig {
+	fn default() -> GameConfig {
+		GameConfig {
+			rule_set: RuleSet::Standard,
+			board_size: DEFAULT_BOARD_SIZE,
+			time_control: Some(TimeControl::default()),
+			first_player: Player::One,
+			rated: false,
+			private: false,
+		}
+	}
+}
+
+impl GameConfig {
+	/// Check the options common to every rule set, the rest is checked by `Rules::validate`
+	pub fn validate(&self) -> Result<(), ConfigError> {
+		if self.board_size < MIN_BOARD_SIZE || self.board_size > MAX_BOARD_SIZE {
+			return Err(ConfigError::BoardSize(self.board_size));
+		}
+		let time_ok = match self.time_control {
+			None => true,
+			Some(TimeControl::Increment { base, increment }) => {
+				base > Duration::from_secs(0) && base <= MAX_BASE_TIME && increment <= MAX_INCREMENT
+			}
+			Some(TimeControl::PerMove(time)) => time >= MIN_MOVE_TIME && time <= MAX_MOVE_TIME,
+		};
+		match self.time_control {
+			Some(control) if !time_ok => Err(ConfigError::TimeControl(control)),
+			_ => Ok(()),
+		}
+	}
+
+	/// Name of the variant in game records: the rule set, followed by the board size if it is not the default one
+	pub fn variant(&self) -> String {
+		if self.board_size == DEFAULT_BOARD_SIZE {
+			self.rule_set.id().to_owned()
+		} else {
+			format!("{} {}x{}", self.rule_set, self.board_size, self.board_size)
+		}
+	}
+
+	/// Whether engine analysis may be shown to a user while the game is in progress: only to the observers of an
+	/// unrated game
+	pub fn allows_analysis(&self, role: UserRole) -> bool {
+		!self.rated && role == UserRole::Observer
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{GameState, Rules, Standard, STANDARD_VARIANT};
+
+	#[test]
+	fn default_config_is_the_standard_game() {
+		let config = GameConfig::default();
+		assert_eq!(config.validate(), Ok(()));
+		assert_eq!(Standard.initial_state(&config), GameState::new());
+		assert_eq!(config.variant(), STANDARD_VARIANT);
+	}
+
+	#[test]
+	fn invalid_configs() {
+		let mut config = GameConfig {
+			board_size: 3,
+			..GameConfig::default()
+		};
+		assert_eq!(config.validate(), Err(ConfigError::BoardSize(3)));
+		config.board_size = 9;
+		config.first_player = Player::Two;
+		assert_eq!(config.validate(), Ok(()));
+		assert_eq!(Standard.initial_state(&config).side_to_move(), Player::Two);
+		assert_eq!(config.variant(), "standard 9x9");
+
+		config.time_control = Some(TimeControl::PerMove(Duration::from_secs(1)));
+		assert!(config.validate().is_err());
+		config.time_control = Some(TimeControl::Increment {
+			base: Duration::from_secs(0),
+			increment: Duration::from_secs(10),
+		});
+		assert!(config.validate().is_err());
+		config.time_control = None;
+		assert_eq!(config.validate(), Ok(()));
+	}
+}
@@ -1,10 +1,59 @@
+mod board;
+mod clock;
+mod config;
+mod moves;
+pub mod notation;
+pub mod perft;
+pub mod plugin;
+pub mod puzzle;
+mod record;
+pub mod render;
+mod result;
+pub mod rules;
+mod state;
+pub mod zobrist;
+
 use std::fmt::{self, Display};
 
+pub use board::{Board, Cell, Square, DEFAULT_BOARD_SIZE};
+pub use clock::{Clocks, TimeControl};
+pub use config::{ConfigError, GameConfig, MAX_BOARD_SIZE, MIN_BOARD_SIZE};
+pub use moves::Move;
+pub use notation::ParseError;
+pub use record::{GameRecord, STANDARD_VARIANT};
+pub use result::{EndReason, GameResult};
+pub use rules::{Blocked, PluginId, RuleSet, Rules, Standard};
+pub use state::{GameState, IllegalMove, Undo, MOVE_LIMIT};
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Action {
 	Init,
-	Move,
-	Finished,
+	Move(Move),
+	Resign,
+	OfferDraw,
+	AcceptDraw,
+	DeclineDraw,
+	RequestTakeback,
+	AcceptTakeback,
+	DeclineTakeback,
+	Finished(GameResult),
+}
+
+impl Display for Action {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Action::Init => write!(f, "init"),
+			Action::Move(mv) => mv.fmt(f),
+			Action::Resign => write!(f, "resign"),
+			Action::OfferDraw => write!(f, "offer draw"),
+			Action::AcceptDraw => write!(f, "accept draw"),
+			Action::DeclineDraw => write!(f, "decline draw"),
+			Action::RequestTakeback => write!(f, "request takeback"),
+			Action::AcceptTakeback => write!(f, "accept takeback"),
+			Action::DeclineTakeback => write!(f, "decline takeback"),
+			Action::Finished(result) => result.fmt(f),
+		}
+	}
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
@@ -14,6 +63,17 @@ pub enum UserRole {
 	Observer,
 }
 
+impl UserRole {
+	/// The player controlled by this role, if any
+	pub fn player(&self) -> Option<Player> {
+		match self {
+			UserRole::Seat1 => Some(Player::One),
+			UserRole::Seat2 => Some(Player::Two),
+			UserRole::Observer => None,
+		}
+	}
+}
+
 impl Display for UserRole {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match self {
@@ -24,6 +84,34 @@ impl Display for UserRole {
 	}
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, PartialOrd, Ord)]
+pub enum Player {
+	One,
+	Two,
+}
+
+impl Player {
+	pub fn opponent(&self) -> Player {
+		match self {
+			Player::One => Player::Two,
+			Player::Two => Player::One,
+		}
+	}
+
+	pub fn seat(&self) -> UserRole {
+		match self {
+			Player::One => UserRole::Seat1,
+			Player::Two => UserRole::Seat2,
+		}
+	}
+}
+
+impl Display for Player {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.seat().fmt(f)
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	#[test]
@@ -0,0 +1,39 @@
+use std::fmt::{self, Display};
+
+use crate::Square;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Move {
+	/// Put a new stone next to one of our stones
+	Grow { to: Square },
+	/// Move one of our stones two squares away
+	Jump { from: Square, to: Square },
+	/// Only allowed when no other move is available
+	Pass,
+}
+
+impl Move {
+	pub fn from(&self) -> Option<Square> {
+		match self {
+			Move::Jump { from, .. } => Some(*from),
+			_ => None,
+		}
+	}
+
+	pub fn to(&self) -> Option<Square> {
+		match self {
+			Move::Grow { to } | Move::Jump { to, .. } => Some(*to),
+			Move::Pass => None,
+		}
+	}
+}
+
+impl Display for Move {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Move::Grow { to } => write!(f, "{}", to),
+			Move::Jump { from, to } => write!(f, "{}{}", from, to),
+			Move::Pass => write!(f, "pass"),
+		}
+	}
+}
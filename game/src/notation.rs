@@ -0,0 +1,305 @@
+//! Text notation of squares, moves and positions
+//!
+//! Squares are written `a1` to `g7` (file then rank), moves as their target square for a grow (`b6`), both squares
+//! for a jump (`a7c5`) and `pass`. A position fits on one line, like the FEN of chess:
+//! `x5o/7/7/7/7/7/o5x x 0 0` is made of the ranks from the top one, separated by `/` (`x` for a stone of player 1,
+//! `o` for player 2, `#` for a blocked square and digits for runs of empty squares), then the player to move, the
+//! number of plies since the last grow move and the number of plies played. The two counters may be omitted.
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::{Board, Cell, GameState, Move, Player, Square};
+
+/// Biggest board the notation can describe, files go from `a` to `z`
+const MAX_NOTATION_RANKS: u8 = 26;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+	BadSquare(String),
+	BadMove(String),
+	BadBoard(String),
+	BadPlayer(String),
+	BadCounter(String),
+	BadHeader(String),
+	BadResult(String),
+	BadTimeControl(String),
+	BadRuleSet(String),
+	MissingField(&'static str),
+	TrailingData(String),
+}
+
+impl Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ParseError::BadSquare(text) => write!(f, "Invalid square '{}'", text),
+			ParseError::BadMove(text) => write!(f, "Invalid move '{}'", text),
+			ParseError::BadBoard(reason) => write!(f, "Invalid board: {}", reason),
+			ParseError::BadPlayer(text) => write!(f, "Invalid player '{}'", text),
+			ParseError::BadCounter(text) => write!(f, "Invalid move counter '{}'", text),
+			ParseError::BadHeader(line) => write!(f, "Invalid header '{}'", line),
+			ParseError::BadResult(text) => write!(f, "Invalid result '{}'", text),
+			ParseError::BadTimeControl(text) => write!(f, "Invalid time control '{}'", text),
+			ParseError::BadRuleSet(text) => write!(f, "Unknown rule set '{}'", text),
+			ParseError::MissingField(field) => write!(f, "Missing {}", field),
+			ParseError::TrailingData(text) => write!(f, "Unexpected '{}' at the end", text),
+		}
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+impl FromStr for Square {
+	type Err = ParseError;
+
+	fn from_str(text: &str) -> Result<Square, ParseError> {
+		match parse_square(text) {
+			Some((square, "")) => Ok(square),
+			_ => Err(ParseError::BadSquare(text.to_owned())),
+		}
+	}
+}
+
+impl FromStr for Move {
+	type Err = ParseError;
+
+	fn from_str(text: &str) -> Result<Move, ParseError> {
+		if text == "pass" {
+			return Ok(Move::Pass);
+		}
+		match parse_square(text) {
+			Some((to, "")) => Ok(Move::Grow { to }),
+			Some((from, rest)) => match parse_square(rest) {
+				Some((to, "")) => Ok(Move::Jump { from, to }),
+				_ => Err(ParseError::BadMove(text.to_owned())),
+			},
+			None => Err(ParseError::BadMove(text.to_owned())),
+		}
+	}
+}
+
+impl FromStr for GameState {
+	type Err = ParseError;
+
+	fn from_str(text: &str) -> Result<GameState, ParseError> {
+		parse_position(text)
+	}
+}
+
+/// One line description of the position, see the module documentation
+pub fn format_position(state: &GameState) -> String {
+	format!(
+		"{} {} {} {}",
+		format_board(state.board()),
+		player_char(state.side_to_move()),
+		state.halfmove_clock(),
+		state.ply()
+	)
+}
+
+pub fn parse_position(text: &str) -> Result<GameState, ParseError> {
+	let mut fields = text.split_whitespace();
+	let board = parse_board(fields.next().ok_or(ParseError::MissingField("board"))?)?;
+	let side_to_move = match fields.next() {
+		Some("x") => Player::One,
+		Some("o") => Player::Two,
+		Some(other) => return Err(ParseError::BadPlayer(other.to_owned())),
+		None => return Err(ParseError::MissingField("player to move")),
+	};
+	let halfmove_clock = parse_counter(fields.next())?;
+	let ply = parse_counter(fields.next())?;
+	if let Some(extra) = fields.next() {
+		return Err(ParseError::TrailingData(extra.to_owned()));
+	}
+	Ok(GameState::from_board(board, side_to_move).with_counters(halfmove_clock, ply))
+}
+
+pub fn format_board(board: &Board) -> String {
+	let mut text = String::new();
+	for row in (0..board.size()).rev() {
+		let mut empty = 0;
+		for col in 0..board.size() {
+			let c = match board.get(Square::new(col, row)) {
+				Some(Cell::Stone(player)) => player_char(player),
+				Some(Cell::Blocked) => '#',
+				_ => {
+					empty += 1;
+					continue;
+				}
+			};
+			if empty > 0 {
+				text.push_str(&empty.to_string());
+				empty = 0;
+			}
+			text.push(c);
+		}
+		if empty > 0 {
+			text.push_str(&empty.to_string());
+		}
+		if row > 0 {
+			text.push('/');
+		}
+	}
+	text
+}
+
+pub fn parse_board(text: &str) -> Result<Board, ParseError> {
+	let ranks: Vec<&str> = text.split('/').collect();
+	if ranks.len() > MAX_NOTATION_RANKS as usize {
+		return Err(ParseError::BadBoard(format!("more than {} ranks", MAX_NOTATION_RANKS)));
+	}
+	let size = ranks.len() as u8;
+	let mut board = Board::empty(size);
+	for (i, rank) in ranks.iter().enumerate() {
+		let row = size - 1 - i as u8;
+		let mut col: usize = 0;
+		let mut chars = rank.chars().peekable();
+		let too_long = || ParseError::BadBoard(format!("rank {} is too long", row + 1));
+		while let Some(c) = chars.next() {
+			let cell = match c {
+				'x' => Cell::Stone(Player::One),
+				'o' => Cell::Stone(Player::Two),
+				'#' => Cell::Blocked,
+				'1'..='9' => {
+					let mut run = c.to_digit(10).unwrap_or(0) as usize;
+					while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+						// A run never exceeds the rank, the count can not overflow
+						if col + run > size as usize {
+							return Err(too_long());
+						}
+						run = run * 10 + digit as usize;
+						chars.next();
+					}
+					col += run;
+					if col > size as usize {
+						return Err(too_long());
+					}
+					continue;
+				}
+				_ => return Err(ParseError::BadBoard(format!("unexpected '{}' in rank {}", c, row + 1))),
+			};
+			if col >= size as usize {
+				return Err(too_long());
+			}
+			board.set(Square::new(col as u8, row), cell);
+			col += 1;
+		}
+		if col != size as usize {
+			return Err(ParseError::BadBoard(format!(
+				"rank {} is not {} squares long",
+				row + 1,
+				size
+			)));
+		}
+	}
+	Ok(board)
+}
+
+fn parse_counter(field: Option<&str>) -> Result<u32, ParseError> {
+	match field {
+		Some(text) => text.parse().map_err(|_| ParseError::BadCounter(text.to_owned())),
+		None => Ok(0),
+	}
+}
+
+fn player_char(player: Player) -> char {
+	match player {
+		Player::One => 'x',
+		Player::Two => 'o',
+	}
+}
+
+// A square at the start of the text, and the rest of the text
+fn parse_square(text: &str) -> Option<(Square, &str)> {
+	let mut chars = text.chars();
+	let col = match chars.next() {
+		Some(c @ 'a'..='z') => c as u8 - b'a',
+		_ => return None,
+	};
+	let digits = text[1..].chars().take_while(|c| c.is_ascii_digit()).count();
+	let rank: u8 = text[1..1 + digits].parse().ok()?;
+	if rank == 0 || rank > MAX_NOTATION_RANKS {
+		return None;
+	}
+	Some((Square::new(col, rank - 1), &text[1 + digits..]))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Action;
+
+	#[test]
+	fn squares_and_moves_round_trip() {
+		for text in ["a1", "g7", "b12", "z26"].iter() {
+			assert_eq!(text.parse::<Square>().unwrap().to_string(), *text);
+		}
+		for text in ["b6", "a7c5", "a10c12", "pass"].iter() {
+			assert_eq!(text.parse::<Move>().unwrap().to_string(), *text);
+		}
+		assert_eq!(
+			"a7c5".parse::<Move>(),
+			Ok(Move::Jump {
+				from: Square::new(0, 6),
+				to: Square::new(2, 4)
+			})
+		);
+		for text in ["", "a", "a0", "7a", "A1", "a1b", "a1b2c3", "passe"].iter() {
+			assert!(text.parse::<Move>().is_err(), "'{}' should not parse", text);
+		}
+	}
+
+	#[test]
+	fn initial_position() {
+		let state = GameState::new();
+		assert_eq!(format_position(&state), "x5o/7/7/7/7/7/o5x x 0 0");
+		assert_eq!(parse_position("x5o/7/7/7/7/7/o5x x"), Ok(state));
+	}
+
+	#[test]
+	fn positions_round_trip() {
+		let mut state = GameState::new();
+		let line = ["b6", "b2", "g1e1", "f6", "e1c2"];
+		for mv in line.iter() {
+			state.apply(&Action::Move(mv.parse().unwrap())).unwrap();
+		}
+		let text = format_position(&state);
+		assert_eq!(text, "x5o/1x3o1/7/7/7/1xx4/o6 o 1 5");
+		let parsed: GameState = text.parse().unwrap();
+		assert_eq!(parsed, state);
+		assert_eq!(parsed.hash(), state.hash());
+
+		for text in ["#1x/o1#/3 o 12 40", "11x/12/12/12/12/12/12/12/12/12/12/o11 x"].iter() {
+			let state = parse_position(text).unwrap();
+			assert_eq!(parse_position(&format_position(&state)), Ok(state));
+		}
+	}
+
+	#[test]
+	fn bad_positions() {
+		let bad = [
+			"",
+			"x5o/7/7/7/7/7/o5x",
+			"x5o/7/7/7/7/7/o5x z",
+			"x5o/7/7/7/7/7/o5x x -1",
+			"x5o/7/7/7/7/7/o5x x 0 0 0",
+			"x5o/7/7/7/7/7/o6x x",
+			"x5o/7/7/7/7/7/o4x x",
+			"x5o/7/7/7/7/7 x",
+			"x5p/7/7/7/7/7/o5x x",
+		];
+		for text in bad.iter() {
+			assert!(parse_position(text).is_err(), "'{}' should not parse", text);
+		}
+	}
+
+	#[test]
+	fn oversized_runs() {
+		let text = format!("x{}/7/7/7/7/7/o5x x", "9".repeat(40));
+		assert!(matches!(parse_position(&text), Err(ParseError::BadBoard(_))));
+		assert!(matches!(
+			parse_board("x5o/7/7/7/7/7/o99x"),
+			Err(ParseError::BadBoard(_))
+		));
+	}
+}
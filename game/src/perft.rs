@@ -0,0 +1,166 @@
+//! Move generation counts (perft), to check the rules against positions with known results
+//!
+//! `perft(state, depth)` is the number of move sequences of `depth` plies which can be played from the position, the
+//! same count as in chess engines. Any mistake in the move generation, the captures or the end of game detection
+//! changes it. The reference counts are in [`CORPUS`], one position per line followed by the counts from depth 1:
+//! `x5o/7/7/7/7/7/o5x x 0 0 ; 16 256 6460`.
+
+use crate::notation::{self, ParseError};
+use crate::{GameState, Move};
+
+/// Reference positions and their counts, checked by the tests and by `cargo run --example perft`
+pub const CORPUS: &str = include_str!("perft.txt");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+	pub position: GameState,
+	pub counts: Vec<u64>, // counts[0] is the count at depth 1
+}
+
+/// Number of move sequences of `depth` plies, the position is restored before returning
+pub fn perft(state: &mut GameState, depth: u32) -> u64 {
+	if depth == 0 {
+		return 1;
+	}
+	let moves = state.legal_moves();
+	if depth == 1 {
+		return moves.len() as u64;
+	}
+	let mut count = 0;
+	for mv in moves.iter() {
+		let undo = state.play(mv).expect("legal moves can be played");
+		count += perft(state, depth - 1);
+		state.undo(undo);
+	}
+	count
+}
+
+/// Count of each first move, to find which one is wrong when a total does not match
+pub fn divide(state: &mut GameState, depth: u32) -> Vec<(Move, u64)> {
+	if depth == 0 {
+		return Vec::new();
+	}
+	let mut counts = Vec::new();
+	for mv in state.legal_moves() {
+		let undo = state.play(&mv).expect("legal moves can be played");
+		counts.push((mv, perft(state, depth - 1)));
+		state.undo(undo);
+	}
+	counts
+}
+
+/// Read a corpus like [`CORPUS`], empty lines and lines starting with `#` are ignored
+pub fn parse_corpus(text: &str) -> Result<Vec<Reference>, ParseError> {
+	let mut references = Vec::new();
+	for line in text.lines().map(str::trim) {
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+		let (position, counts) = match line.find(';') {
+			Some(index) => (&line[..index], &line[index + 1..]),
+			None => return Err(ParseError::MissingField("perft counts")),
+		};
+		let counts = counts
+			.split_whitespace()
+			.map(|count| count.parse().map_err(|_| ParseError::BadCounter(count.to_owned())))
+			.collect::<Result<Vec<u64>, ParseError>>()?;
+		references.push(Reference {
+			position: notation::parse_position(position)?,
+			counts,
+		});
+	}
+	Ok(references)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{render, zobrist};
+
+	// Deeper counts are left to the example, built in release mode
+	const MAX_TEST_NODES: u64 = 20_000;
+
+	#[test]
+	fn corpus_counts() {
+		let references = parse_corpus(CORPUS).unwrap();
+		assert!(references.len() >= 8);
+		for reference in references {
+			let mut state = reference.position.clone();
+			for (depth, expected) in reference.counts.iter().enumerate() {
+				if *expected > MAX_TEST_NODES {
+					break;
+				}
+				let depth = depth as u32 + 1;
+				let text = notation::format_position(&state);
+				assert_eq!(perft(&mut state, depth), *expected, "{} at depth {}", text, depth);
+			}
+			assert_eq!(state, reference.position);
+		}
+	}
+
+	#[test]
+	fn divide_sums_to_perft() {
+		let mut state = GameState::new();
+		let counts = divide(&mut state, 3);
+		assert_eq!(counts.len(), 16);
+		assert_eq!(counts.iter().map(|(_, count)| count).sum::<u64>(), perft(&mut state, 3));
+	}
+
+	// SplitMix64, the crate has no dependency to get random numbers from
+	struct Rng(u64);
+
+	impl Rng {
+		fn below(&mut self, bound: usize) -> usize {
+			self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+			let mut z = self.0;
+			z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+			z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+			((z ^ (z >> 31)) % bound as u64) as usize
+		}
+	}
+
+	/// Along random games from every corpus position: playing any legal move then taking it back restores the exact
+	/// position, and the incremental hash always matches the hash computed from scratch.
+	#[test]
+	fn play_then_undo_restores_position() {
+		let references = parse_corpus(CORPUS).unwrap();
+		for seed in 0..12 {
+			let mut rng = Rng(seed);
+			let mut state = references[seed as usize % references.len()].position.clone();
+			let mut played = Vec::new();
+			while played.len() < 100 {
+				let moves = state.legal_moves();
+				if moves.is_empty() {
+					break;
+				}
+				for mv in moves.iter() {
+					let before = state.clone();
+					let undo = state.play(mv).unwrap();
+					assert_eq!(
+						state.hash(),
+						zobrist::position_hash(state.board(), state.side_to_move()),
+						"hash after {} from {}",
+						mv,
+						notation::format_position(&before)
+					);
+					state.undo(undo);
+					assert_eq!(
+						state,
+						before,
+						"undo of {} from\n{}",
+						mv,
+						render::position(&before, &render::UNICODE)
+					);
+				}
+				let mv = moves[rng.below(moves.len())];
+				played.push((state.clone(), state.play(&mv).unwrap()));
+			}
+			// Take back the whole game
+			while let Some((before, undo)) = played.pop() {
+				state.undo(undo);
+				assert_eq!(state, before);
+				assert_eq!(state.hash(), before.hash());
+			}
+		}
+	}
+}
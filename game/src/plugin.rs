@@ -0,0 +1,295 @@
+//! Rule sets compiled to WebAssembly and loaded by the server at runtime
+//!
+//! A plugin is a wasm module exporting its `memory` and the following functions, which all exchange text:
+//!
+//! | export                             | input                  | output                                   |
+//! |------------------------------------|------------------------|------------------------------------------|
+//! | `ygame_alloc(len: u32) -> u32`     |                        | buffer of `len` bytes for the input      |
+//! | `ygame_free(ptr: u32, len: u32)`   |                        | release an output once it has been read  |
+//! | `ygame_id() -> u64`                |                        | id of the rule set, see [`PluginId`]     |
+//! | `ygame_validate(ptr, len) -> u64`  | configuration          | empty, or why the configuration is wrong |
+//! | `ygame_initial_state(ptr, len)`    | configuration          | position                                 |
+//! | `ygame_legal_moves(ptr, len)`      | position               | moves separated by spaces                |
+//! | `ygame_apply(ptr, len)`            | position `\n` move     | new position, empty if the move is wrong |
+//! | `ygame_outcome(ptr, len)`          | position               | empty, or the result of the game         |
+//!
+//! Inputs are written by the host in a buffer obtained from `ygame_alloc`, and released by the called function.
+//! Outputs are returned as `ptr << 32 | len`. Positions and moves use the [`notation`](crate::notation) module, a
+//! configuration is `<board size> <first player>` like `7 x`, and a result is the winner (`x`, `o` or `=` for a draw)
+//! followed by the reason, like `o no more stones`.
+//!
+//! A plugin crate only has to implement [`Rules`] and call [`export_plugin!`](crate::export_plugin), then be built
+//! for the `wasm32-unknown-unknown` target. The functions are only exported on that target, the text functions of
+//! [`guest`] stay available natively to test the rules.
+//!
+//! [`PluginId`]: crate::PluginId
+
+use crate::notation::{self, ParseError};
+use crate::{EndReason, GameConfig, GameResult, Move, Player, Rules};
+
+pub fn format_config(config: &GameConfig) -> String {
+	format!("{} {}", config.board_size, player_char(config.first_player))
+}
+
+/// Configuration of the rules, the other options keep their default value
+pub fn parse_config(text: &str) -> Result<GameConfig, ParseError> {
+	let mut fields = text.split_whitespace();
+	let size = fields.next().ok_or(ParseError::MissingField("board size"))?;
+	let board_size = size.parse().map_err(|_| ParseError::BadBoard(size.to_owned()))?;
+	let first_player = match fields.next() {
+		Some("x") => Player::One,
+		Some("o") => Player::Two,
+		Some(other) => return Err(ParseError::BadPlayer(other.to_owned())),
+		None => return Err(ParseError::MissingField("first player")),
+	};
+	if let Some(extra) = fields.next() {
+		return Err(ParseError::TrailingData(extra.to_owned()));
+	}
+	Ok(GameConfig {
+		board_size,
+		first_player,
+		..GameConfig::default()
+	})
+}
+
+pub fn format_outcome(outcome: Option<&GameResult>) -> String {
+	match outcome {
+		None => String::new(),
+		Some(result) => {
+			let winner = match result.winner {
+				Some(player) => player_char(player),
+				None => '=',
+			};
+			format!("{} {}", winner, result.reason)
+		}
+	}
+}
+
+pub fn parse_outcome(text: &str) -> Result<Option<GameResult>, ParseError> {
+	if text.is_empty() {
+		return Ok(None);
+	}
+	let (winner, reason) = text.split_at(text.find(' ').ok_or_else(|| ParseError::BadResult(text.to_owned()))?);
+	let reason: EndReason = reason[1..].parse()?;
+	match winner {
+		"x" => Ok(Some(GameResult::win(Player::One, reason))),
+		"o" => Ok(Some(GameResult::win(Player::Two, reason))),
+		"=" => Ok(Some(GameResult::draw(reason))),
+		_ => Err(ParseError::BadResult(text.to_owned())),
+	}
+}
+
+pub fn format_moves(moves: &[Move]) -> String {
+	moves.iter().map(|mv| mv.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+fn player_char(player: Player) -> char {
+	match player {
+		Player::One => 'x',
+		Player::Two => 'o',
+	}
+}
+
+/// Implementation of the exported functions on the text of their input, see the module documentation
+pub mod guest {
+	use super::*;
+
+	pub fn validate(rules: &dyn Rules, input: &str) -> String {
+		let result = parse_config(input).map_err(|e| e.to_string()).and_then(|config| {
+			config.validate().map_err(|e| e.to_string())?;
+			rules.validate(&config).map_err(|e| e.to_string())
+		});
+		result.err().unwrap_or_default()
+	}
+
+	pub fn initial_state(rules: &dyn Rules, input: &str) -> String {
+		let config = parse_config(input).unwrap_or_default();
+		notation::format_position(&rules.initial_state(&config))
+	}
+
+	pub fn legal_moves(rules: &dyn Rules, input: &str) -> String {
+		match notation::parse_position(input) {
+			Ok(state) => format_moves(&rules.legal_moves(&state)),
+			Err(_) => String::new(),
+		}
+	}
+
+	pub fn apply(rules: &dyn Rules, input: &str) -> String {
+		let mut lines = input.lines();
+		let state = lines.next().map(notation::parse_position);
+		let mv = lines.next().map(|mv| mv.parse::<Move>());
+		match (state, mv) {
+			(Some(Ok(mut state)), Some(Ok(mv))) => match rules.apply(&mut state, &mv) {
+				Ok(()) => notation::format_position(&state),
+				Err(_) => String::new(),
+			},
+			_ => String::new(),
+		}
+	}
+
+	pub fn outcome(rules: &dyn Rules, input: &str) -> String {
+		match notation::parse_position(input) {
+			Ok(state) => format_outcome(rules.outcome(&state).as_ref()),
+			Err(_) => String::new(),
+		}
+	}
+
+	/// Buffer of `len` bytes, owned by the caller until it is given back to `take` or `free`
+	#[cfg(target_arch = "wasm32")]
+	pub fn alloc(len: u32) -> u32 {
+		let buffer = vec![0u8; len as usize].into_boxed_slice();
+		Box::into_raw(buffer) as *mut u8 as usize as u32
+	}
+
+	/// # Safety
+	///
+	/// `ptr` and `len` must come from `alloc` or `output`, and not have been released yet.
+	#[cfg(target_arch = "wasm32")]
+	pub unsafe fn free(ptr: u32, len: u32) {
+		drop(take(ptr, len));
+	}
+
+	/// Text written by the host in a buffer from `alloc`, which is released
+	///
+	/// # Safety
+	///
+	/// Same as `free`.
+	#[cfg(target_arch = "wasm32")]
+	pub unsafe fn take(ptr: u32, len: u32) -> String {
+		let slice = std::ptr::slice_from_raw_parts_mut(ptr as usize as *mut u8, len as usize);
+		String::from_utf8_lossy(&Box::from_raw(slice)).into_owned()
+	}
+
+	/// Hand the text to the host, which releases it with `ygame_free`
+	#[cfg(target_arch = "wasm32")]
+	pub fn output(text: String) -> u64 {
+		let len = text.len() as u64;
+		let ptr = Box::into_raw(text.into_bytes().into_boxed_slice()) as *mut u8 as usize as u64;
+		ptr << 32 | len
+	}
+}
+
+/// Export the plugin functions of a rule set, the expression must implement [`Rules`](crate::Rules)
+///
+/// ```ignore
+/// ygame_game::export_plugin!("misere", Misere);
+/// ```
+#[macro_export]
+macro_rules! export_plugin {
+	($id:expr, $rules:expr) => {
+		#[cfg(target_arch = "wasm32")]
+		#[no_mangle]
+		pub extern "C" fn ygame_alloc(len: u32) -> u32 {
+			$crate::plugin::guest::alloc(len)
+		}
+
+		/// # Safety
+		///
+		/// Only called by the host on its own outputs.
+		#[cfg(target_arch = "wasm32")]
+		#[no_mangle]
+		pub unsafe extern "C" fn ygame_free(ptr: u32, len: u32) {
+			$crate::plugin::guest::free(ptr, len)
+		}
+
+		#[cfg(target_arch = "wasm32")]
+		#[no_mangle]
+		pub extern "C" fn ygame_id() -> u64 {
+			$crate::plugin::guest::output(String::from($id))
+		}
+
+		/// # Safety
+		///
+		/// Only called by the host with a buffer from `ygame_alloc`.
+		#[cfg(target_arch = "wasm32")]
+		#[no_mangle]
+		pub unsafe extern "C" fn ygame_validate(ptr: u32, len: u32) -> u64 {
+			let input = $crate::plugin::guest::take(ptr, len);
+			$crate::plugin::guest::output($crate::plugin::guest::validate(&$rules, &input))
+		}
+
+		/// # Safety
+		///
+		/// Only called by the host with a buffer from `ygame_alloc`.
+		#[cfg(target_arch = "wasm32")]
+		#[no_mangle]
+		pub unsafe extern "C" fn ygame_initial_state(ptr: u32, len: u32) -> u64 {
+			let input = $crate::plugin::guest::take(ptr, len);
+			$crate::plugin::guest::output($crate::plugin::guest::initial_state(&$rules, &input))
+		}
+
+		/// # Safety
+		///
+		/// Only called by the host with a buffer from `ygame_alloc`.
+		#[cfg(target_arch = "wasm32")]
+		#[no_mangle]
+		pub unsafe extern "C" fn ygame_legal_moves(ptr: u32, len: u32) -> u64 {
+			let input = $crate::plugin::guest::take(ptr, len);
+			$crate::plugin::guest::output($crate::plugin::guest::legal_moves(&$rules, &input))
+		}
+
+		/// # Safety
+		///
+		/// Only called by the host with a buffer from `ygame_alloc`.
+		#[cfg(target_arch = "wasm32")]
+		#[no_mangle]
+		pub unsafe extern "C" fn ygame_apply(ptr: u32, len: u32) -> u64 {
+			let input = $crate::plugin::guest::take(ptr, len);
+			$crate::plugin::guest::output($crate::plugin::guest::apply(&$rules, &input))
+		}
+
+		/// # Safety
+		///
+		/// Only called by the host with a buffer from `ygame_alloc`.
+		#[cfg(target_arch = "wasm32")]
+		#[no_mangle]
+		pub unsafe extern "C" fn ygame_outcome(ptr: u32, len: u32) -> u64 {
+			let input = $crate::plugin::guest::take(ptr, len);
+			$crate::plugin::guest::output($crate::plugin::guest::outcome(&$rules, &input))
+		}
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{GameState, Standard};
+
+	#[test]
+	fn configs_and_outcomes_round_trip() {
+		let config = GameConfig {
+			board_size: 9,
+			first_player: Player::Two,
+			..GameConfig::default()
+		};
+		assert_eq!(format_config(&config), "9 o");
+		assert_eq!(parse_config("9 o"), Ok(config));
+		assert!(parse_config("9").is_err());
+		assert!(parse_config("nine x").is_err());
+
+		let results = [
+			None,
+			Some(GameResult::win(Player::Two, EndReason::NoStones)),
+			Some(GameResult::draw(EndReason::MoveLimit)),
+		];
+		for result in results.iter() {
+			assert_eq!(parse_outcome(&format_outcome(result.as_ref())), Ok(*result));
+		}
+		assert_eq!(format_outcome(results[1].as_ref()), "o no more stones");
+	}
+
+	#[test]
+	fn guest_functions() {
+		let start = guest::initial_state(&Standard, "7 x");
+		assert_eq!(start, notation::format_position(&GameState::new()));
+		assert_eq!(guest::validate(&Standard, "7 x"), "");
+		assert_ne!(guest::validate(&Standard, "2 x"), "");
+		assert_eq!(guest::legal_moves(&Standard, &start).split(' ').count(), 16);
+		assert_eq!(
+			guest::apply(&Standard, &format!("{}\nb6", start)),
+			"x5o/1x5/7/7/7/7/o5x o 0 1"
+		);
+		assert_eq!(guest::apply(&Standard, &format!("{}\nd4", start)), "");
+		assert_eq!(guest::outcome(&Standard, &start), "");
+	}
+}
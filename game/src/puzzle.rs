@@ -0,0 +1,224 @@
+//! Puzzles: a position and the line of moves to find from it
+//!
+//! The side to move in the position solves the puzzle. The solution alternates its moves and the replies of the
+//! opponent, and ends with a move of the solver. The bundled puzzles are in [`PUZZLES`], one per line:
+//! `5 ; 4o/5/4o/2x2/ox2x o 3 7 ; e3c1 d1 b1d2`.
+
+use std::fmt::{self, Display};
+
+use crate::notation::{self, ParseError};
+use crate::{GameState, IllegalMove, Move, Player};
+
+/// Puzzles available in every client, checked by the tests
+pub const PUZZLES: &str = include_str!("puzzles.txt");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+	pub id: String,
+	pub position: GameState,
+	pub solution: Vec<Move>,
+}
+
+impl Puzzle {
+	pub fn solver(&self) -> Player {
+		self.position.side_to_move()
+	}
+
+	/// Number of moves the solver has to find
+	pub fn moves_to_find(&self) -> usize {
+		self.solution.iter().step_by(2).count()
+	}
+}
+
+/// Read puzzles like [`PUZZLES`], empty lines and lines starting with `#` are ignored
+pub fn parse_puzzles(text: &str) -> Result<Vec<Puzzle>, ParseError> {
+	let mut puzzles = Vec::new();
+	for line in text.lines().map(str::trim) {
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+		let mut fields = line.split(';').map(str::trim);
+		let id = fields
+			.next()
+			.filter(|id| !id.is_empty())
+			.ok_or(ParseError::MissingField("puzzle id"))?;
+		let position = fields.next().ok_or(ParseError::MissingField("puzzle position"))?;
+		let solution = fields.next().ok_or(ParseError::MissingField("puzzle solution"))?;
+		if let Some(extra) = fields.next() {
+			return Err(ParseError::TrailingData(extra.to_owned()));
+		}
+		let solution = solution
+			.split_whitespace()
+			.map(str::parse)
+			.collect::<Result<Vec<Move>, ParseError>>()?;
+		if solution.is_empty() {
+			return Err(ParseError::MissingField("puzzle solution"));
+		}
+		puzzles.push(Puzzle {
+			id: id.to_owned(),
+			position: notation::parse_position(position)?,
+			solution,
+		});
+	}
+	Ok(puzzles)
+}
+
+/// The bundled puzzles
+pub fn bundled() -> Vec<Puzzle> {
+	parse_puzzles(PUZZLES).expect("bundled puzzles are valid")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrongMove {
+	Illegal(IllegalMove),
+	/// Legal, but not the solution
+	NotSolution(Move),
+	Solved,
+}
+
+impl Display for WrongMove {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			WrongMove::Illegal(e) => e.fmt(f),
+			WrongMove::NotSolution(mv) => write!(f, "Move {} is not the solution", mv),
+			WrongMove::Solved => write!(f, "The puzzle is already solved"),
+		}
+	}
+}
+
+impl std::error::Error for WrongMove {}
+
+/// What happened after a right move of the solver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+	/// The opponent answered with this move, the solver plays again
+	Reply(Move),
+	Solved,
+}
+
+/// A player trying to solve a puzzle
+#[derive(Debug, Clone)]
+pub struct Attempt {
+	puzzle: Puzzle,
+	state: GameState,
+	played: usize, // moves of the solution already on the board
+}
+
+impl Attempt {
+	pub fn new(puzzle: Puzzle) -> Attempt {
+		Attempt {
+			state: puzzle.position.clone(),
+			puzzle,
+			played: 0,
+		}
+	}
+
+	pub fn puzzle(&self) -> &Puzzle {
+		&self.puzzle
+	}
+
+	pub fn state(&self) -> &GameState {
+		&self.state
+	}
+
+	pub fn is_solved(&self) -> bool {
+		self.played >= self.puzzle.solution.len()
+	}
+
+	/// Play a move of the solver, and the reply of the opponent when the solution goes on
+	///
+	/// A move reaching the same board as the expected one is right too, and so is any move winning the game at the end
+	/// of the line. Wrong moves leave the position unchanged.
+	pub fn play(&mut self, mv: Move) -> Result<Step, WrongMove> {
+		let expected = match self.puzzle.solution.get(self.played) {
+			Some(expected) => *expected,
+			None => return Err(WrongMove::Solved),
+		};
+		let mut after = self.state.clone();
+		after.play(&mv).map_err(WrongMove::Illegal)?;
+		let mut expected_after = self.state.clone();
+		expected_after.play(&expected).expect("puzzle solutions are legal");
+		let last = self.played + 1 == self.puzzle.solution.len();
+		let wins = after.outcome().and_then(|result| result.winner) == Some(self.puzzle.solver());
+		if after.board() != expected_after.board() && !(last && wins) {
+			return Err(WrongMove::NotSolution(mv));
+		}
+		self.state = after;
+		self.played += 1;
+		match self.puzzle.solution.get(self.played) {
+			Some(reply) => {
+				self.state.play(reply).expect("puzzle solutions are legal");
+				self.played += 1;
+				Ok(Step::Reply(*reply))
+			}
+			None => Ok(Step::Solved),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::render;
+
+	#[test]
+	fn bundled_solutions_win() {
+		let puzzles = bundled();
+		assert!(puzzles.len() >= 10);
+		for puzzle in puzzles {
+			assert_eq!(puzzle.solution.len() % 2, 1, "puzzle {} ends with a reply", puzzle.id);
+			assert!(
+				puzzle.position.outcome().is_none(),
+				"puzzle {} is already over\n{}",
+				puzzle.id,
+				render::position(&puzzle.position, &render::UNICODE)
+			);
+			let mut attempt = Attempt::new(puzzle.clone());
+			for mv in puzzle.solution.iter().step_by(2) {
+				attempt.play(*mv).unwrap();
+			}
+			assert!(attempt.is_solved());
+			let result = attempt.state().outcome();
+			assert_eq!(
+				result.and_then(|r| r.winner),
+				Some(puzzle.solver()),
+				"puzzle {}",
+				puzzle.id
+			);
+		}
+	}
+
+	#[test]
+	fn wrong_moves_are_rejected() {
+		let puzzle = bundled().into_iter().find(|puzzle| puzzle.id == "5").unwrap();
+		let mut attempt = Attempt::new(puzzle);
+		assert!(matches!(
+			attempt.play("e5".parse().unwrap()),
+			Err(WrongMove::Illegal(_))
+		));
+		assert_eq!(
+			attempt.play("d4".parse().unwrap()),
+			Err(WrongMove::NotSolution("d4".parse().unwrap()))
+		);
+		assert_eq!(attempt.state(), &attempt.puzzle().position);
+		assert_eq!(
+			attempt.play("e3c1".parse().unwrap()),
+			Ok(Step::Reply("d1".parse().unwrap()))
+		);
+		assert_eq!(attempt.play("b1d2".parse().unwrap()), Ok(Step::Solved));
+		assert_eq!(attempt.play("a1".parse().unwrap()), Err(WrongMove::Solved));
+	}
+
+	#[test]
+	fn invalid_puzzles() {
+		assert_eq!(
+			parse_puzzles("1 ; x3o/5/5/5/o3x x 0 0"),
+			Err(ParseError::MissingField("puzzle solution"))
+		);
+		assert_eq!(
+			parse_puzzles(" ; x3o/5/5/5/o3x x 0 0 ; b4"),
+			Err(ParseError::MissingField("puzzle id"))
+		);
+		assert!(parse_puzzles("1 ; x3o/5/5/5/o3x x 0 0 ; b4 zz").is_err());
+	}
+}
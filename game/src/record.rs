@@ -0,0 +1,314 @@
+//! Game records, a PGN-like text export of a whole game
+//!
+//! ```text
+//! [Game "Brave Otter"]
+//! [Seat1 "alice"]
+//! [Seat2 "bob"]
+//! [Date "2021.09.30"]
+//! [Variant "standard"]
+//! [Result "1-0"]
+//! [Termination "resignation"]
+//!
+//! 1. b6 b2 2. g1e1 f6 1-0
+//! ```
+//!
+//! Unknown seats are written `?`, the result of a running game is `*` and it has no termination header.
+//! Games which do not start from the standard position have a `Position` header in the notation of positions.
+//! Moves use the notation of the [`notation`](crate::notation) module.
+
+use std::fmt::{self, Display, Write};
+use std::str::FromStr;
+
+use crate::notation::{self, ParseError};
+use crate::{Action, GameResult, GameState, IllegalMove, Move, Player};
+
+/// Variant of the games played with the standard rules on the default board
+pub const STANDARD_VARIANT: &str = "standard";
+
+// Move text lines are wrapped like in PGN
+const MAX_LINE_LENGTH: usize = 80;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord {
+	pub name: String,
+	pub seat_1: Option<String>,
+	pub seat_2: Option<String>,
+	/// `YYYY.MM.DD`
+	pub date: String,
+	pub variant: String,
+	/// Starting position, None for the standard one
+	pub position: Option<GameState>,
+	/// None while the game is running
+	pub result: Option<GameResult>,
+	pub moves: Vec<Move>,
+}
+
+impl GameRecord {
+	/// Record of a game from its action log, seats are left unknown
+	pub fn from_actions(name: &str, date: &str, actions: &[Action]) -> GameRecord {
+		let mut record = GameRecord {
+			name: name.to_owned(),
+			seat_1: None,
+			seat_2: None,
+			date: date.to_owned(),
+			variant: STANDARD_VARIANT.to_owned(),
+			position: None,
+			result: None,
+			moves: Vec::new(),
+		};
+		for action in actions {
+			match action {
+				Action::Move(mv) => record.moves.push(*mv),
+				Action::Finished(result) => record.result = Some(*result),
+				_ => (),
+			}
+		}
+		record
+	}
+
+	/// Action log matching the record, as the game actor stores it
+	pub fn actions(&self) -> Vec<Action> {
+		let mut actions = vec![Action::Init];
+		actions.extend(self.moves.iter().map(|mv| Action::Move(*mv)));
+		if let Some(result) = self.result {
+			actions.push(Action::Finished(result));
+		}
+		actions
+	}
+
+	/// Position at the end of the record, which also checks that every move is legal
+	pub fn final_state(&self) -> Result<GameState, IllegalMove> {
+		let start = self.position.clone().unwrap_or_default();
+		GameState::replay(start, &self.actions())
+	}
+}
+
+impl Display for GameRecord {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write_header(f, "Game", &self.name)?;
+		write_header(f, "Seat1", self.seat_1.as_deref().unwrap_or("?"))?;
+		write_header(f, "Seat2", self.seat_2.as_deref().unwrap_or("?"))?;
+		write_header(f, "Date", &self.date)?;
+		write_header(f, "Variant", &self.variant)?;
+		if let Some(position) = &self.position {
+			write_header(f, "Position", &notation::format_position(position))?;
+		}
+		write_header(f, "Result", result_token(self.result.as_ref()))?;
+		if let Some(result) = self.result {
+			write_header(f, "Termination", &result.reason.to_string())?;
+		}
+		writeln!(f)?;
+
+		let mut tokens = Vec::new();
+		for (i, mv) in self.moves.iter().enumerate() {
+			if i % 2 == 0 {
+				tokens.push(format!("{}.", i / 2 + 1));
+			}
+			tokens.push(mv.to_string());
+		}
+		tokens.push(result_token(self.result.as_ref()).to_owned());
+
+		let mut line = String::new();
+		for token in tokens {
+			if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE_LENGTH {
+				writeln!(f, "{}", line)?;
+				line.clear();
+			}
+			if !line.is_empty() {
+				line.push(' ');
+			}
+			line.push_str(&token);
+		}
+		writeln!(f, "{}", line)
+	}
+}
+
+impl FromStr for GameRecord {
+	type Err = ParseError;
+
+	fn from_str(text: &str) -> Result<GameRecord, ParseError> {
+		let mut record = GameRecord::from_actions("", "", &[]);
+		let mut result = None;
+		let mut termination = None;
+		let mut lines = text.lines();
+
+		for line in &mut lines {
+			let line = line.trim();
+			if line.is_empty() {
+				break;
+			}
+			let (key, value) = parse_header(line)?;
+			match key {
+				"Game" => record.name = value,
+				"Seat1" => record.seat_1 = Some(value).filter(|name| name != "?"),
+				"Seat2" => record.seat_2 = Some(value).filter(|name| name != "?"),
+				"Date" => record.date = value,
+				"Variant" => record.variant = value,
+				"Position" => record.position = Some(notation::parse_position(&value)?),
+				"Result" => result = Some(value),
+				"Termination" => termination = Some(value.parse()?),
+				_ => (), // headers from a newer version
+			}
+		}
+
+		let mut end_token = None;
+		for token in lines.flat_map(|line| line.split_whitespace()) {
+			if end_token.is_some() {
+				return Err(ParseError::TrailingData(token.to_owned()));
+			}
+			if token.ends_with('.') && token[..token.len() - 1].chars().all(|c| c.is_ascii_digit()) {
+				continue; // move number
+			}
+			match token {
+				"1-0" | "0-1" | "1/2-1/2" | "*" => end_token = Some(token.to_owned()),
+				_ => record.moves.push(token.parse()?),
+			}
+		}
+
+		let result = result.ok_or(ParseError::MissingField("result"))?;
+		if end_token.as_ref() != Some(&result) {
+			return Err(ParseError::BadResult(end_token.unwrap_or_default()));
+		}
+		let winner = match result.as_str() {
+			"1-0" => Some(Player::One),
+			"0-1" => Some(Player::Two),
+			_ => None,
+		};
+		record.result = match (result.as_str(), termination) {
+			("*", None) => None,
+			("*", Some(_)) => return Err(ParseError::BadResult(result)),
+			(_, Some(reason)) => Some(GameResult { winner, reason }),
+			(_, None) => return Err(ParseError::MissingField("termination")),
+		};
+		Ok(record)
+	}
+}
+
+fn result_token(result: Option<&GameResult>) -> &'static str {
+	match result.map(|result| result.winner) {
+		None => "*",
+		Some(Some(Player::One)) => "1-0",
+		Some(Some(Player::Two)) => "0-1",
+		Some(None) => "1/2-1/2",
+	}
+}
+
+fn write_header(f: &mut fmt::Formatter, key: &str, value: &str) -> fmt::Result {
+	write!(f, "[{} \"", key)?;
+	for c in value.chars() {
+		if c == '"' || c == '\\' {
+			f.write_char('\\')?;
+		}
+		f.write_char(c)?;
+	}
+	writeln!(f, "\"]")
+}
+
+fn parse_header(line: &str) -> Result<(&str, String), ParseError> {
+	let bad_header = || ParseError::BadHeader(line.to_owned());
+	let inner = line
+		.strip_prefix('[')
+		.and_then(|line| line.strip_suffix(']'))
+		.ok_or_else(bad_header)?;
+	let (key, quoted) = inner.split_at(inner.find(' ').ok_or_else(bad_header)?);
+	let quoted = quoted
+		.trim_start()
+		.strip_prefix('"')
+		.and_then(|value| value.strip_suffix('"'))
+		.ok_or_else(bad_header)?;
+
+	let mut value = String::new();
+	let mut chars = quoted.chars();
+	while let Some(c) = chars.next() {
+		match c {
+			'\\' => value.push(chars.next().ok_or_else(bad_header)?),
+			'"' => return Err(bad_header()),
+			_ => value.push(c),
+		}
+	}
+	Ok((key, value))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{EndReason, GameConfig, Rules, Standard};
+
+	fn finished_record() -> GameRecord {
+		let mut record = GameRecord::from_actions("Brave \"Otter\"", "2021.09.30", &[]);
+		record.seat_1 = Some("alice".to_owned());
+		for mv in ["b6", "b2", "g1e1", "f6"].iter() {
+			record.moves.push(mv.parse().unwrap());
+		}
+		record.result = Some(GameResult::win(Player::One, EndReason::Resignation));
+		record
+	}
+
+	#[test]
+	fn format_record() {
+		let expected = "[Game \"Brave \\\"Otter\\\"\"]\n\
+			[Seat1 \"alice\"]\n\
+			[Seat2 \"?\"]\n\
+			[Date \"2021.09.30\"]\n\
+			[Variant \"standard\"]\n\
+			[Result \"1-0\"]\n\
+			[Termination \"resignation\"]\n\
+			\n\
+			1. b6 b2 2. g1e1 f6 1-0\n";
+		assert_eq!(finished_record().to_string(), expected);
+	}
+
+	#[test]
+	fn records_round_trip() {
+		let record = finished_record();
+		assert_eq!(record.to_string().parse::<GameRecord>(), Ok(record.clone()));
+		assert!(record.final_state().is_ok());
+
+		// Long running game, wrapped on several lines
+		let mut state = GameState::new();
+		let mut running = GameRecord::from_actions("Running", "2021.10.01", &[]);
+		for _ in 0..40 {
+			let mv = state.legal_moves()[0];
+			state.play(&mv).unwrap();
+			running.moves.push(mv);
+		}
+		let text = running.to_string();
+		assert!(text.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+		assert_eq!(text.parse::<GameRecord>(), Ok(running.clone()));
+		assert_eq!(running.final_state(), Ok(state));
+	}
+
+	#[test]
+	fn custom_start_round_trip() {
+		let config = GameConfig {
+			board_size: 5,
+			first_player: Player::Two,
+			..GameConfig::default()
+		};
+		let mut record = GameRecord::from_actions("Small", "2021.10.02", &[]);
+		record.variant = config.variant();
+		record.position = Some(Standard.initial_state(&config));
+		record.moves.push("b1".parse().unwrap());
+		let text = record.to_string();
+		assert!(text.contains("[Position \"x3o/5/5/5/o3x o 0 0\"]"));
+		assert_eq!(text.parse::<GameRecord>(), Ok(record.clone()));
+		assert!(record.final_state().is_ok());
+	}
+
+	#[test]
+	fn bad_records() {
+		let good = finished_record().to_string();
+		let bad = [
+			good.replace("[Result \"1-0\"]\n", ""),
+			good.replace("[Termination \"resignation\"]\n", ""),
+			good.replace("f6 1-0", "f6 0-1"),
+			good.replace("f6 1-0", "f6"),
+			good.replace("f6 1-0", "f6 1-0 c5"),
+			good.replace("g1e1", "g1e"),
+			good.replace("[Date \"2021.09.30\"]", "[Date 2021.09.30]"),
+		];
+		for text in bad.iter() {
+			assert!(text.parse::<GameRecord>().is_err(), "should not parse:\n{}", text);
+		}
+	}
+}
@@ -0,0 +1,189 @@
+//! Text drawings of positions, for logs, test failures and terminal clients
+//!
+//! The Unicode style draws a grid with box drawing characters:
+//!
+//! ```text
+//!    ┌───┬───┬───┬───┬───┐
+//!  5 │ ● │   │   │   │ ○ │
+//!    ├───┼───┼───┼───┼───┤
+//!    ...
+//!    └───┴───┴───┴───┴───┘
+//!      a   b   c   d   e
+//! ● Player 1 to move
+//! ```
+//!
+//! The ASCII style uses the characters of the position notation, one per square. [`svg`] draws the board as an image,
+//! for thumbnails and link previews.
+
+use crate::{Board, Cell, GameState, Player, Square};
+
+// Side of a square in the SVG drawing, in pixels
+const SVG_SQUARE: u32 = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+	pub player_one: char,
+	pub player_two: char,
+	pub blocked: char,
+	pub empty: char,
+	/// Draw the lines between squares
+	pub grid: bool,
+}
+
+pub const UNICODE: Style = Style {
+	player_one: '●',
+	player_two: '○',
+	blocked: '■',
+	empty: ' ',
+	grid: true,
+};
+
+pub const ASCII: Style = Style {
+	player_one: 'x',
+	player_two: 'o',
+	blocked: '#',
+	empty: '.',
+	grid: false,
+};
+
+impl Style {
+	fn cell(&self, cell: Option<Cell>) -> char {
+		match cell {
+			Some(Cell::Stone(Player::One)) => self.player_one,
+			Some(Cell::Stone(Player::Two)) => self.player_two,
+			Some(Cell::Blocked) => self.blocked,
+			_ => self.empty,
+		}
+	}
+
+	fn stone(&self, player: Player) -> char {
+		match player {
+			Player::One => self.player_one,
+			Player::Two => self.player_two,
+		}
+	}
+}
+
+/// The board from the top rank, with the ranks on the left and the files below
+pub fn board(board: &Board, style: &Style) -> String {
+	let size = board.size();
+	let cells = |row: u8| (0..size).map(move |col| style.cell(board.get(Square::new(col, row))));
+	let mut lines = Vec::new();
+	if style.grid {
+		let border = |left: &str, middle: &str, right: &str| {
+			format!("   {}{}{}", left, vec!["───"; size as usize].join(middle), right)
+		};
+		lines.push(border("┌", "┬", "┐"));
+		for row in (0..size).rev() {
+			let squares: Vec<String> = cells(row).map(|cell| format!(" {} ", cell)).collect();
+			lines.push(format!("{:>2} │{}│", row + 1, squares.join("│")));
+			if row > 0 {
+				lines.push(border("├", "┼", "┤"));
+			}
+		}
+		lines.push(border("└", "┴", "┘"));
+		let files: Vec<String> = (0..size).map(|col| ((b'a' + col) as char).to_string()).collect();
+		lines.push(format!("     {}", files.join("   ")));
+	} else {
+		for row in (0..size).rev() {
+			lines.push(format!("{:>2} {}", row + 1, cells(row).collect::<String>()));
+		}
+		lines.push(format!(
+			"   {}",
+			(0..size).map(|col| (b'a' + col) as char).collect::<String>()
+		));
+	}
+	lines.join("\n")
+}
+
+/// The board followed by the player to move, or by the result once the game is over
+pub fn position(state: &GameState, style: &Style) -> String {
+	let status = match state.outcome() {
+		Some(result) => result.to_string(),
+		None => format!("{} {} to move", style.stone(state.side_to_move()), state.side_to_move()),
+	};
+	format!("{}\n{}", board(state.board(), style), status)
+}
+
+/// Standalone SVG image of the board, with the top rank at the top
+pub fn svg(board: &Board) -> String {
+	let size = board.size() as u32;
+	let side = size * SVG_SQUARE;
+	let mut svg = String::new();
+	svg.push_str(&format!(
+		r##"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}" viewBox="0 0 {0} {0}">"##,
+		side
+	));
+	svg.push_str(&format!(r##"<rect width="{0}" height="{0}" fill="#e8c887"/>"##, side));
+	for line in 1..size {
+		let at = line * SVG_SQUARE;
+		svg.push_str(&format!(
+			r##"<path d="M{0} 0V{1}M0 {0}H{1}" stroke="#8a6d3b" stroke-width="1"/>"##,
+			at, side
+		));
+	}
+	for row in 0..board.size() {
+		for col in 0..board.size() {
+			let x = col as u32 * SVG_SQUARE;
+			let y = (size - 1 - row as u32) * SVG_SQUARE;
+			let (center_x, center_y, radius) = (x + SVG_SQUARE / 2, y + SVG_SQUARE / 2, SVG_SQUARE * 2 / 5);
+			let shape = match board.get(Square::new(col, row)) {
+				Some(Cell::Stone(Player::One)) => format!(
+					r##"<circle cx="{}" cy="{}" r="{}" fill="#222222"/>"##,
+					center_x, center_y, radius
+				),
+				Some(Cell::Stone(Player::Two)) => format!(
+					r##"<circle cx="{}" cy="{}" r="{}" fill="#f4f4f4" stroke="#222222" stroke-width="2"/>"##,
+					center_x, center_y, radius
+				),
+				Some(Cell::Blocked) => format!(
+					r##"<rect x="{}" y="{}" width="{2}" height="{2}" fill="#555555"/>"##,
+					x, y, SVG_SQUARE
+				),
+				_ => continue,
+			};
+			svg.push_str(&shape);
+		}
+	}
+	svg.push_str("</svg>");
+	svg
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::notation;
+
+	#[test]
+	fn unicode_grid() {
+		let state = notation::parse_position("x1o/1#1/o1x o 0 0").unwrap();
+		let expected = "   ┌───┬───┬───┐\n \
+			3 │ ● │   │ ○ │\n   \
+			├───┼───┼───┤\n \
+			2 │   │ ■ │   │\n   \
+			├───┼───┼───┤\n \
+			1 │ ○ │   │ ● │\n   \
+			└───┴───┴───┘\n     \
+			a   b   c\n\
+			○ Player 2 to move";
+		assert_eq!(position(&state, &UNICODE), expected);
+	}
+
+	#[test]
+	fn ascii_board() {
+		let state = notation::parse_position("x1o/1#1/o1x o 0 0").unwrap();
+		assert_eq!(board(state.board(), &ASCII), " 3 x.o\n 2 .#.\n 1 o.x\n   abc");
+	}
+
+	#[test]
+	fn svg_stones() {
+		let state = notation::parse_position("x1o/1#1/o1x o 0 0").unwrap();
+		let svg = svg(state.board());
+		assert!(svg.starts_with("<svg ") && svg.ends_with("</svg>"));
+		assert!(svg.contains(r#"viewBox="0 0 120 120""#));
+		assert_eq!(svg.matches("<circle").count(), 4);
+		// Player 1 in the top left corner, the blocked square in the middle
+		assert!(svg.contains(r##"<circle cx="20" cy="20" r="16" fill="#222222"/>"##));
+		assert!(svg.contains(r##"<rect x="40" y="40" width="40" height="40" fill="#555555"/>"##));
+	}
+}
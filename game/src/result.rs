@@ -0,0 +1,87 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::{ParseError, Player};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EndReason {
+	/// Nobody can move anymore, the player with the most stones wins
+	NoMoves,
+	/// One of the players lost all their stones
+	NoStones,
+	/// Too many plies were played without any grow move
+	MoveLimit,
+	/// The same position occurred too many times
+	Repetition,
+	Resignation,
+	Timeout,
+	Agreement,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GameResult {
+	pub winner: Option<Player>, // None for a draw
+	pub reason: EndReason,
+}
+
+impl GameResult {
+	pub fn win(winner: Player, reason: EndReason) -> GameResult {
+		GameResult {
+			winner: Some(winner),
+			reason,
+		}
+	}
+
+	pub fn draw(reason: EndReason) -> GameResult {
+		GameResult { winner: None, reason }
+	}
+
+	pub fn is_draw(&self) -> bool {
+		self.winner.is_none()
+	}
+}
+
+impl Display for EndReason {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			EndReason::NoMoves => write!(f, "no more moves"),
+			EndReason::NoStones => write!(f, "no more stones"),
+			EndReason::MoveLimit => write!(f, "move limit reached"),
+			EndReason::Repetition => write!(f, "repetition"),
+			EndReason::Resignation => write!(f, "resignation"),
+			EndReason::Timeout => write!(f, "timeout"),
+			EndReason::Agreement => write!(f, "agreement"),
+		}
+	}
+}
+
+impl FromStr for EndReason {
+	type Err = ParseError;
+
+	/// Parse the `Display` form of a reason
+	fn from_str(text: &str) -> Result<EndReason, ParseError> {
+		let reasons = [
+			EndReason::NoMoves,
+			EndReason::NoStones,
+			EndReason::MoveLimit,
+			EndReason::Repetition,
+			EndReason::Resignation,
+			EndReason::Timeout,
+			EndReason::Agreement,
+		];
+		reasons
+			.iter()
+			.find(|reason| reason.to_string() == text)
+			.copied()
+			.ok_or_else(|| ParseError::BadResult(text.to_owned()))
+	}
+}
+
+impl Display for GameResult {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.winner {
+			Some(player) => write!(f, "{} wins by {}", player, self.reason),
+			None => write!(f, "Draw by {}", self.reason),
+		}
+	}
+}
@@ -0,0 +1,277 @@
+//! Rule sets a game can be played with
+//!
+//! The server only talks to a game through the [`Rules`] trait, so that games with different rules can be hosted side
+//! by side. Each rule set is identified by a [`RuleSet`], which is part of the [`GameConfig`]: either one of the rule
+//! sets of this crate, or a plugin loaded by the server (see [`plugin`](crate::plugin)).
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::{
+	Action, Board, Cell, ConfigError, GameConfig, GameResult, GameState, IllegalMove, Move, ParseError, Square,
+};
+
+pub trait Rules: fmt::Debug + Sync {
+	/// Check the parts of the configuration specific to these rules
+	fn validate(&self, _config: &GameConfig) -> Result<(), ConfigError> {
+		Ok(())
+	}
+
+	fn initial_state(&self, config: &GameConfig) -> GameState;
+
+	/// Check a custom starting position, used instead of `initial_state` for teaching or puzzles
+	///
+	/// The player to move is the one of the position, whatever the first player of the configuration.
+	fn validate_position(&self, config: &GameConfig, state: &GameState) -> Result<(), ConfigError> {
+		let size = state.board().size();
+		if size != config.board_size {
+			return Err(ConfigError::PositionSize(size));
+		}
+		if self.outcome(state).is_some() {
+			return Err(ConfigError::FinishedPosition);
+		}
+		Ok(())
+	}
+
+	fn legal_moves(&self, state: &GameState) -> Vec<Move>;
+
+	/// Play a move, the position is left untouched if it is illegal
+	fn apply(&self, state: &mut GameState, mv: &Move) -> Result<(), IllegalMove>;
+
+	/// Result of the game if the position is terminal
+	fn outcome(&self, state: &GameState) -> Option<GameResult>;
+
+	/// Position reached after playing all the actions of a game log from its starting position
+	fn replay(&self, start: &GameState, actions: &[Action]) -> Result<GameState, IllegalMove> {
+		let mut state = start.clone();
+		for action in actions {
+			if let Action::Move(mv) = action {
+				self.apply(&mut state, mv)?;
+			}
+		}
+		Ok(state)
+	}
+}
+
+/// Longest id of a plugin rule set
+pub const MAX_PLUGIN_ID_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RuleSet {
+	Standard,
+	Blocked,
+	Plugin(PluginId),
+}
+
+/// Id of a plugin rule set: up to `MAX_PLUGIN_ID_LEN` lowercase letters, digits, `-` or `_`
+///
+/// Stored inline so that rule sets, and the configurations holding them, stay `Copy`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PluginId {
+	len: u8,
+	bytes: [u8; MAX_PLUGIN_ID_LEN],
+}
+
+impl RuleSet {
+	/// Rule sets implemented by this crate
+	pub const BUILTIN: [RuleSet; 2] = [RuleSet::Standard, RuleSet::Blocked];
+
+	pub fn id(&self) -> &str {
+		match self {
+			RuleSet::Standard => "standard",
+			RuleSet::Blocked => "blocked",
+			RuleSet::Plugin(id) => id.as_str(),
+		}
+	}
+
+	/// Rules of the built-in rule sets, plugins are only known by the server which loaded them
+	pub fn rules(&self) -> Option<&'static dyn Rules> {
+		match self {
+			RuleSet::Standard => Some(&Standard),
+			RuleSet::Blocked => Some(&Blocked),
+			RuleSet::Plugin(_) => None,
+		}
+	}
+}
+
+impl PluginId {
+	pub fn as_str(&self) -> &str {
+		// Only built from ASCII text
+		std::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or_default()
+	}
+}
+
+impl fmt::Debug for PluginId {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "PluginId({:?})", self.as_str())
+	}
+}
+
+impl Display for PluginId {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+impl FromStr for PluginId {
+	type Err = ParseError;
+
+	fn from_str(text: &str) -> Result<PluginId, ParseError> {
+		let valid_char = |c: u8| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'-' || c == b'_';
+		if text.is_empty() || text.len() > MAX_PLUGIN_ID_LEN || !text.bytes().all(valid_char) {
+			return Err(ParseError::BadRuleSet(text.to_owned()));
+		}
+		let mut bytes = [0; MAX_PLUGIN_ID_LEN];
+		bytes[..text.len()].copy_from_slice(text.as_bytes());
+		Ok(PluginId {
+			len: text.len() as u8,
+			bytes,
+		})
+	}
+}
+
+impl Display for RuleSet {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.id())
+	}
+}
+
+impl FromStr for RuleSet {
+	type Err = ParseError;
+
+	/// Any valid id which is not a built-in rule set is taken as a plugin
+	fn from_str(text: &str) -> Result<RuleSet, ParseError> {
+		match RuleSet::BUILTIN.iter().find(|rule_set| rule_set.id() == text) {
+			Some(rule_set) => Ok(*rule_set),
+			None => Ok(RuleSet::Plugin(text.parse()?)),
+		}
+	}
+}
+
+/// Stones in the corners of an empty board
+#[derive(Debug, Clone, Copy)]
+pub struct Standard;
+
+impl Rules for Standard {
+	fn initial_state(&self, config: &GameConfig) -> GameState {
+		GameState::from_board(Board::with_corners(config.board_size), config.first_player)
+	}
+
+	fn legal_moves(&self, state: &GameState) -> Vec<Move> {
+		state.legal_moves()
+	}
+
+	fn apply(&self, state: &mut GameState, mv: &Move) -> Result<(), IllegalMove> {
+		state.play(mv).map(|_| ())
+	}
+
+	fn outcome(&self, state: &GameState) -> Option<GameResult> {
+		state.outcome()
+	}
+}
+
+/// Standard rules with the center of the board and the squares around it in a cross blocked
+#[derive(Debug, Clone, Copy)]
+pub struct Blocked;
+
+impl Rules for Blocked {
+	fn validate(&self, config: &GameConfig) -> Result<(), ConfigError> {
+		// The board needs a center square
+		if config.board_size & 1 == 0 {
+			return Err(ConfigError::BoardSize(config.board_size));
+		}
+		Ok(())
+	}
+
+	fn initial_state(&self, config: &GameConfig) -> GameState {
+		let mut board = Board::with_corners(config.board_size);
+		let center = config.board_size / 2;
+		let cross = [(0, 0), (-1, 0), (1, 0), (0, -1), (0, 1)];
+		for (col, row) in cross.iter() {
+			let square = Square::new((center as i8 + col) as u8, (center as i8 + row) as u8);
+			board.set(square, Cell::Blocked);
+		}
+		GameState::from_board(board, config.first_player)
+	}
+
+	fn legal_moves(&self, state: &GameState) -> Vec<Move> {
+		Standard.legal_moves(state)
+	}
+
+	fn apply(&self, state: &mut GameState, mv: &Move) -> Result<(), IllegalMove> {
+		Standard.apply(state, mv)
+	}
+
+	fn outcome(&self, state: &GameState) -> Option<GameResult> {
+		Standard.outcome(state)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::notation;
+
+	#[test]
+	fn rule_set_ids() {
+		for rule_set in RuleSet::BUILTIN.iter() {
+			assert_eq!(rule_set.id().parse::<RuleSet>(), Ok(*rule_set));
+			assert!(rule_set.rules().is_some());
+		}
+		let plugin: RuleSet = "misere".parse().unwrap();
+		assert_eq!(plugin.id(), "misere");
+		assert_eq!(plugin.rules().map(|_| ()), None);
+		for text in ["", "Chess", "no jump", "a-very-long-plugin-name-over-the-limit"].iter() {
+			assert!(text.parse::<RuleSet>().is_err(), "'{}' should not parse", text);
+		}
+	}
+
+	#[test]
+	fn blocked_center() {
+		let config = GameConfig {
+			rule_set: RuleSet::Blocked,
+			..GameConfig::default()
+		};
+		let rules = &Blocked;
+		let state = rules.initial_state(&config);
+		assert_eq!(notation::format_position(&state), "x5o/7/3#3/2###2/3#3/7/o5x x 0 0");
+
+		// Nothing can be played on the blocked squares
+		let mut moved = state.clone();
+		assert!(rules.apply(&mut moved, &"d4".parse().unwrap()).is_err());
+		assert_eq!(moved, state);
+
+		let moves = vec![Action::Move("b6".parse().unwrap()), Action::Move("b2".parse().unwrap())];
+		let replayed = rules.replay(&state, &moves).unwrap();
+		assert_eq!(
+			rules.legal_moves(&replayed).len(),
+			Standard.legal_moves(&replayed).len()
+		);
+
+		let even = GameConfig {
+			board_size: 8,
+			..config
+		};
+		assert_eq!(rules.validate(&even), Err(ConfigError::BoardSize(8)));
+	}
+
+	#[test]
+	fn custom_positions() {
+		let config = GameConfig::default();
+		let position = |text: &str| notation::parse_position(text).unwrap();
+		let state = position("x5o/7/2xo3/3o3/7/7/o5x o 0 12");
+		assert_eq!(Standard.validate_position(&config, &state), Ok(()));
+		assert_eq!(
+			Standard.validate_position(&config, &position("x3o/5/5/5/o3x x")),
+			Err(ConfigError::PositionSize(5))
+		);
+		assert_eq!(
+			Standard.validate_position(&config, &position("x6/7/7/7/7/7/6x o")),
+			Err(ConfigError::FinishedPosition)
+		);
+
+		let moves = vec![Action::Move("c3".parse().unwrap())];
+		let replayed = Standard.replay(&state, &moves).unwrap();
+		assert_eq!(notation::format_position(&replayed), "x5o/7/2xo3/3o3/2o4/7/o5x x 0 13");
+	}
+}
@@ -0,0 +1,519 @@
+use std::fmt::{self, Display};
+
+use crate::{zobrist, Action, Board, Cell, EndReason, GameResult, Move, Player, Square};
+
+/// Number of plies without any grow move after which the game is drawn
+pub const MOVE_LIMIT: u32 = 100;
+
+/// Position of a running game: the board and whose turn it is
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GameState {
+	board: Board,
+	side_to_move: Player,
+	halfmove_clock: u32, // plies since the last grow move
+	ply: u32,
+	hash: u64, // zobrist hash of the board and side to move, see `zobrist`
+}
+
+/// Everything needed to take back a move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Undo {
+	mv: Move,
+	captured: Vec<Square>,
+	halfmove_clock: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalMove {
+	GameOver,
+	OutOfBoard(Square),
+	NotYourStone(Square),
+	Occupied(Square),
+	NoStoneAround(Square),
+	BadDistance {
+		from: Square,
+		to: Square,
+	},
+	PassNotAllowed,
+	/// Refused by rules which do not tell why, like plugins
+	Rejected(Move),
+}
+
+impl Display for IllegalMove {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			IllegalMove::GameOver => write!(f, "The game is over"),
+			IllegalMove::OutOfBoard(square) => write!(f, "Square {} is outside of the board", square),
+			IllegalMove::NotYourStone(square) => write!(f, "There is no stone of yours on {}", square),
+			IllegalMove::Occupied(square) => write!(f, "Square {} is not free", square),
+			IllegalMove::NoStoneAround(square) => write!(f, "None of your stones is next to {}", square),
+			IllegalMove::BadDistance { from, to } => write!(f, "Unable to jump from {} to {}", from, to),
+			IllegalMove::PassNotAllowed => write!(f, "You can only pass when no other move is available"),
+			IllegalMove::Rejected(mv) => write!(f, "Move {} is not allowed", mv),
+		}
+	}
+}
+
+impl std::error::Error for IllegalMove {}
+
+impl GameState {
+	pub fn new() -> GameState {
+		GameState::from_board(Board::default(), Player::One)
+	}
+
+	pub fn from_board(board: Board, side_to_move: Player) -> GameState {
+		GameState {
+			hash: zobrist::position_hash(&board, side_to_move),
+			board,
+			side_to_move,
+			halfmove_clock: 0,
+			ply: 0,
+		}
+	}
+
+	/// Same position with other move counters, used to restore a position from its notation
+	pub(crate) fn with_counters(mut self, halfmove_clock: u32, ply: u32) -> GameState {
+		self.halfmove_clock = halfmove_clock;
+		self.ply = ply;
+		self
+	}
+
+	pub fn board(&self) -> &Board {
+		&self.board
+	}
+
+	pub fn side_to_move(&self) -> Player {
+		self.side_to_move
+	}
+
+	pub fn halfmove_clock(&self) -> u32 {
+		self.halfmove_clock
+	}
+
+	pub fn ply(&self) -> u32 {
+		self.ply
+	}
+
+	/// Stable hash of the position (board and side to move), updated incrementally by each move
+	///
+	/// Move counters are not part of it, so it can be used to detect repeated positions.
+	pub fn hash(&self) -> u64 {
+		self.hash
+	}
+
+	/// Result of the game if the position is terminal
+	pub fn outcome(&self) -> Option<GameResult> {
+		let (stones_1, stones_2) = (self.board.count(Player::One), self.board.count(Player::Two));
+		if stones_1 == 0 {
+			return Some(GameResult::win(Player::Two, EndReason::NoStones));
+		}
+		if stones_2 == 0 {
+			return Some(GameResult::win(Player::One, EndReason::NoStones));
+		}
+		if !self.has_stone_move(Player::One) && !self.has_stone_move(Player::Two) {
+			return Some(match stones_1.cmp(&stones_2) {
+				std::cmp::Ordering::Greater => GameResult::win(Player::One, EndReason::NoMoves),
+				std::cmp::Ordering::Less => GameResult::win(Player::Two, EndReason::NoMoves),
+				std::cmp::Ordering::Equal => GameResult::draw(EndReason::NoMoves),
+			});
+		}
+		if self.halfmove_clock >= MOVE_LIMIT {
+			return Some(GameResult::draw(EndReason::MoveLimit));
+		}
+		None
+	}
+
+	/// Every move the side to move can play, empty when the game is over
+	pub fn legal_moves(&self) -> Vec<Move> {
+		if self.outcome().is_some() {
+			return Vec::new();
+		}
+		let moves = self.stone_moves(self.side_to_move);
+		if moves.is_empty() {
+			vec![Move::Pass]
+		} else {
+			moves
+		}
+	}
+
+	pub fn check_move(&self, mv: &Move) -> Result<(), IllegalMove> {
+		if self.outcome().is_some() {
+			return Err(IllegalMove::GameOver);
+		}
+		match mv {
+			Move::Pass => {
+				if self.has_stone_move(self.side_to_move) {
+					Err(IllegalMove::PassNotAllowed)
+				} else {
+					Ok(())
+				}
+			}
+			Move::Grow { to } => {
+				self.check_target(*to)?;
+				let own_stone = Cell::Stone(self.side_to_move);
+				if self.board.ring(*to, 1).any(|sq| self.board.get(sq) == Some(own_stone)) {
+					Ok(())
+				} else {
+					Err(IllegalMove::NoStoneAround(*to))
+				}
+			}
+			Move::Jump { from, to } => {
+				if !self.board.contains(*from) {
+					return Err(IllegalMove::OutOfBoard(*from));
+				}
+				if self.board.get(*from) != Some(Cell::Stone(self.side_to_move)) {
+					return Err(IllegalMove::NotYourStone(*from));
+				}
+				self.check_target(*to)?;
+				if from.distance(to) != 2 {
+					return Err(IllegalMove::BadDistance { from: *from, to: *to });
+				}
+				Ok(())
+			}
+		}
+	}
+
+	/// Play the given action, the position is left untouched if the action is illegal
+	///
+	/// Only moves change the position, other actions are part of the game lifecycle and are handled by the caller
+	pub fn apply(&mut self, action: &Action) -> Result<(), IllegalMove> {
+		match action {
+			Action::Move(mv) => self.play(mv).map(|_| ()),
+			_ => Ok(()),
+		}
+	}
+
+	/// Play a move, the returned value allows to take it back with `undo`
+	pub fn play(&mut self, mv: &Move) -> Result<Undo, IllegalMove> {
+		self.check_move(mv)?;
+		Ok(self.play_unchecked(mv))
+	}
+
+	/// Take back the last move played, `undo` must come from the matching call to `play`
+	pub fn undo(&mut self, undo: Undo) {
+		let player = self.side_to_move.opponent();
+		match undo.mv {
+			Move::Pass => (),
+			Move::Grow { to } => {
+				self.set_cell(to, Cell::Empty);
+			}
+			Move::Jump { from, to } => {
+				self.set_cell(to, Cell::Empty);
+				self.set_cell(from, Cell::Stone(player));
+			}
+		}
+		for square in undo.captured {
+			self.set_cell(square, Cell::Stone(player.opponent()));
+		}
+		self.halfmove_clock = undo.halfmove_clock;
+		self.set_side_to_move(player);
+		self.ply -= 1;
+	}
+
+	/// Position reached after playing all the actions from `start`
+	pub fn replay(start: GameState, actions: &[Action]) -> Result<GameState, IllegalMove> {
+		let mut state = start;
+		for action in actions {
+			state.apply(action)?;
+		}
+		Ok(state)
+	}
+
+	fn play_unchecked(&mut self, mv: &Move) -> Undo {
+		let player = self.side_to_move;
+		let halfmove_clock = self.halfmove_clock;
+		let captured = match mv {
+			Move::Pass => {
+				self.halfmove_clock += 1;
+				Vec::new()
+			}
+			Move::Grow { to } => {
+				self.set_cell(*to, Cell::Stone(player));
+				self.halfmove_clock = 0;
+				self.capture_around(*to, player)
+			}
+			Move::Jump { from, to } => {
+				self.set_cell(*from, Cell::Empty);
+				self.set_cell(*to, Cell::Stone(player));
+				self.halfmove_clock += 1;
+				self.capture_around(*to, player)
+			}
+		};
+		self.set_side_to_move(player.opponent());
+		self.ply += 1;
+		Undo {
+			mv: *mv,
+			captured,
+			halfmove_clock,
+		}
+	}
+
+	fn capture_around(&mut self, square: Square, player: Player) -> Vec<Square> {
+		let opponent_stone = Cell::Stone(player.opponent());
+		let captured: Vec<Square> = self
+			.board
+			.ring(square, 1)
+			.filter(|sq| self.board.get(*sq) == Some(opponent_stone))
+			.collect();
+		for sq in captured.iter() {
+			self.set_cell(*sq, Cell::Stone(player));
+		}
+		captured
+	}
+
+	// Every change of the position goes through these two functions, to keep the hash up to date
+	fn set_cell(&mut self, square: Square, cell: Cell) {
+		if let Some(previous) = self.board.get(square) {
+			self.hash ^= zobrist::cell_key(square, previous);
+		}
+		self.board.set(square, cell);
+		self.hash ^= zobrist::cell_key(square, cell);
+	}
+
+	fn set_side_to_move(&mut self, player: Player) {
+		self.hash ^= zobrist::side_key(self.side_to_move) ^ zobrist::side_key(player);
+		self.side_to_move = player;
+	}
+
+	fn check_target(&self, to: Square) -> Result<(), IllegalMove> {
+		match self.board.get(to) {
+			None => Err(IllegalMove::OutOfBoard(to)),
+			Some(Cell::Empty) => Ok(()),
+			Some(_) => Err(IllegalMove::Occupied(to)),
+		}
+	}
+
+	fn stone_moves(&self, player: Player) -> Vec<Move> {
+		let own_stone = Cell::Stone(player);
+		let mut moves = Vec::new();
+		for to in self.board.squares() {
+			if self.board.get(to) != Some(Cell::Empty) {
+				continue;
+			}
+			if self.board.ring(to, 1).any(|sq| self.board.get(sq) == Some(own_stone)) {
+				moves.push(Move::Grow { to });
+			}
+			for from in self.board.ring(to, 2) {
+				if self.board.get(from) == Some(own_stone) {
+					moves.push(Move::Jump { from, to });
+				}
+			}
+		}
+		moves
+	}
+
+	fn has_stone_move(&self, player: Player) -> bool {
+		let own_stone = Cell::Stone(player);
+		self.board.squares().any(|to| {
+			self.board.get(to) == Some(Cell::Empty)
+				&& (self.board.ring(to, 1).any(|sq| self.board.get(sq) == Some(own_stone))
+					|| self.board.ring(to, 2).any(|sq| self.board.get(sq) == Some(own_stone)))
+		})
+	}
+}
+
+impl Default for GameState {
+	fn default() -> GameState {
+		GameState::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sq(name: &str) -> Square {
+		let bytes = name.as_bytes();
+		Square::new(bytes[0] - b'a', bytes[1] - b'1')
+	}
+
+	#[test]
+	fn initial_position_moves() {
+		let state = GameState::new();
+		let moves = state.legal_moves();
+		assert_eq!(moves.len(), 16);
+		assert_eq!(moves.iter().filter(|mv| matches!(mv, Move::Grow { .. })).count(), 6);
+		assert!(!moves.contains(&Move::Pass));
+	}
+
+	#[test]
+	fn grow_captures_neighbours() {
+		let mut board = Board::empty(7);
+		board.set(sq("a1"), Cell::Stone(Player::One));
+		board.set(sq("c2"), Cell::Stone(Player::Two));
+		board.set(sq("c3"), Cell::Stone(Player::Two));
+		board.set(sq("g7"), Cell::Stone(Player::Two));
+		let mut state = GameState::from_board(board, Player::One);
+		state.apply(&Action::Move(Move::Grow { to: sq("b2") })).unwrap();
+		assert_eq!(state.board().count(Player::One), 4);
+		assert_eq!(state.board().count(Player::Two), 1);
+		assert_eq!(state.side_to_move(), Player::Two);
+		assert_eq!(state.halfmove_clock(), 0);
+	}
+
+	#[test]
+	fn jump_leaves_origin() {
+		let mut state = GameState::new();
+		state
+			.apply(&Action::Move(Move::Jump {
+				from: sq("a7"),
+				to: sq("c5"),
+			}))
+			.unwrap();
+		assert_eq!(state.board().get(sq("a7")), Some(Cell::Empty));
+		assert_eq!(state.board().get(sq("c5")), Some(Cell::Stone(Player::One)));
+		assert_eq!(state.halfmove_clock(), 1);
+		assert_eq!(state.ply(), 1);
+	}
+
+	#[test]
+	fn illegal_moves_are_refused() {
+		let mut state = GameState::new();
+		let before = state.clone();
+		let illegal = [
+			(Move::Grow { to: sq("d4") }, IllegalMove::NoStoneAround(sq("d4"))),
+			(Move::Grow { to: sq("a1") }, IllegalMove::Occupied(sq("a1"))),
+			(
+				Move::Grow { to: Square::new(7, 0) },
+				IllegalMove::OutOfBoard(Square::new(7, 0)),
+			),
+			(
+				Move::Jump {
+					from: sq("a1"),
+					to: sq("c3"),
+				},
+				IllegalMove::NotYourStone(sq("a1")),
+			),
+			(
+				Move::Jump {
+					from: sq("a7"),
+					to: sq("d7"),
+				},
+				IllegalMove::BadDistance {
+					from: sq("a7"),
+					to: sq("d7"),
+				},
+			),
+			(Move::Pass, IllegalMove::PassNotAllowed),
+		];
+		for (mv, err) in illegal.iter() {
+			assert_eq!(state.apply(&Action::Move(*mv)), Err(*err));
+		}
+		assert_eq!(state, before);
+	}
+
+	#[test]
+	fn pass_when_blocked() {
+		let mut board = Board::empty(4);
+		board.set(sq("a1"), Cell::Stone(Player::One));
+		for blocked in ["a2", "b1", "b2"].iter() {
+			board.set(sq(blocked), Cell::Blocked);
+		}
+		for stone in ["a3", "b3", "c1", "c2", "c3"].iter() {
+			board.set(sq(stone), Cell::Stone(Player::Two));
+		}
+		let state = GameState::from_board(board, Player::One);
+		assert_eq!(state.legal_moves(), vec![Move::Pass]);
+		assert_eq!(state.outcome(), None);
+	}
+
+	#[test]
+	fn undo_restores_position() {
+		let mut state = GameState::new();
+		let line = [
+			Move::Grow { to: sq("b6") },
+			Move::Grow { to: sq("b2") },
+			Move::Jump {
+				from: sq("g1"),
+				to: sq("e1"),
+			},
+		];
+		for mv in line.iter() {
+			state.play(mv).unwrap();
+		}
+		for mv in state.legal_moves() {
+			let before = state.clone();
+			let undo = state.play(&mv).unwrap();
+			assert_ne!(state, before);
+			state.undo(undo);
+			assert_eq!(state, before);
+		}
+		let actions: Vec<Action> = line.iter().map(|mv| Action::Move(*mv)).collect();
+		assert_eq!(GameState::replay(GameState::new(), &actions), Ok(state));
+	}
+
+	#[test]
+	fn incremental_hash() {
+		let mut state = GameState::new();
+		let start = state.hash();
+		let line = [
+			Move::Jump {
+				from: sq("a7"),
+				to: sq("c7"),
+			},
+			Move::Jump {
+				from: sq("a1"),
+				to: sq("c1"),
+			},
+			Move::Jump {
+				from: sq("c7"),
+				to: sq("a7"),
+			},
+			Move::Jump {
+				from: sq("c1"),
+				to: sq("a1"),
+			},
+		];
+		for mv in line.iter() {
+			state.play(mv).unwrap();
+			assert_eq!(
+				state.hash(),
+				zobrist::position_hash(state.board(), state.side_to_move())
+			);
+		}
+		// Same position after two back and forth jumps, even though the move counters changed
+		assert_eq!(state.hash(), start);
+		state.play(&Move::Grow { to: sq("b6") }).unwrap();
+		assert_ne!(state.hash(), start);
+	}
+
+	#[test]
+	fn outcome_detection() {
+		let mut board = Board::empty(3);
+		board.set(sq("a1"), Cell::Stone(Player::One));
+		board.set(sq("c3"), Cell::Stone(Player::Two));
+		let state = GameState::from_board(board.clone(), Player::One);
+		assert_eq!(state.outcome(), None);
+
+		board.set(sq("c3"), Cell::Stone(Player::One));
+		let state = GameState::from_board(board.clone(), Player::Two);
+		assert_eq!(state.outcome(), Some(GameResult::win(Player::One, EndReason::NoStones)));
+		assert!(state.legal_moves().is_empty());
+
+		for square in board.clone().squares() {
+			board.set(square, Cell::Stone(Player::Two));
+		}
+		board.set(sq("a1"), Cell::Stone(Player::One));
+		let mut state = GameState::from_board(board, Player::One);
+		assert_eq!(state.outcome(), Some(GameResult::win(Player::Two, EndReason::NoMoves)));
+		assert_eq!(state.apply(&Action::Move(Move::Pass)), Err(IllegalMove::GameOver));
+	}
+
+	#[test]
+	fn move_limit_draw() {
+		let mut state = GameState::new();
+		let jumps = [("a7", "c7"), ("a1", "c1"), ("c7", "a7"), ("c1", "a1")];
+		for _ in 0..MOVE_LIMIT / 4 {
+			assert_eq!(state.outcome(), None);
+			for (from, to) in jumps.iter() {
+				state
+					.apply(&Action::Move(Move::Jump {
+						from: sq(from),
+						to: sq(to),
+					}))
+					.unwrap();
+			}
+		}
+		assert_eq!(state.outcome(), Some(GameResult::draw(EndReason::MoveLimit)));
+	}
+}
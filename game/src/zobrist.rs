@@ -0,0 +1,45 @@
+//! Zobrist keys of the positions
+//!
+//! Keys are derived from a fixed seed instead of a random table, so they do not depend on the board size and stay
+//! the same between runs and builds: hashes can be stored (opening book, game records) and compared later.
+
+use crate::{Board, Cell, Player, Square};
+
+const SEED: u64 = 0x5947_414d_455f_5a42; // "YGAME_ZB"
+
+/// Key of a cell content on a square, 0 for an empty square
+pub fn cell_key(square: Square, cell: Cell) -> u64 {
+	let kind = match cell {
+		Cell::Empty => return 0,
+		Cell::Blocked => 1,
+		Cell::Stone(Player::One) => 2,
+		Cell::Stone(Player::Two) => 3,
+	};
+	mix((square.row as u64) << 16 | (square.col as u64) << 8 | kind)
+}
+
+/// Key of the player to move
+pub fn side_key(player: Player) -> u64 {
+	match player {
+		Player::One => mix(1 << 32),
+		Player::Two => mix(2 << 32),
+	}
+}
+
+/// Hash of a full position, computed from scratch
+pub fn position_hash(board: &Board, side_to_move: Player) -> u64 {
+	let board_key = mix((board.size() as u64) << 40);
+	board
+		.squares()
+		.fold(board_key ^ side_key(side_to_move), |hash, square| {
+			hash ^ cell_key(square, board.get(square).expect("Square taken from the board"))
+		})
+}
+
+// SplitMix64 finalizer
+fn mix(value: u64) -> u64 {
+	let mut z = value.wrapping_add(SEED);
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	z ^ (z >> 31)
+}
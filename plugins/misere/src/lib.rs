@@ -0,0 +1,57 @@
+//! Sample rule set plugin: the standard game, except that the player with the fewest stones at the end wins
+//!
+//! Build it with `cargo build -p ygame-plugin-misere --release --target wasm32-unknown-unknown` and copy
+//! `ygame_plugin_misere.wasm` in the plugin directory of the server.
+
+use ygame_game::{GameConfig, GameResult, GameState, IllegalMove, Move, Rules, Standard};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Misere;
+
+impl Rules for Misere {
+	fn validate(&self, config: &GameConfig) -> Result<(), ygame_game::ConfigError> {
+		Standard.validate(config)
+	}
+
+	fn initial_state(&self, config: &GameConfig) -> GameState {
+		Standard.initial_state(config)
+	}
+
+	fn legal_moves(&self, state: &GameState) -> Vec<Move> {
+		Standard.legal_moves(state)
+	}
+
+	fn apply(&self, state: &mut GameState, mv: &Move) -> Result<(), IllegalMove> {
+		Standard.apply(state, mv)
+	}
+
+	fn outcome(&self, state: &GameState) -> Option<GameResult> {
+		Standard.outcome(state).map(|result| GameResult {
+			winner: result.winner.map(|player| player.opponent()),
+			reason: result.reason,
+		})
+	}
+}
+
+ygame_game::export_plugin!("misere", Misere);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ygame_game::plugin::guest;
+
+	#[test]
+	fn fewest_stones_win() {
+		// Player 2 has no stone left, which makes them the winner
+		let position = "x6/7/7/7/7/7/7 o";
+		assert_eq!(guest::outcome(&Standard, position), "x no more stones");
+		assert_eq!(guest::outcome(&Misere, position), "o no more stones");
+
+		let start = guest::initial_state(&Misere, "7 x");
+		assert_eq!(
+			guest::legal_moves(&Misere, &start),
+			guest::legal_moves(&Standard, &start)
+		);
+		assert_eq!(guest::outcome(&Misere, &start), "");
+	}
+}
@@ -0,0 +1,370 @@
+// Golden file tests of the wire format
+//
+// Every message of the frozen modules is encoded and compared with the files of the fixtures directory, then
+// decoded from them and encoded again. Handshake and error messages are always sent in JSON, so only that encoding
+// is checked, versionned messages are checked in JSON and MessagePack. A failure means a message changed in a way
+// old clients or servers would not understand: renamed variant or field, reordered field, changed type...
+//
+// After a deliberate change (e.g. a new error message or a new protocol version), regenerate the files with
+//   YGAME_BLESS=1 cargo test -p ygame-protocol conformance
+// and review their diff.
+
+use crate::error::{code, ErrorMessage};
+use crate::handshake::{Capability, DisconnectMessage, Encoding, HelloMessage, HelloResponseMessage};
+use crate::v1;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+fn fixture_path(module: &str, name: &str, extension: &str) -> PathBuf {
+	let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+	path.push("fixtures");
+	path.push(module);
+	path.push(format!("{}.{}", name, extension));
+	path
+}
+
+fn check_fixture(path: PathBuf, encoded: &[u8]) {
+	if std::env::var_os("YGAME_BLESS").is_some() {
+		fs::create_dir_all(path.parent().unwrap()).unwrap();
+		fs::write(&path, encoded).unwrap();
+		return;
+	}
+	let expected = match fs::read(&path) {
+		Ok(expected) => expected,
+		Err(e) => panic!(
+			"Unable to read {}: {}, run with YGAME_BLESS=1 to create it",
+			path.display(),
+			e
+		),
+	};
+	assert!(
+		encoded == &expected[..],
+		"{} changed:\n  expected {:?}\n  got      {:?}",
+		path.display(),
+		expected,
+		encoded
+	);
+}
+
+/// Check the JSON encoding of a message against its fixture, and that decoding it gives back the same message
+fn check_json<T: Serialize + DeserializeOwned>(module: &str, name: &str, msg: &T) {
+	let json = serde_json::to_string(msg).unwrap();
+	check_fixture(fixture_path(module, name, "json"), json.as_bytes());
+	let decoded: T = serde_json::from_str(&json).unwrap();
+	assert_eq!(
+		serde_json::to_string(&decoded).unwrap(),
+		json,
+		"{}/{}.json round trip",
+		module,
+		name
+	);
+}
+
+/// Same as check_json, for both encodings
+fn check<T: Serialize + DeserializeOwned>(module: &str, name: &str, msg: &T) {
+	check_json(module, name, msg);
+	let msgpack = rmp_serde::encode::to_vec(msg).unwrap();
+	check_fixture(fixture_path(module, name, "msgpack"), &msgpack);
+	let decoded: T = rmp_serde::from_read_ref(&msgpack).unwrap();
+	assert_eq!(
+		rmp_serde::encode::to_vec(&decoded).unwrap(),
+		msgpack,
+		"{}/{}.msgpack round trip",
+		module,
+		name
+	);
+}
+
+#[test]
+fn handshake() {
+	check_json(
+		"handshake",
+		"hello",
+		&HelloMessage {
+			known_protocols: vec![1, 2],
+			encodings: Vec::new(),
+			capabilities: Vec::new(),
+		},
+	);
+	check_json(
+		"handshake",
+		"hello_with_encodings",
+		&HelloMessage {
+			known_protocols: vec![1, 2],
+			encodings: vec![Encoding::Json, Encoding::MessagePack],
+			capabilities: Vec::new(),
+		},
+	);
+	check_json(
+		"handshake",
+		"hello_response_success",
+		&HelloResponseMessage::Success {
+			protocol_version: 1,
+			encoding: Encoding::MessagePack,
+			capabilities: Vec::new(),
+		},
+	);
+	check_json(
+		"handshake",
+		"hello_response_success_json",
+		&HelloResponseMessage::Success {
+			protocol_version: 2,
+			encoding: Encoding::Json,
+			capabilities: Vec::new(),
+		},
+	);
+	check_json(
+		"handshake",
+		"hello_response_failure",
+		&HelloResponseMessage::Failure { should_reload: true },
+	);
+	check_json(
+		"handshake",
+		"hello_with_capabilities",
+		&HelloMessage {
+			known_protocols: vec![2],
+			encodings: Vec::new(),
+			capabilities: vec![
+				Capability::Chat,
+				Capability::Clocks,
+				Capability::Spectate,
+				Capability::Compression,
+				Capability::Resume,
+			],
+		},
+	);
+	check_json(
+		"handshake",
+		"hello_response_success_capabilities",
+		&HelloResponseMessage::Success {
+			protocol_version: 2,
+			encoding: Encoding::MessagePack,
+			capabilities: vec![Capability::Clocks, Capability::Spectate],
+		},
+	);
+	check_json("handshake", "disconnect_from_client", &DisconnectMessage::FromClient);
+	check_json("handshake", "disconnect_from_server", &DisconnectMessage::FromServer);
+}
+
+#[test]
+fn unknown_capabilities() {
+	let hello: HelloMessage =
+		serde_json::from_str(r#"{"known_protocols":[2],"capabilities":["Clocks","Telepathy"]}"#).unwrap();
+	assert_eq!(hello.capabilities, vec![Capability::Clocks, Capability::Unknown]);
+}
+
+#[test]
+fn unknown_encodings() {
+	let hello: HelloMessage = serde_json::from_str(r#"{"known_protocols":[2],"encodings":["Cbor","Json"]}"#).unwrap();
+	assert_eq!(hello.encodings, vec![Encoding::Unknown, Encoding::Json]);
+}
+
+#[test]
+fn error() {
+	check_json("error", "default", &ErrorMessage::default());
+	check_json(
+		"error",
+		"without_code",
+		&ErrorMessage {
+			error_code: None,
+			error_description: "Bad handshake".to_owned(),
+			should_reload: true,
+			should_reconnect: true,
+			should_handshake: true,
+		},
+	);
+	check_json(
+		"error",
+		"illegal_move",
+		&ErrorMessage {
+			error_code: Some(code::game::ILLEGAL_MOVE),
+			error_description: "Illegal move".to_owned(),
+			..ErrorMessage::default()
+		},
+	);
+}
+
+fn overview(id: &str, status: v1::GameStatus) -> v1::GameOverview {
+	v1::GameOverview {
+		id: id.to_owned(),
+		name: format!("Game {}", id),
+		status,
+	}
+}
+
+fn details() -> v1::GameDetails {
+	v1::GameDetails {
+		id: "g1".to_owned(),
+		name: "Game g1".to_owned(),
+		is_finished: false,
+		seat_1_username: Some("alice".to_owned()),
+		seat_2_username: None,
+	}
+}
+
+fn v1_game_action(name: &str, action: v1::GameAction) {
+	check(
+		"v1",
+		&format!("client_game_{}", name),
+		&v1::RunningClientMessage::Game {
+			game_id: "g1".to_owned(),
+			request_id: "r1".to_owned(),
+			action,
+		},
+	);
+}
+
+fn v1_lobby_client(name: &str, msg: v1::LobbyClientMessage) {
+	check(
+		"v1",
+		&format!("client_lobby_{}", name),
+		&v1::RunningClientMessage::Lobby(msg),
+	);
+}
+
+fn v1_game_server(name: &str, message: v1::GameServerMessage) {
+	check(
+		"v1",
+		&format!("server_game_{}", name),
+		&v1::RunningServerMessage::Game {
+			game_id: "g1".to_owned(),
+			message,
+		},
+	);
+}
+
+fn v1_lobby_server(name: &str, msg: v1::LobbyServerMessages) {
+	check(
+		"v1",
+		&format!("server_lobby_{}", name),
+		&v1::RunningServerMessage::Lobby(msg),
+	);
+}
+
+#[test]
+fn v1_login() {
+	check(
+		"v1",
+		"login",
+		&v1::LoginMessage {
+			name: "alice".to_owned(),
+			uid: Some("u1".to_owned()),
+			session_uid: None,
+		},
+	);
+	check(
+		"v1",
+		"login_response",
+		&v1::LoginResponseMessage {
+			name: "alice".to_owned(),
+			user_uid: "u1".to_owned(),
+			session_uid: "s1".to_owned(),
+		},
+	);
+}
+
+#[test]
+fn v1_client() {
+	v1_lobby_client("ask_game_list", v1::LobbyClientMessage::AskGameList);
+	v1_lobby_client(
+		"create_game",
+		v1::LobbyClientMessage::CreateGame {
+			request_uid: "r1".to_owned(),
+		},
+	);
+	v1_lobby_client(
+		"join_game",
+		v1::LobbyClientMessage::JoinGame {
+			game_uid: "g1".to_owned(),
+		},
+	);
+
+	v1_game_action("init", v1::GameAction::Init);
+	v1_game_action("move", v1::GameAction::Move);
+	v1_game_action("finished", v1::GameAction::Finished);
+}
+
+#[test]
+fn v1_server_lobby() {
+	v1_lobby_server(
+		"game_list",
+		v1::LobbyServerMessages::GameList {
+			list: vec![
+				overview("g1", v1::GameStatus::Joinable),
+				overview("g2", v1::GameStatus::Rejoinable),
+				overview("g3", v1::GameStatus::Full),
+				overview("g4", v1::GameStatus::Finished),
+			],
+		},
+	);
+	v1_lobby_server(
+		"game_created",
+		v1::LobbyServerMessages::GameCreated {
+			request_uid: "r1".to_owned(),
+			info: details(),
+			role: v1::UserRole::Seat1,
+		},
+	);
+	v1_lobby_server(
+		"new_game",
+		v1::LobbyServerMessages::NewGame(overview("g1", v1::GameStatus::Joinable)),
+	);
+	v1_lobby_server(
+		"game_info_changed",
+		v1::LobbyServerMessages::GameInfoChanged(overview("g1", v1::GameStatus::Full)),
+	);
+	v1_lobby_server(
+		"game_joined",
+		v1::LobbyServerMessages::GameJoined {
+			info: v1::GameDetails {
+				is_finished: true,
+				..details()
+			},
+			role: v1::UserRole::Observer,
+			moves: vec![v1::GameAction::Init, v1::GameAction::Move, v1::GameAction::Finished],
+		},
+	);
+	v1_lobby_server(
+		"game_removed",
+		v1::LobbyServerMessages::GameRemoved { id: "g1".to_owned() },
+	);
+}
+
+#[test]
+fn v1_server_game() {
+	v1_game_server("action", v1::GameServerMessage::Action(v1::GameAction::Move));
+	v1_game_server(
+		"action_response_ok",
+		v1::GameServerMessage::GameActionResponse {
+			request_id: "r1".to_owned(),
+			response: v1::GameActionResponse::Ok,
+		},
+	);
+	v1_game_server(
+		"action_response_illegal",
+		v1::GameServerMessage::GameActionResponse {
+			request_id: "r1".to_owned(),
+			response: v1::GameActionResponse::Illegal {
+				reason: code::game::NOT_YOUR_TURN,
+			},
+		},
+	);
+	v1_game_server(
+		"user_join",
+		v1::GameServerMessage::UserJoin {
+			user_uid: "u2".to_owned(),
+			username: "bob".to_owned(),
+			role: v1::UserRole::Seat2,
+		},
+	);
+	v1_game_server(
+		"user_quit",
+		v1::GameServerMessage::UserQuit {
+			user_uid: "u2".to_owned(),
+			role: v1::UserRole::Seat2,
+		},
+	);
+}
@@ -33,11 +33,18 @@ pub mod code {
 		pub const GAME_ALREADY_JOINED: u32 = 301;
 		pub const GAME_DOESNT_EXISTS: u32 = 302;
 		pub const GAME_NOT_JOINED: u32 = 303;
+		pub const INVALID_GAME_CONFIG: u32 = 304;
+		pub const GAME_FULL: u32 = 305;
 	}
 	pub mod game {
 		pub const GAME_ERROR: u32 = 400;
 		pub const ILLEGAL_MOVE: u32 = 401;
 		pub const NOT_YOUR_TURN: u32 = 402;
+		pub const NO_DRAW_OFFER: u32 = 403;
+		pub const NO_TAKEBACK_REQUEST: u32 = 404;
+		pub const TIME_OUT: u32 = 405;
+		pub const ANALYSIS_NOT_ALLOWED: u32 = 406;
+		pub const ANALYSIS_RUNNING: u32 = 407;
 	}
 }
 
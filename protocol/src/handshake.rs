@@ -3,12 +3,28 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct HelloMessage {
 	pub known_protocols: Vec<u32>,
+	/// Encodings the client can use after the handshake, preferred first. Older clients do not send it.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub encodings: Vec<Encoding>,
+	/// Optional features supported by the client, on top of its protocols
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub capabilities: Vec<Capability>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub enum HelloResponseMessage {
-	Success { protocol_version: u32 },
-	Failure { should_reload: bool },
+	Success {
+		protocol_version: u32,
+		/// Omitted by older servers, which only speak MessagePack
+		#[serde(default, skip_serializing_if = "Encoding::is_default")]
+		encoding: Encoding,
+		/// Features supported by both sides, they can be used for the rest of the connection
+		#[serde(default, skip_serializing_if = "Vec::is_empty")]
+		capabilities: Vec<Capability>,
+	},
+	Failure {
+		should_reload: bool,
+	},
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -16,3 +32,44 @@ pub enum DisconnectMessage {
 	FromClient,
 	FromServer,
 }
+
+/// Optional feature, which can be rolled out without a new protocol version
+///
+/// A feature may only be used once both sides agreed on it during the handshake.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Capability {
+	Chat,
+	Clocks,
+	Spectate,
+	Compression,
+	Resume,
+	/// Capability of a newer peer, never agreed on
+	#[serde(other)]
+	Unknown,
+}
+
+/// Encoding of the login and running messages
+///
+/// MessagePack messages are sent in binary frames, JSON ones in text frames.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Encoding {
+	MessagePack,
+	Json,
+	/// Encoding of a newer peer, never chosen
+	#[serde(other)]
+	Unknown,
+}
+
+impl Encoding {
+	pub fn is_default(&self) -> bool {
+		*self == Encoding::default()
+	}
+}
+
+// Written by hand, the compiler of the docker images predates #[default] on enums
+#[allow(clippy::derivable_impls)]
+impl Default for Encoding {
+	fn default() -> Encoding {
+		Encoding::MessagePack
+	}
+}
@@ -2,8 +2,8 @@
 //Each submodule follow i's own rules:
 //
 // * Handshake:
-//   This module should never change.
-//   Messages are designed to be sent in json text format. It is used to choose subsequent protocol
+//   This module should never change, apart from optional fields older peers can leave out.
+//   Messages are designed to be sent in json text format. It is used to choose subsequent protocol and encoding
 //
 // * Error:
 //   The main error message is designed to be sent in json text format by the server. It should also never change
@@ -14,10 +14,16 @@
 //   While finalized you should not changed a protocol version.
 //   You can add as many protocol as you want though
 //   The application is designed to choose the highest protocol number during handshake
+//
+// The conformance module checks the encoding of frozen messages against the files of the fixtures directory.
 
 pub mod error;
 pub mod handshake;
 pub mod v1;
+pub mod v2;
+
+#[cfg(test)]
+mod conformance;
 
 #[cfg(test)]
 mod tests {
@@ -1,6 +1,12 @@
+// First protocol version
+//
+// Released clients speak it, so its messages must not change: new data and messages go in a new version.
+
 use serde::{Deserialize, Serialize};
 use ygame_game as rules;
 
+use std::convert::TryFrom;
+
 pub const VERSION: u32 = 1;
 
 // ---------------- Common data ----------------------
@@ -155,42 +161,16 @@ impl From<&rules::UserRole> for UserRole {
 	}
 }
 
-impl From<rules::Action> for GameAction {
-	fn from(val: rules::Action) -> Self {
-		match val {
-			rules::Action::Init => GameAction::Init,
-			rules::Action::Move => GameAction::Move,
-			rules::Action::Finished => GameAction::Finished,
-		}
-	}
-}
-
-impl From<&rules::Action> for GameAction {
-	fn from(val: &rules::Action) -> Self {
-		match val {
-			rules::Action::Init => GameAction::Init,
-			rules::Action::Move => GameAction::Move,
-			rules::Action::Finished => GameAction::Finished,
-		}
-	}
-}
-
-impl From<&GameAction> for rules::Action {
-	fn from(val: &GameAction) -> Self {
-		match val {
-			GameAction::Init => rules::Action::Init,
-			GameAction::Move => rules::Action::Move,
-			GameAction::Finished => rules::Action::Finished,
-		}
-	}
-}
+/// Protocol 1 neither carries the move played nor the result, nor the actions added later
+impl TryFrom<&rules::Action> for GameAction {
+	type Error = ();
 
-impl From<GameAction> for rules::Action {
-	fn from(val: GameAction) -> Self {
+	fn try_from(val: &rules::Action) -> Result<Self, Self::Error> {
 		match val {
-			GameAction::Init => rules::Action::Init,
-			GameAction::Move => rules::Action::Move,
-			GameAction::Finished => rules::Action::Finished,
+			rules::Action::Init => Ok(GameAction::Init),
+			rules::Action::Move(_) => Ok(GameAction::Move),
+			rules::Action::Finished(_) => Ok(GameAction::Finished),
+			_ => Err(()),
 		}
 	}
 }
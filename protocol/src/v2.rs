@@ -0,0 +1,554 @@
+//Second protocol version
+//
+// Compared to the first one:
+// * Moves are sent with their ply and the player who played them, and the end of a game has its own message
+// * Clock state is a structure shared by every message carrying it
+// * Requests answered by the server carry a numeric id, echoed in the answer
+// * Clients can only send the actions a player can take, Init and Finished are server side events
+// * Games are created with a configuration and an optional starting position, their details carry the result
+// * Players can resign, offer draws and request takebacks, and ask for the record or the analysis of a game
+//
+// Data which did not change is reused from the first version. Failed requests are still answered by the
+// error message of the error module, which has no request id.
+
+use serde::{Deserialize, Serialize};
+use ygame_game as rules;
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+pub use crate::v1::{GameActionResponse, GameStatus, LoginMessage, LoginResponseMessage, UserRole};
+
+pub const VERSION: u32 = 2;
+
+/// Chosen by the client, it should be unique among its pending requests
+pub type RequestId = u32;
+
+// ---------------- Common data ----------------------
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum GameAction {
+	Move(Move),
+	Resign,
+	OfferDraw,
+	AcceptDraw,
+	DeclineDraw,
+	RequestTakeback,
+	AcceptTakeback,
+	DeclineTakeback,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Move {
+	Grow { to: Square },
+	Jump { from: Square, to: Square },
+	Pass,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct Square {
+	pub col: u8,
+	pub row: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct GameResult {
+	pub winner: Option<UserRole>,
+	pub reason: EndReason,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum EndReason {
+	NoMoves,
+	NoStones,
+	MoveLimit,
+	Repetition,
+	Resignation,
+	Timeout,
+	Agreement,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum TimeControl {
+	Increment { base_ms: u64, increment_ms: u64 },
+	PerMove { ms: u64 },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum RuleSet {
+	Standard,
+	Blocked,
+	Plugin { id: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct GameConfig {
+	pub rule_set: RuleSet,
+	pub board_size: u8,
+	pub time_control: Option<TimeControl>,
+	pub first_player: UserRole, // Seat1 or Seat2
+	pub rated: bool,
+	pub private: bool,
+}
+
+/// A move with its place in the game
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct PlayedMove {
+	pub ply: u32, // 1 for the first move played from the start position
+	pub role: UserRole,
+	pub mv: Move,
+}
+
+/// Remaining time of both seats in milliseconds
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct ClockState {
+	pub seat_1_ms: u64,
+	pub seat_2_ms: u64,
+	pub running: Option<UserRole>,
+}
+
+// ---------------- Client messages ----------------------
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub enum RunningClientMessage {
+	Lobby(LobbyClientMessage),
+	Game {
+		game_id: String,
+		request_id: RequestId,
+		action: GameAction,
+	},
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub enum LobbyClientMessage {
+	AskGameList,
+	AskRuleSets,
+	CreateGame {
+		request_id: RequestId,
+		config: GameConfig,
+		position: Option<String>, // custom starting position, in the notation of the rules crate
+	},
+	JoinGame {
+		request_id: RequestId,
+		game_id: String,
+	},
+	AskGameRecord {
+		request_id: RequestId,
+		game_id: String,
+	},
+	/// Engine analysis of every move, refused to the players of a rated game in progress
+	AskGameAnalysis {
+		request_id: RequestId,
+		game_id: String,
+	},
+}
+
+// ---------------- Server messages ----------------------
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum RunningServerMessage {
+	Lobby(LobbyServerMessage),
+	Game {
+		game_id: String,
+		message: GameServerMessage,
+	},
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum GameServerMessage {
+	/// Both seats are taken, the first player can move
+	Started,
+	MovePlayed(PlayedMove),
+	GameOver(GameResult),
+	ActionResponse {
+		request_id: RequestId,
+		response: GameActionResponse,
+	},
+	UserJoin {
+		user_uid: String,
+		username: String,
+		role: UserRole,
+	},
+	UserQuit {
+		user_uid: String,
+		role: UserRole,
+	},
+	DrawOffered {
+		role: UserRole,
+	},
+	DrawDeclined {
+		role: UserRole,
+	},
+	TakebackRequested {
+		role: UserRole,
+	},
+	TakebackDeclined {
+		role: UserRole,
+	},
+	/// The last plies are cancelled
+	Rollback {
+		plies: u32,
+	},
+	/// Sent after every move, and when joining a game with a time control
+	Clocks(ClockState),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum LobbyServerMessage {
+	GameList {
+		list: Vec<GameOverview>,
+	},
+	/// Rule sets games can be created with, built-in ones and plugins loaded by the server
+	RuleSets {
+		list: Vec<RuleSet>,
+	},
+	NewGame(GameOverview),
+	GameInfoChanged(GameOverview),
+	GameRemoved {
+		id: String,
+	},
+	GameCreated {
+		request_id: RequestId,
+		info: GameDetails,
+		role: UserRole,
+	},
+	GameJoined {
+		request_id: RequestId,
+		info: GameDetails,
+		role: UserRole,
+		moves: Vec<PlayedMove>,
+	},
+	GameRecord {
+		request_id: RequestId,
+		game_id: String,
+		record: String,
+	},
+	GameAnalysis {
+		request_id: RequestId,
+		game_id: String,
+		plies: Vec<PlyAnalysis>,
+	},
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GameOverview {
+	pub id: String,
+	pub name: String,
+	pub status: GameStatus,
+	pub config: GameConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GameDetails {
+	pub id: String,
+	pub name: String,
+	pub result: Option<GameResult>,
+	pub seat_1_username: Option<String>,
+	pub seat_2_username: Option<String>,
+	pub config: GameConfig,
+	pub position: Option<String>, // custom starting position, None for the one of the rules
+}
+
+/// Analysis of the position before a move
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct PlyAnalysis {
+	pub played: Move,
+	pub best_move: Option<Move>,
+	pub score: i32, // for the player who played the move
+	pub depth: u32,
+	pub principal_variation: Vec<Move>,
+}
+
+// ------------------------ Some rules enum casting routines ---------------------------------
+
+/// Moves of an action log, numbered from the start position where `first` is to move
+pub fn played_moves(first: rules::Player, actions: &[rules::Action]) -> Vec<PlayedMove> {
+	let moves = actions.iter().filter_map(|action| match action {
+		rules::Action::Move(mv) => Some(mv),
+		_ => None,
+	});
+	let mut player = first;
+	let mut played = Vec::new();
+	for (index, mv) in moves.enumerate() {
+		played.push(PlayedMove {
+			ply: index as u32 + 1,
+			role: player.seat().into(),
+			mv: mv.into(),
+		});
+		player = player.opponent();
+	}
+	played
+}
+
+impl From<GameAction> for rules::Action {
+	fn from(val: GameAction) -> Self {
+		match val {
+			GameAction::Move(mv) => rules::Action::Move(mv.into()),
+			GameAction::Resign => rules::Action::Resign,
+			GameAction::OfferDraw => rules::Action::OfferDraw,
+			GameAction::AcceptDraw => rules::Action::AcceptDraw,
+			GameAction::DeclineDraw => rules::Action::DeclineDraw,
+			GameAction::RequestTakeback => rules::Action::RequestTakeback,
+			GameAction::AcceptTakeback => rules::Action::AcceptTakeback,
+			GameAction::DeclineTakeback => rules::Action::DeclineTakeback,
+		}
+	}
+}
+
+/// Init and Finished are not player actions
+impl TryFrom<rules::Action> for GameAction {
+	type Error = rules::Action;
+
+	fn try_from(val: rules::Action) -> Result<Self, Self::Error> {
+		match val {
+			rules::Action::Move(mv) => Ok(GameAction::Move(mv.into())),
+			rules::Action::Resign => Ok(GameAction::Resign),
+			rules::Action::OfferDraw => Ok(GameAction::OfferDraw),
+			rules::Action::AcceptDraw => Ok(GameAction::AcceptDraw),
+			rules::Action::DeclineDraw => Ok(GameAction::DeclineDraw),
+			rules::Action::RequestTakeback => Ok(GameAction::RequestTakeback),
+			rules::Action::AcceptTakeback => Ok(GameAction::AcceptTakeback),
+			rules::Action::DeclineTakeback => Ok(GameAction::DeclineTakeback),
+			rules::Action::Init | rules::Action::Finished(_) => Err(val),
+		}
+	}
+}
+
+impl From<rules::Move> for Move {
+	fn from(val: rules::Move) -> Self {
+		match val {
+			rules::Move::Grow { to } => Move::Grow { to: to.into() },
+			rules::Move::Jump { from, to } => Move::Jump {
+				from: from.into(),
+				to: to.into(),
+			},
+			rules::Move::Pass => Move::Pass,
+		}
+	}
+}
+
+impl From<&rules::Move> for Move {
+	fn from(val: &rules::Move) -> Self {
+		(*val).into()
+	}
+}
+
+impl From<Move> for rules::Move {
+	fn from(val: Move) -> Self {
+		match val {
+			Move::Grow { to } => rules::Move::Grow { to: to.into() },
+			Move::Jump { from, to } => rules::Move::Jump {
+				from: from.into(),
+				to: to.into(),
+			},
+			Move::Pass => rules::Move::Pass,
+		}
+	}
+}
+
+impl From<&Move> for rules::Move {
+	fn from(val: &Move) -> Self {
+		(*val).into()
+	}
+}
+
+impl From<rules::Square> for Square {
+	fn from(val: rules::Square) -> Self {
+		Square {
+			col: val.col,
+			row: val.row,
+		}
+	}
+}
+
+impl From<Square> for rules::Square {
+	fn from(val: Square) -> Self {
+		rules::Square::new(val.col, val.row)
+	}
+}
+
+impl From<rules::GameResult> for GameResult {
+	fn from(val: rules::GameResult) -> Self {
+		GameResult {
+			winner: val.winner.map(|player| player.seat().into()),
+			reason: val.reason.into(),
+		}
+	}
+}
+
+impl From<&rules::GameResult> for GameResult {
+	fn from(val: &rules::GameResult) -> Self {
+		(*val).into()
+	}
+}
+
+impl From<GameResult> for rules::GameResult {
+	fn from(val: GameResult) -> Self {
+		rules::GameResult {
+			winner: match val.winner {
+				Some(UserRole::Seat1) => Some(rules::Player::One),
+				Some(UserRole::Seat2) => Some(rules::Player::Two),
+				Some(UserRole::Observer) | None => None,
+			},
+			reason: val.reason.into(),
+		}
+	}
+}
+
+impl From<&GameResult> for rules::GameResult {
+	fn from(val: &GameResult) -> Self {
+		(*val).into()
+	}
+}
+
+impl From<rules::EndReason> for EndReason {
+	fn from(val: rules::EndReason) -> Self {
+		match val {
+			rules::EndReason::NoMoves => EndReason::NoMoves,
+			rules::EndReason::NoStones => EndReason::NoStones,
+			rules::EndReason::MoveLimit => EndReason::MoveLimit,
+			rules::EndReason::Repetition => EndReason::Repetition,
+			rules::EndReason::Resignation => EndReason::Resignation,
+			rules::EndReason::Timeout => EndReason::Timeout,
+			rules::EndReason::Agreement => EndReason::Agreement,
+		}
+	}
+}
+
+impl From<EndReason> for rules::EndReason {
+	fn from(val: EndReason) -> Self {
+		match val {
+			EndReason::NoMoves => rules::EndReason::NoMoves,
+			EndReason::NoStones => rules::EndReason::NoStones,
+			EndReason::MoveLimit => rules::EndReason::MoveLimit,
+			EndReason::Repetition => rules::EndReason::Repetition,
+			EndReason::Resignation => rules::EndReason::Resignation,
+			EndReason::Timeout => rules::EndReason::Timeout,
+			EndReason::Agreement => rules::EndReason::Agreement,
+		}
+	}
+}
+
+impl From<rules::TimeControl> for TimeControl {
+	fn from(val: rules::TimeControl) -> Self {
+		match val {
+			rules::TimeControl::Increment { base, increment } => TimeControl::Increment {
+				base_ms: base.as_millis() as u64,
+				increment_ms: increment.as_millis() as u64,
+			},
+			rules::TimeControl::PerMove(time) => TimeControl::PerMove {
+				ms: time.as_millis() as u64,
+			},
+		}
+	}
+}
+
+impl From<TimeControl> for rules::TimeControl {
+	fn from(val: TimeControl) -> Self {
+		match val {
+			TimeControl::Increment { base_ms, increment_ms } => rules::TimeControl::Increment {
+				base: Duration::from_millis(base_ms),
+				increment: Duration::from_millis(increment_ms),
+			},
+			TimeControl::PerMove { ms } => rules::TimeControl::PerMove(Duration::from_millis(ms)),
+		}
+	}
+}
+
+impl From<rules::RuleSet> for RuleSet {
+	fn from(val: rules::RuleSet) -> Self {
+		match val {
+			rules::RuleSet::Standard => RuleSet::Standard,
+			rules::RuleSet::Blocked => RuleSet::Blocked,
+			rules::RuleSet::Plugin(id) => RuleSet::Plugin { id: id.to_string() },
+		}
+	}
+}
+
+/// Plugin ids are free text in the protocol, but the rules only accept short ids
+impl TryFrom<RuleSet> for rules::RuleSet {
+	type Error = rules::ParseError;
+
+	fn try_from(val: RuleSet) -> Result<Self, Self::Error> {
+		match val {
+			RuleSet::Standard => Ok(rules::RuleSet::Standard),
+			RuleSet::Blocked => Ok(rules::RuleSet::Blocked),
+			RuleSet::Plugin { id } => Ok(rules::RuleSet::Plugin(id.parse()?)),
+		}
+	}
+}
+
+impl From<rules::GameConfig> for GameConfig {
+	fn from(val: rules::GameConfig) -> Self {
+		GameConfig {
+			rule_set: val.rule_set.into(),
+			board_size: val.board_size,
+			time_control: val.time_control.map(|control| control.into()),
+			first_player: val.first_player.seat().into(),
+			rated: val.rated,
+			private: val.private,
+		}
+	}
+}
+
+impl From<&rules::GameConfig> for GameConfig {
+	fn from(val: &rules::GameConfig) -> Self {
+		(*val).into()
+	}
+}
+
+impl TryFrom<GameConfig> for rules::GameConfig {
+	type Error = rules::ParseError;
+
+	fn try_from(val: GameConfig) -> Result<Self, Self::Error> {
+		Ok(rules::GameConfig {
+			rule_set: rules::RuleSet::try_from(val.rule_set)?,
+			board_size: val.board_size,
+			time_control: val.time_control.map(|control| control.into()),
+			first_player: match val.first_player {
+				UserRole::Seat2 => rules::Player::Two,
+				UserRole::Seat1 | UserRole::Observer => rules::Player::One,
+			},
+			rated: val.rated,
+			private: val.private,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn moves_are_numbered_from_the_first_player() {
+		let actions = vec![
+			rules::Action::Init,
+			rules::Action::Move("b6".parse().unwrap()),
+			rules::Action::OfferDraw,
+			rules::Action::Move("b2".parse().unwrap()),
+			rules::Action::DeclineDraw,
+			rules::Action::Move(rules::Move::Pass),
+		];
+		let played = played_moves(rules::Player::Two, &actions);
+		assert_eq!(played.len(), 3);
+		assert_eq!(
+			played[1],
+			PlayedMove {
+				ply: 2,
+				role: UserRole::Seat1,
+				mv: Move::Grow {
+					to: Square { col: 1, row: 1 }
+				},
+			}
+		);
+		assert_eq!((played[2].ply, played[2].role), (3, UserRole::Seat2));
+	}
+
+	#[test]
+	fn server_side_actions() {
+		assert_eq!(GameAction::try_from(rules::Action::Resign), Ok(GameAction::Resign));
+		assert_eq!(GameAction::try_from(rules::Action::Init), Err(rules::Action::Init));
+		let action: rules::Action = GameAction::Move(Move::Pass).into();
+		assert_eq!(action, rules::Action::Move(rules::Move::Pass));
+	}
+}
@@ -7,6 +7,7 @@ use ygame_game as rules;
 use ygame_protocol as msg;
 
 use std::result::Result as StdResult;
+use std::time::Duration;
 
 #[derive(MessageResponse, Clone, Debug)]
 pub struct RegisterUserResponse {
@@ -46,9 +47,20 @@ pub struct GameList {
 #[rtype(result = "GameList")]
 pub struct AskGameList {}
 
+#[derive(MessageResponse, Clone, Debug)]
+pub struct RuleSets {
+	pub list: Vec<rules::RuleSet>,
+}
+
+#[derive(Message, Clone, Debug)]
+#[rtype(result = "RuleSets")]
+pub struct AskRuleSets {}
+
 #[derive(Message, Clone, Debug)]
-#[rtype(result = "GameCreated")]
+#[rtype(result = "StdResult<GameCreated, rules::ConfigError>")]
 pub struct CreateGame {
+	pub config: rules::GameConfig,
+	pub position: Option<rules::GameState>, // custom starting position
 	pub user_uid: Uuid,
 	pub user_name: String,
 	pub client_id: Uuid,
@@ -61,13 +73,15 @@ pub struct GameCreated {
 	pub game_name: String,
 	pub game_addr: Recipient<GameAction>,
 	pub user_seat: rules::UserRole,
+	pub config: rules::GameConfig,
+	pub position: Option<rules::GameState>,
 }
 
 #[derive(Message, Clone, Debug)]
 #[rtype(result = "GameFound")]
 pub struct GetGame {
 	pub game_id: Uuid,
-	pub client_addr: Recipient<GameMessage>,
+	pub client_addr: Option<Recipient<GameMessage>>, // None for the HTTP routes
 }
 
 #[derive(MessageResponse, Clone, Debug)]
@@ -83,16 +97,20 @@ pub struct GameJoined {
 	pub seat_1_username: Option<String>,
 	pub seat_2_username: Option<String>,
 	pub moves: Vec<rules::Action>,
+	pub result: Option<rules::GameResult>,
+	pub config: rules::GameConfig,
+	pub position: Option<rules::GameState>,
 }
 
 #[derive(Message, Clone, Debug)]
-#[rtype(result = "GameJoined")]
+#[rtype(result = "Option<GameJoined>")]
 pub struct JoinGame {
 	//pub game_id: Uuid,
 	pub user_uid: Uuid,
 	pub user_name: String,
 	pub client_id: Uuid,
 	pub client_addr: Recipient<GameMessage>,
+	pub spectate: bool, // whether the client may join as an observer, None is returned otherwise
 }
 
 #[derive(Message, Clone, Debug)]
@@ -103,12 +121,55 @@ pub enum LobbyMessage {
 	GameRemoved { game_id: Uuid },
 }
 
+#[derive(MessageResponse, Clone, Debug)]
+pub struct GameRecord {
+	pub record: rules::GameRecord,
+}
+
+#[derive(Message, Clone, Debug)]
+#[rtype(result = "GameRecord")]
+pub struct AskGameRecord {}
+
+#[derive(MessageResponse, Clone, Debug)]
+pub struct Position {
+	pub state: rules::GameState,
+}
+
+#[derive(Message, Clone, Debug)]
+#[rtype(result = "Position")]
+pub struct AskPosition {}
+
+/// Moves of a game to analyse, the search is too long to run in the game actor
+#[derive(MessageResponse, Clone, Debug)]
+pub enum GameMoves {
+	Available {
+		start: rules::GameState,
+		actions: Vec<rules::Action>,
+	},
+	/// The game is not over, and the user plays in it or it is rated
+	NotAllowed,
+	/// The engine only knows the built-in rule sets
+	UnsupportedRules(rules::RuleSet),
+}
+
+#[derive(Message, Clone, Debug)]
+#[rtype(result = "GameMoves")]
+pub struct AskGameMoves {
+	pub user_uid: Uuid,
+}
+
 #[derive(Message, Clone, Debug)]
 #[rtype(result = "()")]
 pub struct GameClosed {
 	pub game_id: Uuid,
 }
 
+#[derive(Message, Clone, Debug)]
+#[rtype(result = "()")]
+pub struct GameInfoChanged {
+	pub info: game::Info,
+}
+
 #[derive(Message, Clone, Debug)]
 #[rtype(result = "()")]
 pub struct GameMessage {
@@ -119,6 +180,12 @@ pub struct GameMessage {
 #[derive(Clone, Debug)]
 pub enum GameMessageDetails {
 	Action(rules::Action),
+	/// Move accepted by the rules, ply 1 being the first move from the start position
+	Move {
+		mv: rules::Move,
+		ply: u32,
+		player: rules::Player,
+	},
 	UserJoin {
 		user_uid: Uuid,
 		user_name: String,
@@ -128,6 +195,26 @@ pub enum GameMessageDetails {
 		user_uid: Uuid,
 		user_seat: rules::UserRole,
 	},
+	DrawOffered {
+		user_seat: rules::UserRole,
+	},
+	DrawDeclined {
+		user_seat: rules::UserRole,
+	},
+	TakebackRequested {
+		user_seat: rules::UserRole,
+	},
+	TakebackDeclined {
+		user_seat: rules::UserRole,
+	},
+	Rollback {
+		plies: u32,
+	},
+	Clocks {
+		seat_1: Duration,
+		seat_2: Duration,
+		running: Option<rules::UserRole>, // seat whose clock is ticking
+	},
 }
 
 #[derive(MessageResponse, Clone, Debug)]
@@ -1,4 +1,5 @@
 mod v1;
+mod v2;
 
 use std::collections::BTreeMap;
 use std::result::Result as StdResult;
@@ -13,6 +14,7 @@ use crate::lobby::Lobby;
 use crate::log;
 use crate::user;
 use crate::Result;
+use ygame_ai as ai;
 use ygame_game as rules;
 
 use actix::fut::ActorFuture;
@@ -24,10 +26,29 @@ use msg::error::code::game as game_error;
 use msg::error::code::lobby as lobby_error;
 use msg::error::code::protocol as protocol_error;
 use msg::error::code::server as server_error;
+use msg::handshake::Capability;
+use msg::handshake::Encoding;
 use msg::v1::LobbyClientMessage;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use uuid::Uuid;
 use ygame_protocol as msg;
 
+// Optional features this server implements, offered to the clients during the handshake
+const SUPPORTED_CAPABILITIES: [Capability; 2] = [Capability::Clocks, Capability::Spectate];
+
+// Search time for each move of an analysed game
+const ANALYSIS_TIME_PER_MOVE: Duration = Duration::from_millis(100);
+
+/// Id given by the client to a request, sent back in the answer
+#[derive(Debug, Clone, PartialEq)]
+enum RequestId {
+	/// Protocol 1 only identifies game creations and game actions, with free text
+	Text(String),
+	Number(u32),
+	Unset,
+}
+
 #[derive(PartialEq)]
 pub enum ClientStatus {
 	Handshake,
@@ -42,6 +63,9 @@ pub struct Client {
 	lobby_addr: Addr<Lobby>,
 	games: BTreeMap<Uuid, Recipient<actmsg::GameAction>>,
 	protocol_version: Option<u32>,
+	encoding: Encoding,            // of the login and running messages
+	capabilities: Vec<Capability>, // agreed during the handshake
+	analysing: bool,               // one engine analysis at a time per connection
 	user_info: Option<user::User>,
 	heart_beat: Instant,
 }
@@ -84,6 +108,9 @@ impl Client {
 			id: Uuid::new_v4(),
 			status: ClientStatus::Handshake,
 			protocol_version: None,
+			encoding: Encoding::default(),
+			capabilities: Vec::new(),
+			analysing: false,
 			user_info: None,
 			lobby_addr,
 			games: BTreeMap::new(),
@@ -108,13 +135,37 @@ impl Client {
 		proposed_protocols.reverse();
 		let sorted_protocols: &Vec<u32> = proposed_protocols;
 		for &protocol_id in sorted_protocols {
-			if protocol_id == msg::v1::VERSION {
+			if protocol_id == msg::v1::VERSION || protocol_id == msg::v2::VERSION {
 				return Some(protocol_id);
 			}
 		}
 		None
 	}
 
+	/// Every known encoding is supported, the first one proposed by the client is taken
+	fn choose_encoding(proposed_encodings: &[Encoding]) -> Encoding {
+		proposed_encodings
+			.iter()
+			.copied()
+			.find(|encoding| *encoding != Encoding::Unknown)
+			.unwrap_or_default()
+	}
+
+	/// Capabilities proposed by the client which are supported here, unknown ones are dropped
+	fn choose_capabilities(proposed_capabilities: &[Capability]) -> Vec<Capability> {
+		let mut capabilities = Vec::new();
+		for capability in proposed_capabilities {
+			if SUPPORTED_CAPABILITIES.contains(capability) && !capabilities.contains(capability) {
+				capabilities.push(*capability);
+			}
+		}
+		capabilities
+	}
+
+	fn has_capability(&self, capability: Capability) -> bool {
+		self.capabilities.contains(&capability)
+	}
+
 	fn required_login(&self) -> Result<&user::User> {
 		match &self.user_info {
 			Some(user_info) => Ok(user_info),
@@ -138,26 +189,57 @@ impl Client {
 	}
 
 	fn on_txt_message(&mut self, msg: String, ctx: &mut ClientContext) -> Result<()> {
-		match self.status {
-			ClientStatus::Handshake => self.on_handshake(ctx, msg),
-			_ => match self.required_protocol()? {
-				1 => Protocol::<1>::on_txt_message(self, msg, ctx),
-				protocol => Err(unimpl!("on_txt for protocol {}", protocol)),
-			},
+		match (&self.status, self.encoding) {
+			(ClientStatus::Handshake, _) => self.on_handshake(ctx, msg),
+			(_, Encoding::Json) => self.on_message(msg.as_bytes(), ctx),
+			(_, Encoding::MessagePack) | (_, Encoding::Unknown) => Err(Error::ProtocolError {
+				details: "Unexpected text message".to_owned(),
+				source: None,
+				error_code: Some(protocol_error::UNEXPECTED_STRING),
+			}),
 		}
 	}
 
 	fn on_bin_message(&mut self, msg: Bytes, ctx: &mut ClientContext) -> Result<()> {
-		match self.status {
-			ClientStatus::Handshake => Err(Error::ProtocolError {
+		match (&self.status, self.encoding) {
+			(ClientStatus::Handshake, _) | (_, Encoding::Json) | (_, Encoding::Unknown) => Err(Error::ProtocolError {
 				details: "Unexpected bin message".to_owned(),
 				source: None,
 				error_code: Some(protocol_error::UNEXPECTED_BIN),
 			}),
-			_ => match self.required_protocol()? {
-				1 => Protocol::<1>::on_bin_message(self, msg, ctx),
-				protocol => Err(unimpl!("on_bin for protocol {}", protocol)),
-			},
+			(_, Encoding::MessagePack) => self.on_message(msg.as_ref(), ctx),
+		}
+	}
+
+	fn on_message(&mut self, msg: &[u8], ctx: &mut ClientContext) -> Result<()> {
+		match self.required_protocol()? {
+			1 => Protocol::<1>::on_message(self, msg, ctx),
+			2 => Protocol::<2>::on_message(self, msg, ctx),
+			protocol => Err(unimpl!("on_message for protocol {}", protocol)),
+		}
+	}
+
+	/// Send a login or running message in the encoding chosen during the handshake
+	fn send_encoded<T: Serialize>(&self, ctx: &mut ClientContext, msg: &T) -> Result<()> {
+		let res: StdResult<(), Box<dyn std::error::Error>> = match self.encoding {
+			Encoding::MessagePack => rmp_serde::encode::to_vec(msg)
+				.map(|msg| ctx.binary(msg))
+				.map_err(Box::from),
+			Encoding::Json => serde_json::to_string(msg).map(|msg| ctx.text(msg)).map_err(Box::from),
+			Encoding::Unknown => Err(Box::from("unknown encoding")),
+		};
+		res.map_err(|e| Error::ServerError {
+			details: "Serialization issue".to_owned(),
+			source: Some(e),
+			error_code: Some(server_error::SERIALIZATION_ERROR),
+		})
+	}
+
+	fn decode_message<T: DeserializeOwned>(&self, msg: &[u8]) -> StdResult<T, Box<dyn std::error::Error>> {
+		match self.encoding {
+			Encoding::MessagePack => rmp_serde::from_read_ref(msg).map_err(Box::from),
+			Encoding::Json => serde_json::from_slice(msg).map_err(Box::from),
+			Encoding::Unknown => Err(Box::from("unknown encoding")),
 		}
 	}
 
@@ -167,17 +249,6 @@ impl Client {
 		}
 	}
 
-	fn on_serialize_error(&mut self, ctx: &mut ClientContext, error: rmp_serde::encode::Error) {
-		self.send_error(
-			ctx,
-			&Error::ServerError {
-				details: "Serialization issue".to_owned(),
-				source: Some(Box::new(error)),
-				error_code: Some(server_error::SERIALIZATION_ERROR),
-			},
-		)
-	}
-
 	fn map_mailbox_err(e: &actix::MailboxError, actor_name: &str) -> Error {
 		Error::ServerError {
 			details: format!("Unable to contact {} actor", actor_name),
@@ -194,6 +265,8 @@ impl Client {
 		if let Error::ProtocolError { .. } = error {
 			self.games = BTreeMap::new();
 			self.protocol_version = None;
+			self.encoding = Encoding::default();
+			self.capabilities = Vec::new();
 			self.status = ClientStatus::Handshake;
 		}
 
@@ -254,11 +327,22 @@ impl Client {
 				} else {
 					let best_protocol_id: Option<u32> = Client::choose_protocol(&mut hello_msg.known_protocols);
 					let resp = if let Some(best_protocol_id) = best_protocol_id {
+						let encoding = Client::choose_encoding(&hello_msg.encodings);
+						let capabilities = Client::choose_capabilities(&hello_msg.capabilities);
 						self.protocol_version = Some(best_protocol_id);
+						self.encoding = encoding;
+						self.capabilities = capabilities.clone();
 						self.status = ClientStatus::Login;
-						log::debug!("Protocol choosen: {}", best_protocol_id);
+						log::debug!(
+							"Protocol choosen: {} with {:?} encoding and capabilities {:?}",
+							best_protocol_id,
+							encoding,
+							capabilities
+						);
 						msg::handshake::HelloResponseMessage::Success {
 							protocol_version: best_protocol_id,
+							encoding,
+							capabilities,
 						}
 					} else {
 						log::warning!("Protocol handshake failure");
@@ -294,12 +378,38 @@ impl Client {
 		Ok(())
 	}
 
-	fn on_create_game(&mut self, ctx: &mut ClientContext, request_uid: String) -> Result<()> {
+	fn on_ask_rule_sets(&self, ctx: &mut ClientContext) -> Result<()> {
+		self.required_login()?;
+		let protocol = self.required_protocol()?;
+		self.lobby_addr
+			.send(actmsg::AskRuleSets {})
+			.into_actor(self)
+			.then(move |res, this, ctx| {
+				let res = match res {
+					Ok(res) => this.forward_msg_with_protocol(protocol, ctx, &res),
+					Err(e) => Err(Client::map_mailbox_err(&e, "lobby")),
+				};
+				this.send_error_if_failed(ctx, res);
+				fut::ready(())
+			})
+			.wait(ctx);
+		Ok(())
+	}
+
+	fn on_create_game(
+		&mut self,
+		ctx: &mut ClientContext,
+		request_id: RequestId,
+		config: rules::GameConfig,
+		position: Option<rules::GameState>,
+	) -> Result<()> {
 		let addr = ctx.address();
 		let user_info = self.required_login()?;
 		let protocol = self.required_protocol()?;
 		self.lobby_addr
 			.send(actmsg::CreateGame {
+				config,
+				position,
 				user_uid: user_info.uid,
 				user_name: user_info.name.clone(),
 				client_id: self.id,
@@ -308,7 +418,12 @@ impl Client {
 			.into_actor(self)
 			.then(move |res, this, ctx| {
 				let res = match res {
-					Ok(res) => this.on_game_created(protocol, ctx, &res, request_uid),
+					Ok(Ok(res)) => this.on_game_created(protocol, ctx, &res, request_id),
+					Ok(Err(e)) => Err(Error::LobbyError {
+						details: "Invalid game configuration".to_owned(),
+						source: Some(Box::new(e)),
+						error_code: Some(lobby_error::INVALID_GAME_CONFIG),
+					}),
 					Err(e) => Err(Client::map_mailbox_err(&e, "lobby")),
 				};
 				this.send_error_if_failed(ctx, res);
@@ -318,7 +433,7 @@ impl Client {
 		Ok(())
 	}
 
-	fn on_join_game(&self, ctx: &mut ClientContext, game_id: Uuid) -> Result<()> {
+	fn on_join_game(&self, ctx: &mut ClientContext, request_id: RequestId, game_id: Uuid) -> Result<()> {
 		let addr = ctx.address();
 		let user_info = self.required_login()?;
 		let protocol = self.required_protocol()?;
@@ -334,7 +449,7 @@ impl Client {
 		self.lobby_addr
 			.send(actmsg::GetGame {
 				game_id: game_id,
-				client_addr: addr.clone().recipient(),
+				client_addr: Some(addr.clone().recipient()),
 			})
 			.into_actor(self)
 			.then(move |res, this, ctx| {
@@ -345,11 +460,25 @@ impl Client {
 							user_name,
 							client_id: this.id,
 							client_addr: addr.recipient(),
+							// Protocol 1 clients spectated before the capabilities existed
+							spectate: protocol == 1 || this.has_capability(Capability::Spectate),
 						})
 						.into_actor(this)
 						.then(move |res, this, ctx| {
 							let res = match res {
-								Ok(res) => this.on_game_joined(protocol, ctx, game_id, res, game.clone().recipient()),
+								Ok(Some(res)) => this.on_game_joined(
+									protocol,
+									ctx,
+									request_id,
+									game_id,
+									res,
+									game.clone().recipient(),
+								),
+								Ok(None) => Err(Error::LobbyError {
+									details: "The game is full and spectating was not agreed".to_owned(),
+									source: None,
+									error_code: Some(lobby_error::GAME_FULL),
+								}),
 								Err(e) => Err(Client::map_mailbox_err(&e, "game")),
 							};
 							this.send_error_if_failed(ctx, res);
@@ -372,17 +501,161 @@ impl Client {
 		Ok(())
 	}
 
+	fn on_ask_game_record(&self, ctx: &mut ClientContext, request_id: RequestId, game_id: Uuid) -> Result<()> {
+		let addr = ctx.address();
+		self.required_login()?;
+		let protocol = self.required_protocol()?;
+		self.lobby_addr
+			.send(actmsg::GetGame {
+				game_id,
+				client_addr: Some(addr.recipient()),
+			})
+			.into_actor(self)
+			.then(move |res, this, ctx| {
+				let res = match res {
+					Ok(actmsg::GameFound::Success { game }) => {
+						game.send(actmsg::AskGameRecord {})
+							.into_actor(this)
+							.then(move |res, this, ctx| {
+								let res = match res {
+									Ok(res) => match protocol {
+										1 => Protocol::<1>::send_game_record(this, ctx, request_id, game_id, &res),
+										2 => Protocol::<2>::send_game_record(this, ctx, request_id, game_id, &res),
+										_ => Err(unimpl!("send_game_record for protocol {}", protocol)),
+									},
+									Err(e) => Err(Client::map_mailbox_err(&e, "game")),
+								};
+								this.send_error_if_failed(ctx, res);
+								fut::ready(())
+							})
+							.wait(ctx);
+						Ok(())
+					}
+					Ok(actmsg::GameFound::Failure) => Err(Error::LobbyError {
+						details: format!("Unable to find game {}", game_id),
+						source: None,
+						error_code: Some(lobby_error::GAME_DOESNT_EXISTS),
+					}),
+					Err(e) => Err(Client::map_mailbox_err(&e, "lobby")),
+				};
+				this.send_error_if_failed(ctx, res);
+				fut::ready(())
+			})
+			.wait(ctx);
+		Ok(())
+	}
+
+	fn on_ask_game_analysis(&self, ctx: &mut ClientContext, request_id: RequestId, game_id: Uuid) -> Result<()> {
+		if self.analysing {
+			return Err(Error::GameError {
+				details: "An analysis is already running for this connection".to_owned(),
+				source: None,
+				error_code: Some(game_error::ANALYSIS_RUNNING),
+			});
+		}
+		let addr = ctx.address();
+		let user_uid = self.required_login()?.uid;
+		let protocol = self.required_protocol()?;
+		self.lobby_addr
+			.send(actmsg::GetGame {
+				game_id,
+				client_addr: Some(addr.recipient()),
+			})
+			.into_actor(self)
+			.then(move |res, this, ctx| {
+				let res = match res {
+					Ok(actmsg::GameFound::Success { game }) => {
+						game.send(actmsg::AskGameMoves { user_uid })
+							.into_actor(this)
+							.then(move |res, this, ctx| {
+								let res = match res {
+									Ok(moves) => this.analyse_game(protocol, ctx, request_id, game_id, moves),
+									Err(e) => Err(Client::map_mailbox_err(&e, "game")),
+								};
+								this.send_error_if_failed(ctx, res);
+								fut::ready(())
+							})
+							.wait(ctx);
+						Ok(())
+					}
+					Ok(actmsg::GameFound::Failure) => Err(Error::LobbyError {
+						details: format!("Unable to find game {}", game_id),
+						source: None,
+						error_code: Some(lobby_error::GAME_DOESNT_EXISTS),
+					}),
+					Err(e) => Err(Client::map_mailbox_err(&e, "lobby")),
+				};
+				this.send_error_if_failed(ctx, res);
+				fut::ready(())
+			})
+			.wait(ctx);
+		Ok(())
+	}
+
+	// The search runs on the blocking thread pool, the client keeps handling its messages meanwhile
+	fn analyse_game(
+		&mut self,
+		protocol: u32,
+		ctx: &mut ClientContext,
+		request_id: RequestId,
+		game_id: Uuid,
+		moves: actmsg::GameMoves,
+	) -> Result<()> {
+		let (start, actions) = match moves {
+			actmsg::GameMoves::Available { start, actions } => (start, actions),
+			actmsg::GameMoves::NotAllowed => {
+				return Err(Error::GameError {
+					details: "Analysis of a game in progress is only available to the observers of unrated games"
+						.to_owned(),
+					source: None,
+					error_code: Some(game_error::ANALYSIS_NOT_ALLOWED),
+				})
+			}
+			actmsg::GameMoves::UnsupportedRules(rule_set) => {
+				return Err(Error::GameError {
+					details: format!("Unable to analyse games of the {} rule set", rule_set),
+					source: None,
+					error_code: Some(game_error::ANALYSIS_NOT_ALLOWED),
+				})
+			}
+		};
+		self.analysing = true;
+		let analysis = actix_web::web::block(move || {
+			let mut engine = ai::AlphaBeta::new(ai::MaterialEvaluator, ai::Limits::time(ANALYSIS_TIME_PER_MOVE));
+			Ok::<_, std::convert::Infallible>(ai::analyse_game(&mut engine, &start, &actions))
+		});
+		ctx.spawn(analysis.into_actor(self).map(move |res, this, ctx| {
+			this.analysing = false;
+			let res = match res {
+				Ok(plies) => match protocol {
+					1 => Protocol::<1>::send_game_analysis(this, ctx, request_id, game_id, &plies),
+					2 => Protocol::<2>::send_game_analysis(this, ctx, request_id, game_id, &plies),
+					_ => Err(unimpl!("send_game_analysis for protocol {}", protocol)),
+				},
+				Err(e) => Err(Error::ServerError {
+					details: format!("Analysis of game {} failed: {}", game_id, e),
+					source: None,
+					error_code: None,
+				}),
+			};
+			this.send_error_if_failed(ctx, res);
+		}));
+		Ok(())
+	}
+
 	fn on_game_joined(
 		&mut self,
 		protocol: u32,
 		ctx: &mut ClientContext,
+		request_id: RequestId,
 		game_uid: Uuid,
 		msg: actmsg::GameJoined,
 		game_addr: Recipient<actmsg::GameAction>,
 	) -> Result<()> {
 		self.games.insert(game_uid, game_addr.clone());
 		match protocol {
-			1 => Protocol::<1>::send_game_joined(self, ctx, game_uid, &msg),
+			1 => Protocol::<1>::send_game_joined(self, ctx, request_id, game_uid, &msg),
+			2 => Protocol::<2>::send_game_joined(self, ctx, request_id, game_uid, &msg),
 			_ => Err(unimpl!("send_game_joined for protocol {}", protocol)),
 		}
 	}
@@ -392,11 +665,12 @@ impl Client {
 		protocol: u32,
 		ctx: &mut ClientContext,
 		msg: &actmsg::GameCreated,
-		request_uid: String,
+		request_id: RequestId,
 	) -> Result<()> {
 		self.games.insert(msg.game_uid, msg.game_addr.clone());
 		match protocol {
-			1 => Protocol::<1>::send_game_created(self, ctx, msg, request_uid),
+			1 => Protocol::<1>::send_game_created(self, ctx, msg, request_id),
+			2 => Protocol::<2>::send_game_created(self, ctx, msg, request_id),
 			_ => Err(unimpl!("on_game_created for protocol {}", protocol)),
 		}
 	}
@@ -404,11 +678,12 @@ impl Client {
 	fn on_game_action(
 		&mut self,
 		ctx: &mut ClientContext,
-		request_id: String,
+		request_id: RequestId,
 		game_id: Uuid,
 		msg: rules::Action,
 	) -> Result<()> {
 		let user_uid = self.required_login()?.uid.clone();
+		let protocol = self.required_protocol()?;
 		let game = match self.games.get(&game_id) {
 			Some(v) => Ok(v),
 			None => Err(Error::LobbyError {
@@ -424,7 +699,11 @@ impl Client {
 		.into_actor(self)
 		.then(move |res, this, ctx| {
 			let res = match res {
-				Ok(msg) => this.send_game_action_response(ctx, request_id, game_id, msg),
+				Ok(msg) => match protocol {
+					1 => Protocol::<1>::send_game_action_response(this, ctx, request_id, game_id, msg),
+					2 => Protocol::<2>::send_game_action_response(this, ctx, request_id, game_id, msg),
+					_ => Err(unimpl!("send_game_action_response for protocol {}", protocol)),
+				},
 				Err(e) => Err(Client::map_mailbox_err(&e, "lobby")),
 			};
 			this.send_error_if_failed(ctx, res);
@@ -436,7 +715,7 @@ impl Client {
 
 	fn forward_msg<T>(&self, ctx: &mut ClientContext, msg: &T) -> Result<()>
 	where
-		Self: MessageForwarder<T, 1>,
+		Self: MessageForwarder<T, 1> + MessageForwarder<T, 2>,
 	{
 		let protocol = self.required_protocol()?;
 		self.forward_msg_with_protocol(protocol, ctx, msg)
@@ -444,10 +723,11 @@ impl Client {
 
 	fn forward_msg_with_protocol<T>(&self, protocol: u32, ctx: &mut ClientContext, msg: &T) -> Result<()>
 	where
-		Self: MessageForwarder<T, 1>,
+		Self: MessageForwarder<T, 1> + MessageForwarder<T, 2>,
 	{
 		match protocol {
 			1 => MessageForwarder::<T, 1>::forward_msg(self, ctx, msg),
+			2 => MessageForwarder::<T, 2>::forward_msg(self, ctx, msg),
 			_ => Err(unimpl!("forward_msg match for protocol {}", protocol)),
 		}
 	}
@@ -534,18 +814,48 @@ trait MessageForwarder<T, const PROTOCOL: u32> {
 }
 
 trait Protocol<const PROTOCOL: u32> {
-	fn on_txt_message(&mut self, msg: String, ctx: &mut ClientContext) -> Result<()>;
-	fn on_bin_message(&mut self, msg: Bytes, ctx: &mut ClientContext) -> Result<()>;
+	/// Login or running message, already checked to be in the chosen encoding
+	fn on_message(&mut self, msg: &[u8], ctx: &mut ClientContext) -> Result<()>;
 
-	fn send_game_joined(&self, ctx: &mut ClientContext, game_id: Uuid, res: &actmsg::GameJoined) -> Result<()>;
-	fn send_game_created(&self, ctx: &mut ClientContext, res: &actmsg::GameCreated, request_uid: String) -> Result<()>;
+	fn send_game_joined(
+		&self,
+		ctx: &mut ClientContext,
+		request_id: RequestId,
+		game_id: Uuid,
+		res: &actmsg::GameJoined,
+	) -> Result<()>;
+	fn send_game_created(
+		&self,
+		ctx: &mut ClientContext,
+		res: &actmsg::GameCreated,
+		request_id: RequestId,
+	) -> Result<()>;
 	fn send_game_action_response(
 		&self,
 		ctx: &mut ClientContext,
-		request_id: String,
+		request_id: RequestId,
 		game_id: Uuid,
 		msg: actmsg::GameActionResponse,
 	) -> Result<()>;
+	// Records and analyses can not be asked with protocol 1
+	fn send_game_record(
+		&self,
+		_ctx: &mut ClientContext,
+		_request_id: RequestId,
+		_game_id: Uuid,
+		_res: &actmsg::GameRecord,
+	) -> Result<()> {
+		Err(unimpl!("send_game_record for protocol {}", PROTOCOL))
+	}
+	fn send_game_analysis(
+		&self,
+		_ctx: &mut ClientContext,
+		_request_id: RequestId,
+		_game_id: Uuid,
+		_plies: &[(rules::Move, ai::Analysis)],
+	) -> Result<()> {
+		Err(unimpl!("send_game_analysis for protocol {}", PROTOCOL))
+	}
 
 	fn protocol() -> u32 {
 		PROTOCOL
@@ -4,6 +4,7 @@ use crate::client::ClientContext;
 use crate::client::ClientStatus;
 use crate::client::MessageForwarder;
 use crate::client::Protocol;
+use crate::client::RequestId;
 use crate::error::*;
 use crate::game;
 use crate::user;
@@ -15,11 +16,37 @@ use ygame_game as rules;
 use ygame_protocol::error::code as error_code;
 use ygame_protocol::v1 as msg;
 
+use std::convert::TryFrom;
 use uuid::Uuid;
 
 impl MessageForwarder<actmsg::GameMessage, 1> for Client {
 	fn forward_msg(&self, ctx: &mut ClientContext, msg: &actmsg::GameMessage) -> Result<()> {
-		let response: msg::RunningServerMessage = msg.clone().into();
+		let message = match &msg.details {
+			actmsg::GameMessageDetails::Action(action) => match msg::GameAction::try_from(action) {
+				Ok(action) => msg::GameServerMessage::Action(action),
+				Err(()) => return Ok(()),
+			},
+			actmsg::GameMessageDetails::Move { .. } => msg::GameServerMessage::Action(msg::GameAction::Move),
+			actmsg::GameMessageDetails::UserJoin {
+				user_uid,
+				user_name,
+				user_seat,
+			} => msg::GameServerMessage::UserJoin {
+				user_uid: user_uid.to_string(),
+				username: user_name.clone(),
+				role: user_seat.into(),
+			},
+			actmsg::GameMessageDetails::UserQuit { user_uid, user_seat } => msg::GameServerMessage::UserQuit {
+				user_uid: user_uid.to_string(),
+				role: user_seat.into(),
+			},
+			// Draws, takebacks and clocks came with protocol 2
+			_ => return Ok(()),
+		};
+		let response = msg::RunningServerMessage::Game {
+			game_id: msg.game_id.to_string(),
+			message,
+		};
 		self.v1_send_response(ctx, &response)
 	}
 }
@@ -54,35 +81,8 @@ impl MessageForwarder<actmsg::GameList, 1> for Client {
 	}
 }
 
-impl From<actmsg::GameMessage> for msg::RunningServerMessage {
-	fn from(msg: actmsg::GameMessage) -> msg::RunningServerMessage {
-		match msg.details {
-			actmsg::GameMessageDetails::Action(action) => msg::RunningServerMessage::Game {
-				game_id: msg.game_id.to_string(),
-				message: msg::GameServerMessage::Action(action.into()),
-			},
-			actmsg::GameMessageDetails::UserJoin {
-				user_uid,
-				user_name,
-				user_seat,
-			} => msg::RunningServerMessage::Game {
-				game_id: msg.game_id.to_string(),
-				message: msg::GameServerMessage::UserJoin {
-					user_uid: user_uid.to_string(),
-					username: user_name,
-					role: user_seat.into(),
-				},
-			},
-			actmsg::GameMessageDetails::UserQuit { user_uid, user_seat } => msg::RunningServerMessage::Game {
-				game_id: msg.game_id.to_string(),
-				message: msg::GameServerMessage::UserQuit {
-					user_uid: user_uid.to_string(),
-					role: user_seat.into(),
-				},
-			},
-		}
-	}
-}
+// Protocol 1 clients can not ask for the rule sets
+impl MessageForwarder<actmsg::RuleSets, 1> for Client {}
 
 impl From<actmsg::GameActionResponse> for msg::GameActionResponse {
 	fn from(msg: actmsg::GameActionResponse) -> msg::GameActionResponse {
@@ -93,42 +93,71 @@ impl From<actmsg::GameActionResponse> for msg::GameActionResponse {
 	}
 }
 
+// Ids of protocol 1 clients are always text
+fn v1_request_id(request_id: RequestId) -> String {
+	match request_id {
+		RequestId::Text(id) => id,
+		RequestId::Number(id) => id.to_string(),
+		RequestId::Unset => String::new(),
+	}
+}
+
+// Status of a game for the user
+pub(super) fn map_v1_game_status(user: &user::User, game_info: &game::Info) -> msg::GameStatus {
+	if game_info.status == game::Status::Finished {
+		msg::GameStatus::Finished
+	} else if Some(user.uid) == game_info.seat_1 || Some(user.uid) == game_info.seat_2 {
+		msg::GameStatus::Rejoinable
+	} else if game_info.seat_1.is_none() || game_info.seat_2.is_none() {
+		msg::GameStatus::Joinable
+	} else {
+		msg::GameStatus::Full
+	}
+}
+
 fn map_v1_game_overview(user: &user::User, game_info: &game::Info) -> msg::GameOverview {
 	msg::GameOverview {
 		id: game_info.id.to_string(),
 		name: game_info.name.clone(),
-		status: if game_info.status == game::Status::Finished {
-			msg::GameStatus::Finished
-		} else if Some(user.uid) == game_info.seat_1 || Some(user.uid) == game_info.seat_2 {
-			msg::GameStatus::Rejoinable
-		} else if game_info.seat_1.is_none() || game_info.seat_2.is_none() {
-			msg::GameStatus::Joinable
-		} else {
-			msg::GameStatus::Full
-		},
+		status: map_v1_game_status(user, game_info),
 	}
 }
 
 impl Protocol<1> for Client {
-	fn send_game_joined(&self, ctx: &mut ClientContext, game_id: Uuid, res: &actmsg::GameJoined) -> Result<()> {
+	fn send_game_joined(
+		&self,
+		ctx: &mut ClientContext,
+		_: RequestId,
+		game_id: Uuid,
+		res: &actmsg::GameJoined,
+	) -> Result<()> {
 		let msg = msg::RunningServerMessage::Lobby(msg::LobbyServerMessages::GameJoined {
 			info: msg::GameDetails {
 				id: game_id.to_string(),
 				name: res.game_name.clone(),
-				is_finished: res.moves.contains(&rules::Action::Finished),
+				is_finished: res.result.is_some(),
 				seat_1_username: res.seat_1_username.clone(),
 				seat_2_username: res.seat_2_username.clone(),
 			},
 			role: res.user_role.into(),
-			moves: res.moves.iter().map(|v| v.into()).collect(),
+			moves: res
+				.moves
+				.iter()
+				.filter_map(|action| msg::GameAction::try_from(action).ok())
+				.collect(),
 		});
 		self.v1_send_response(ctx, &msg)
 	}
 
-	fn send_game_created(&self, ctx: &mut ClientContext, res: &actmsg::GameCreated, request_uid: String) -> Result<()> {
+	fn send_game_created(
+		&self,
+		ctx: &mut ClientContext,
+		res: &actmsg::GameCreated,
+		request_id: RequestId,
+	) -> Result<()> {
 		let login = self.required_login()?;
 		let msg = msg::RunningServerMessage::Lobby(msg::LobbyServerMessages::GameCreated {
-			request_uid,
+			request_uid: v1_request_id(request_id),
 			info: msg::GameDetails {
 				id: res.game_uid.to_string(),
 				name: res.game_name.clone(),
@@ -152,32 +181,24 @@ impl Protocol<1> for Client {
 	fn send_game_action_response(
 		&self,
 		ctx: &mut ClientContext,
-		request_id: String,
+		request_id: RequestId,
 		game_id: Uuid,
 		msg: actmsg::GameActionResponse,
 	) -> Result<()> {
 		let msg = msg::RunningServerMessage::Game {
 			game_id: game_id.to_string(),
 			message: msg::GameServerMessage::GameActionResponse {
-				request_id,
+				request_id: v1_request_id(request_id),
 				response: msg.into(),
 			},
 		};
 		self.v1_send_response(ctx, &msg)
 	}
 
-	fn on_txt_message(&mut self, _: String, _: &mut ClientContext) -> Result<()> {
-		Err(Error::ProtocolError {
-			details: "Unexpected text message".to_owned(),
-			source: None,
-			error_code: Some(error_code::protocol::UNEXPECTED_STRING),
-		})
-	}
-
-	fn on_bin_message(&mut self, msg: Bytes, ctx: &mut ClientContext) -> Result<()> {
+	fn on_message(&mut self, msg: &[u8], ctx: &mut ClientContext) -> Result<()> {
 		match self.status {
 			ClientStatus::Handshake => Err(Error::ProtocolError {
-				details: "Unexpected bin message".to_owned(),
+				details: "Unexpected message".to_owned(),
 				source: None,
 				error_code: Some(error_code::protocol::UNEXPECTED_BIN),
 			}),
@@ -187,21 +208,19 @@ impl Protocol<1> for Client {
 	}
 }
 
-trait ProtocolImplv1 {
+pub(super) trait ProtocolImplv1 {
 	fn v1_send_response(&self, ctx: &mut ClientContext, msg: &msg::RunningServerMessage) -> Result<()>;
 
-	fn v1_on_login(&mut self, ctx: &mut ClientContext, bin: Bytes) -> Result<()>;
-	fn v1_on_running(&mut self, ctx: &mut ClientContext, bin: Bytes) -> Result<()>;
+	fn v1_on_login(&mut self, ctx: &mut ClientContext, msg: &[u8]) -> Result<()>;
+	fn v1_on_running(&mut self, ctx: &mut ClientContext, msg: &[u8]) -> Result<()>;
 
 	fn v1_parse_game_id(&self, game_id: String) -> Result<Uuid>;
 	fn v1_on_client_lobby(&mut self, ctx: &mut ClientContext, msg: msg::LobbyClientMessage) -> Result<()>;
 }
 
 impl ProtocolImplv1 for Client {
-	fn v1_on_login(&mut self, ctx: &mut ClientContext, bin: Bytes) -> Result<()> {
-		let bin: &[u8] = bin.as_ref();
-		let msg: StdResult<msg::LoginMessage, rmp_serde::decode::Error> = rmp_serde::from_read(bin);
-		match msg {
+	fn v1_on_login(&mut self, ctx: &mut ClientContext, msg: &[u8]) -> Result<()> {
+		match self.decode_message::<msg::LoginMessage>(msg) {
 			Ok(msg) => {
 				self.lobby_addr
 					.send(actmsg::RegisterUserMessage {
@@ -225,11 +244,7 @@ impl ProtocolImplv1 for Client {
 									session_uid: res.session_uid.to_string(),
 								};
 								this.status = ClientStatus::Running;
-								match rmp_serde::encode::to_vec(&response) {
-									Ok(response) => ctx.binary(response),
-									Err(e) => this.on_serialize_error(ctx, e),
-								}
-								Ok(())
+								this.send_encoded(ctx, &response)
 							}
 							Err(e) => Err(Client::map_mailbox_err(&e, "lobby")),
 						};
@@ -241,51 +256,49 @@ impl ProtocolImplv1 for Client {
 			}
 			Err(err) => Err(Error::ProtocolError {
 				details: "Bad Login".to_owned(),
-				source: Some(Box::new(err)),
+				source: Some(err),
 				error_code: Some(error_code::protocol::BAD_HANDSHAKE),
 			}),
 		}
 	}
 
-	fn v1_on_running(&mut self, ctx: &mut ClientContext, bin: Bytes) -> Result<()> {
-		let bin: &[u8] = bin.as_ref();
-		let msg: StdResult<msg::RunningClientMessage, rmp_serde::decode::Error> = rmp_serde::from_read(bin);
-		match msg {
+	fn v1_on_running(&mut self, ctx: &mut ClientContext, msg: &[u8]) -> Result<()> {
+		match self.decode_message::<msg::RunningClientMessage>(msg) {
 			Ok(msg) => match msg {
 				msg::RunningClientMessage::Lobby(msg) => self.v1_on_client_lobby(ctx, msg),
-				msg::RunningClientMessage::Game {
-					game_id,
-					request_id,
-					action,
-				} => self.on_game_action(ctx, request_id, self.v1_parse_game_id(game_id)?, action.into()),
+				// Protocol 1 actions do not carry the move played
+				msg::RunningClientMessage::Game { .. } => Err(Error::GameError {
+					details: "Game actions need protocol 2".to_owned(),
+					source: None,
+					error_code: Some(error_code::game::ILLEGAL_MOVE),
+				}),
 			},
 			Err(err) => Err(Error::ProtocolError {
 				details: "Bad message".to_owned(),
-				source: Some(Box::new(err)),
+				source: Some(err),
 				error_code: Some(error_code::protocol::INVALID_MESSAGE),
 			}),
 		}
 	}
 
 	fn v1_send_response(&self, ctx: &mut ClientContext, msg: &msg::RunningServerMessage) -> Result<()> {
-		match rmp_serde::encode::to_vec(&msg) {
-			Ok(response) => {
-				ctx.binary(response);
-				Ok(())
-			}
-			Err(e) => Err(Error::ServerError {
-				details: "Serialization issue".to_owned(),
-				source: Some(Box::new(e)),
-				error_code: Some(error_code::server::SERIALIZATION_ERROR),
-			}),
-		}
+		self.send_encoded(ctx, msg)
 	}
 
 	fn v1_on_client_lobby(&mut self, ctx: &mut ClientContext, msg: msg::LobbyClientMessage) -> Result<()> {
 		match msg {
 			msg::LobbyClientMessage::AskGameList => self.on_ask_game_list(ctx),
-			msg::LobbyClientMessage::CreateGame { request_uid } => self.on_create_game(ctx, request_uid),
-			msg::LobbyClientMessage::JoinGame { game_uid } => self.on_join_game(ctx, self.v1_parse_game_id(game_uid)?),
+			// Games of protocol 1 clients have the default configuration, without clocks they could not show
+			msg::LobbyClientMessage::CreateGame { request_uid } => {
+				let config = rules::GameConfig {
+					time_control: None,
+					..rules::GameConfig::default()
+				};
+				self.on_create_game(ctx, RequestId::Text(request_uid), config, None)
+			}
+			msg::LobbyClientMessage::JoinGame { game_uid } => {
+				self.on_join_game(ctx, RequestId::Unset, self.v1_parse_game_id(game_uid)?)
+			}
 		}
 	}
 
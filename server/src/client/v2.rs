@@ -0,0 +1,348 @@
+use crate::actor_msg as actmsg;
+use crate::client::v1::{map_v1_game_status, ProtocolImplv1};
+use crate::client::Client;
+use crate::client::ClientContext;
+use crate::client::ClientStatus;
+use crate::client::MessageForwarder;
+use crate::client::Protocol;
+use crate::client::RequestId;
+use crate::error::*;
+use crate::game;
+use crate::user;
+use crate::{Result, StdResult};
+
+use actix_web::web::Bytes;
+use ygame_ai as ai;
+use ygame_game as rules;
+use ygame_protocol::error::code as error_code;
+use ygame_protocol::handshake::Capability;
+use ygame_protocol::v2 as msg;
+
+use std::convert::TryFrom;
+use uuid::Uuid;
+
+impl MessageForwarder<actmsg::GameMessage, 2> for Client {
+	fn forward_msg(&self, ctx: &mut ClientContext, msg: &actmsg::GameMessage) -> Result<()> {
+		let message = match &msg.details {
+			actmsg::GameMessageDetails::Action(rules::Action::Init) => msg::GameServerMessage::Started,
+			actmsg::GameMessageDetails::Action(rules::Action::Finished(result)) => {
+				msg::GameServerMessage::GameOver(result.into())
+			}
+			// Other actions only change the game through the messages below
+			actmsg::GameMessageDetails::Action(_) => return Ok(()),
+			actmsg::GameMessageDetails::Move { mv, ply, player } => {
+				msg::GameServerMessage::MovePlayed(msg::PlayedMove {
+					ply: *ply,
+					role: player.seat().into(),
+					mv: mv.into(),
+				})
+			}
+			actmsg::GameMessageDetails::UserJoin {
+				user_uid,
+				user_name,
+				user_seat,
+			} => msg::GameServerMessage::UserJoin {
+				user_uid: user_uid.to_string(),
+				username: user_name.clone(),
+				role: user_seat.into(),
+			},
+			actmsg::GameMessageDetails::UserQuit { user_uid, user_seat } => msg::GameServerMessage::UserQuit {
+				user_uid: user_uid.to_string(),
+				role: user_seat.into(),
+			},
+			actmsg::GameMessageDetails::DrawOffered { user_seat } => {
+				msg::GameServerMessage::DrawOffered { role: user_seat.into() }
+			}
+			actmsg::GameMessageDetails::DrawDeclined { user_seat } => {
+				msg::GameServerMessage::DrawDeclined { role: user_seat.into() }
+			}
+			actmsg::GameMessageDetails::TakebackRequested { user_seat } => {
+				msg::GameServerMessage::TakebackRequested { role: user_seat.into() }
+			}
+			actmsg::GameMessageDetails::TakebackDeclined { user_seat } => {
+				msg::GameServerMessage::TakebackDeclined { role: user_seat.into() }
+			}
+			actmsg::GameMessageDetails::Rollback { plies } => msg::GameServerMessage::Rollback { plies: *plies },
+			actmsg::GameMessageDetails::Clocks { .. } if !self.has_capability(Capability::Clocks) => return Ok(()),
+			actmsg::GameMessageDetails::Clocks {
+				seat_1,
+				seat_2,
+				running,
+			} => msg::GameServerMessage::Clocks(msg::ClockState {
+				seat_1_ms: seat_1.as_millis() as u64,
+				seat_2_ms: seat_2.as_millis() as u64,
+				running: running.as_ref().map(|role| role.into()),
+			}),
+		};
+		let response = msg::RunningServerMessage::Game {
+			game_id: msg.game_id.to_string(),
+			message,
+		};
+		self.v2_send_response(ctx, &response)
+	}
+}
+
+impl MessageForwarder<actmsg::LobbyMessage, 2> for Client {
+	fn forward_msg(&self, ctx: &mut ClientContext, msg: &actmsg::LobbyMessage) -> Result<()> {
+		let user = self.required_login()?;
+		let response = match msg {
+			actmsg::LobbyMessage::NewGame(game_info) => msg::RunningServerMessage::Lobby(
+				msg::LobbyServerMessage::NewGame(map_v2_game_overview(user, game_info)),
+			),
+			actmsg::LobbyMessage::GameStatusChanged(game_info) => msg::RunningServerMessage::Lobby(
+				msg::LobbyServerMessage::GameInfoChanged(map_v2_game_overview(user, game_info)),
+			),
+			actmsg::LobbyMessage::GameRemoved { game_id } => {
+				msg::RunningServerMessage::Lobby(msg::LobbyServerMessage::GameRemoved {
+					id: game_id.to_string(),
+				})
+			}
+		};
+		self.v2_send_response(ctx, &response)
+	}
+}
+
+impl MessageForwarder<actmsg::GameList, 2> for Client {
+	fn forward_msg(&self, ctx: &mut ClientContext, msg: &actmsg::GameList) -> Result<()> {
+		let user = self.required_login()?;
+		let response = msg::RunningServerMessage::Lobby(msg::LobbyServerMessage::GameList {
+			list: msg.list.iter().map(|k| map_v2_game_overview(user, k)).collect(),
+		});
+		self.v2_send_response(ctx, &response)
+	}
+}
+
+impl MessageForwarder<actmsg::RuleSets, 2> for Client {
+	fn forward_msg(&self, ctx: &mut ClientContext, msg: &actmsg::RuleSets) -> Result<()> {
+		let response = msg::RunningServerMessage::Lobby(msg::LobbyServerMessage::RuleSets {
+			list: msg.list.iter().map(|rule_set| (*rule_set).into()).collect(),
+		});
+		self.v2_send_response(ctx, &response)
+	}
+}
+
+fn map_v2_game_overview(user: &user::User, game_info: &game::Info) -> msg::GameOverview {
+	msg::GameOverview {
+		id: game_info.id.to_string(),
+		name: game_info.name.clone(),
+		status: map_v1_game_status(user, game_info),
+		config: game_info.config.into(),
+	}
+}
+
+// The shared client code only gives back the ids read by the protocol 2 implementation
+fn v2_request_id(request_id: RequestId) -> Result<msg::RequestId> {
+	match request_id {
+		RequestId::Number(id) => Ok(id),
+		other => Err(Error::ImplError {
+			details: format!("Request id {:?} can not be sent with protocol 2", other),
+			source: None,
+			error_code: Some(error_code::server::UNIMPL_ERROR),
+		}),
+	}
+}
+
+impl Protocol<2> for Client {
+	fn send_game_joined(
+		&self,
+		ctx: &mut ClientContext,
+		request_id: RequestId,
+		game_id: Uuid,
+		res: &actmsg::GameJoined,
+	) -> Result<()> {
+		let first_player = match &res.position {
+			Some(position) => position.side_to_move(),
+			None => res.config.first_player,
+		};
+		let msg = msg::RunningServerMessage::Lobby(msg::LobbyServerMessage::GameJoined {
+			request_id: v2_request_id(request_id)?,
+			info: msg::GameDetails {
+				id: game_id.to_string(),
+				name: res.game_name.clone(),
+				result: res.result.map(|result| result.into()),
+				seat_1_username: res.seat_1_username.clone(),
+				seat_2_username: res.seat_2_username.clone(),
+				config: res.config.into(),
+				position: res.position.as_ref().map(rules::notation::format_position),
+			},
+			role: res.user_role.into(),
+			moves: msg::played_moves(first_player, &res.moves),
+		});
+		self.v2_send_response(ctx, &msg)
+	}
+
+	fn send_game_created(
+		&self,
+		ctx: &mut ClientContext,
+		res: &actmsg::GameCreated,
+		request_id: RequestId,
+	) -> Result<()> {
+		let login = self.required_login()?;
+		let username_if = |seat: rules::UserRole| {
+			if res.user_seat == seat {
+				Some(login.name.clone())
+			} else {
+				None
+			}
+		};
+		let msg = msg::RunningServerMessage::Lobby(msg::LobbyServerMessage::GameCreated {
+			request_id: v2_request_id(request_id)?,
+			info: msg::GameDetails {
+				id: res.game_uid.to_string(),
+				name: res.game_name.clone(),
+				result: None,
+				seat_1_username: username_if(rules::UserRole::Seat1),
+				seat_2_username: username_if(rules::UserRole::Seat2),
+				config: res.config.into(),
+				position: res.position.as_ref().map(rules::notation::format_position),
+			},
+			role: res.user_seat.into(),
+		});
+		self.v2_send_response(ctx, &msg)
+	}
+
+	fn send_game_action_response(
+		&self,
+		ctx: &mut ClientContext,
+		request_id: RequestId,
+		game_id: Uuid,
+		msg: actmsg::GameActionResponse,
+	) -> Result<()> {
+		let msg = msg::RunningServerMessage::Game {
+			game_id: game_id.to_string(),
+			message: msg::GameServerMessage::ActionResponse {
+				request_id: v2_request_id(request_id)?,
+				response: msg.into(),
+			},
+		};
+		self.v2_send_response(ctx, &msg)
+	}
+
+	fn send_game_record(
+		&self,
+		ctx: &mut ClientContext,
+		request_id: RequestId,
+		game_id: Uuid,
+		res: &actmsg::GameRecord,
+	) -> Result<()> {
+		let msg = msg::RunningServerMessage::Lobby(msg::LobbyServerMessage::GameRecord {
+			request_id: v2_request_id(request_id)?,
+			game_id: game_id.to_string(),
+			record: res.record.to_string(),
+		});
+		self.v2_send_response(ctx, &msg)
+	}
+
+	fn send_game_analysis(
+		&self,
+		ctx: &mut ClientContext,
+		request_id: RequestId,
+		game_id: Uuid,
+		plies: &[(rules::Move, ai::Analysis)],
+	) -> Result<()> {
+		let msg = msg::RunningServerMessage::Lobby(msg::LobbyServerMessage::GameAnalysis {
+			request_id: v2_request_id(request_id)?,
+			game_id: game_id.to_string(),
+			plies: plies
+				.iter()
+				.map(|(played, analysis)| msg::PlyAnalysis {
+					played: played.into(),
+					best_move: analysis.best_move.map(|mv| mv.into()),
+					score: analysis.score,
+					depth: analysis.depth,
+					principal_variation: analysis.principal_variation.iter().map(|mv| mv.into()).collect(),
+				})
+				.collect(),
+		});
+		self.v2_send_response(ctx, &msg)
+	}
+
+	fn on_message(&mut self, msg: &[u8], ctx: &mut ClientContext) -> Result<()> {
+		match self.status {
+			ClientStatus::Handshake => Err(Error::ProtocolError {
+				details: "Unexpected message".to_owned(),
+				source: None,
+				error_code: Some(error_code::protocol::UNEXPECTED_BIN),
+			}),
+			// Login did not change since protocol 1
+			ClientStatus::Login => self.v1_on_login(ctx, msg),
+			ClientStatus::Running => self.v2_on_running(ctx, msg),
+		}
+	}
+}
+
+trait ProtocolImplv2 {
+	fn v2_send_response(&self, ctx: &mut ClientContext, msg: &msg::RunningServerMessage) -> Result<()>;
+
+	fn v2_on_running(&mut self, ctx: &mut ClientContext, msg: &[u8]) -> Result<()>;
+	fn v2_on_client_lobby(&mut self, ctx: &mut ClientContext, msg: msg::LobbyClientMessage) -> Result<()>;
+}
+
+impl ProtocolImplv2 for Client {
+	fn v2_on_running(&mut self, ctx: &mut ClientContext, msg: &[u8]) -> Result<()> {
+		match self.decode_message::<msg::RunningClientMessage>(msg) {
+			Ok(msg) => match msg {
+				msg::RunningClientMessage::Lobby(msg) => self.v2_on_client_lobby(ctx, msg),
+				msg::RunningClientMessage::Game {
+					game_id,
+					request_id,
+					action,
+				} => self.on_game_action(
+					ctx,
+					RequestId::Number(request_id),
+					self.v1_parse_game_id(game_id)?,
+					action.into(),
+				),
+			},
+			Err(err) => Err(Error::ProtocolError {
+				details: "Bad message".to_owned(),
+				source: Some(err),
+				error_code: Some(error_code::protocol::INVALID_MESSAGE),
+			}),
+		}
+	}
+
+	fn v2_send_response(&self, ctx: &mut ClientContext, msg: &msg::RunningServerMessage) -> Result<()> {
+		self.send_encoded(ctx, msg)
+	}
+
+	fn v2_on_client_lobby(&mut self, ctx: &mut ClientContext, msg: msg::LobbyClientMessage) -> Result<()> {
+		match msg {
+			msg::LobbyClientMessage::AskGameList => self.on_ask_game_list(ctx),
+			msg::LobbyClientMessage::AskRuleSets => self.on_ask_rule_sets(ctx),
+			msg::LobbyClientMessage::CreateGame {
+				request_id,
+				config,
+				position,
+			} => {
+				let invalid_config = |e: rules::ParseError| Error::LobbyError {
+					details: "Invalid game configuration".to_owned(),
+					source: Some(Box::new(e)),
+					error_code: Some(error_code::lobby::INVALID_GAME_CONFIG),
+				};
+				let config = rules::GameConfig::try_from(config).map_err(invalid_config)?;
+				if config.time_control.is_some() && !self.has_capability(Capability::Clocks) {
+					return Err(Error::LobbyError {
+						details: "Timed games need the clocks capability".to_owned(),
+						source: None,
+						error_code: Some(error_code::lobby::INVALID_GAME_CONFIG),
+					});
+				}
+				let position = match position {
+					Some(position) => Some(rules::notation::parse_position(&position).map_err(invalid_config)?),
+					None => None,
+				};
+				self.on_create_game(ctx, RequestId::Number(request_id), config, position)
+			}
+			msg::LobbyClientMessage::JoinGame { request_id, game_id } => {
+				self.on_join_game(ctx, RequestId::Number(request_id), self.v1_parse_game_id(game_id)?)
+			}
+			msg::LobbyClientMessage::AskGameRecord { request_id, game_id } => {
+				self.on_ask_game_record(ctx, RequestId::Number(request_id), self.v1_parse_game_id(game_id)?)
+			}
+			msg::LobbyClientMessage::AskGameAnalysis { request_id, game_id } => {
+				self.on_ask_game_analysis(ctx, RequestId::Number(request_id), self.v1_parse_game_id(game_id)?)
+			}
+		}
+	}
+}
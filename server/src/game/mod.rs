@@ -3,6 +3,7 @@ mod name;
 use std::{
 	collections::{BTreeMap, BTreeSet},
 	fmt::{self, Display},
+	result::Result as StdResult,
 	time::{Duration, Instant},
 };
 
@@ -16,18 +17,30 @@ use crate::error::*;
 use crate::lobby;
 use crate::log;
 use crate::user;
+use crate::utils::date;
 use ygame_game as rules;
+use ygame_protocol::error::code::game as game_error;
 
 //use serde::{Deserialize, Serialize};
 
 const GAME_EXPIRATION: Duration = Duration::from_secs(3600 * 24 * 30);
 
+// Number of occurrences of the same position leading to a draw
+const REPETITION_LIMIT: usize = 3;
+
+// How often the clock of the player to move is checked for a flag fall
+const CLOCK_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
 #[derive(Debug)]
 pub struct Game {
 	pub id: Uuid,
 	pub name: String,
+	date: String, // creation date, for the game record
+	config: rules::GameConfig,
+	rules: &'static dyn rules::Rules,   // rules of the configured rule set
+	position: Option<rules::GameState>, // custom starting position, instead of the one of the rules
 	inited: bool,
-	finished: bool,
+	result: Option<rules::GameResult>,
 	timeout: Instant,
 	lobby_addr: Addr<lobby::Lobby>,
 	seat_1_user_uid: Option<Uuid>,
@@ -37,6 +50,12 @@ pub struct Game {
 	clients: BTreeMap<Uuid, Recipient<actmsg::GameMessage>>,
 	users: BTreeMap<Uuid, BTreeSet<Uuid>>, // clients ids indexed by user id
 	moves: Vec<rules::Action>,
+	state: rules::GameState,
+	positions: Vec<u64>, // hashes of every position reached since the start, to detect repetitions
+	draw_offer: Option<rules::Player>, // player waiting for an answer to their draw offer
+	takeback_request: Option<rules::Player>, // player waiting for an answer to their takeback request
+	clocks: Option<rules::Clocks>, // None for untimed games
+	turn_start: Instant, // when the player to move got their turn
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -53,6 +72,7 @@ pub struct Info {
 	pub status: Status,
 	pub seat_1: Option<Uuid>,
 	pub seat_2: Option<Uuid>,
+	pub config: rules::GameConfig,
 }
 
 impl Actor for Game {
@@ -64,6 +84,9 @@ impl Actor for Game {
 				ctx.stop();
 			}
 		});
+		ctx.run_interval(CLOCK_CHECK_INTERVAL, |act, _| {
+			act.check_flag();
+		});
 	}
 
 	fn stopping(&mut self, _: &mut Self::Context) -> Running {
@@ -73,14 +96,24 @@ impl Actor for Game {
 }
 
 impl Game {
-	pub fn new(lobby_addr: Addr<lobby::Lobby>) -> Game {
+	pub fn new(
+		lobby_addr: Addr<lobby::Lobby>,
+		config: rules::GameConfig,
+		rules: &'static dyn rules::Rules,
+		position: Option<rules::GameState>,
+	) -> Game {
 		Game {
 			id: Uuid::new_v4(),
+			config,
+			rules,
+			state: position.clone().unwrap_or_else(|| rules.initial_state(&config)),
+			position,
 			inited: false,
-			finished: false,
+			result: None,
 			timeout: Instant::now() + GAME_EXPIRATION,
 			lobby_addr,
 			name: name::generate_name(),
+			date: date::today(),
 			seat_1_user_uid: None,
 			seat_1_username: None,
 			seat_2_user_uid: None,
@@ -88,6 +121,11 @@ impl Game {
 			clients: BTreeMap::new(),
 			users: BTreeMap::new(),
 			moves: Vec::new(),
+			positions: Vec::new(),
+			draw_offer: None,
+			takeback_request: None,
+			clocks: config.time_control.map(rules::Clocks::new),
+			turn_start: Instant::now(),
 		}
 	}
 
@@ -118,7 +156,7 @@ impl Game {
 		Info {
 			id: self.id,
 			name: self.name.clone(),
-			status: if self.finished {
+			status: if self.result.is_some() {
 				Status::Finished
 			} else if self.inited {
 				Status::Started
@@ -127,6 +165,14 @@ impl Game {
 			},
 			seat_1: self.seat_1_user_uid,
 			seat_2: self.seat_2_user_uid,
+			config: self.config,
+		}
+	}
+
+	fn start_position(&self) -> rules::GameState {
+		match &self.position {
+			Some(position) => position.clone(),
+			None => self.rules.initial_state(&self.config),
 		}
 	}
 
@@ -167,7 +213,7 @@ impl Game {
 	fn get_user_role(&self, user_uid: Uuid) -> rules::UserRole {
 		if Some(user_uid) == self.seat_1_user_uid {
 			rules::UserRole::Seat1
-		} else if Some(user_uid) == self.seat_1_user_uid {
+		} else if Some(user_uid) == self.seat_2_user_uid {
 			rules::UserRole::Seat2
 		} else {
 			rules::UserRole::Observer
@@ -177,10 +223,81 @@ impl Game {
 	fn init_game(&mut self) {
 		let action = rules::Action::Init;
 		self.moves.push(action.clone());
+		self.positions.push(self.state.hash());
 		self.inited = true;
+		self.turn_start = Instant::now();
+		self.broadcast(actmsg::GameMessageDetails::Action(action));
+		self.broadcast_clocks();
+		self.notify_lobby();
+	}
+
+	fn finish(&mut self, result: rules::GameResult) {
+		log::info!("Game {} finished: {}", self.name, result);
+		let action = rules::Action::Finished(result);
+		self.moves.push(action.clone());
+		self.result = Some(result);
+		self.draw_offer = None;
+		self.takeback_request = None;
+		self.broadcast(actmsg::GameMessageDetails::Action(action));
+		self.broadcast_clocks();
+		self.notify_lobby();
+	}
+
+	fn clocks_details(&self) -> Option<actmsg::GameMessageDetails> {
+		let running = self.inited && self.result.is_none();
+		self.clocks.map(|clocks| actmsg::GameMessageDetails::Clocks {
+			seat_1: clocks.remaining(rules::Player::One),
+			seat_2: clocks.remaining(rules::Player::Two),
+			running: if running {
+				Some(self.state.side_to_move().seat())
+			} else {
+				None
+			},
+		})
+	}
+
+	fn broadcast_clocks(&self) {
+		if let Some(details) = self.clocks_details() {
+			self.broadcast(details);
+		}
+	}
+
+	// Charge the time spent since the start of the turn to the player to move, false if their flag fell
+	fn charge_clock(&mut self) -> bool {
+		let elapsed = self.turn_start.elapsed();
+		self.turn_start = Instant::now();
+		let player = self.state.side_to_move();
+		match &mut self.clocks {
+			Some(clocks) => clocks.spend(player, elapsed),
+			None => true,
+		}
+	}
+
+	fn flag_fall(&mut self) {
+		let player = self.state.side_to_move();
+		self.finish(rules::GameResult::win(player.opponent(), rules::EndReason::Timeout));
+	}
+
+	fn check_flag(&mut self) {
+		if !self.inited || self.result.is_some() {
+			return;
+		}
+		if let Some(clocks) = &self.clocks {
+			if self.turn_start.elapsed() >= clocks.remaining(self.state.side_to_move()) {
+				self.charge_clock();
+				self.flag_fall();
+			}
+		}
+	}
+
+	fn notify_lobby(&self) {
+		self.lobby_addr.do_send(actmsg::GameInfoChanged { info: self.info() });
+	}
+
+	fn broadcast(&self, details: actmsg::GameMessageDetails) {
 		let msg = actmsg::GameMessage {
 			game_id: self.id,
-			details: actmsg::GameMessageDetails::Action(action),
+			details,
 		};
 		for (_, client) in &self.clients {
 			if let Err(e) = client.do_send(msg.clone()) {
@@ -188,20 +305,203 @@ impl Game {
 			}
 		}
 	}
+
+	// The player controlled by the user, as long as the game is running
+	fn running_player(&self, user_uid: Option<Uuid>) -> StdResult<rules::Player, u32> {
+		let player = user_uid.and_then(|uid| self.get_user_role(uid).player());
+		match player {
+			Some(player) if self.inited && self.result.is_none() => Ok(player),
+			_ => Err(game_error::NOT_YOUR_TURN),
+		}
+	}
+
+	// Check that the player is allowed to move now and that the rules accept it
+	fn play(&mut self, player: rules::Player, action: rules::Action) -> StdResult<(), u32> {
+		if player != self.state.side_to_move() {
+			return Err(game_error::NOT_YOUR_TURN);
+		}
+		if !self.charge_clock() {
+			self.flag_fall();
+			return Err(game_error::TIME_OUT);
+		}
+		let mv = match &action {
+			rules::Action::Move(mv) => *mv,
+			_ => return Err(game_error::ILLEGAL_MOVE),
+		};
+		if let Err(e) = self.rules.apply(&mut self.state, &mv) {
+			log::debug!("Illegal move {} in game {}: {}", action, self.name, e);
+			return Err(game_error::ILLEGAL_MOVE);
+		}
+		log::debug!(
+			"Game {}: {} played {}, position {}",
+			self.name,
+			player,
+			action,
+			rules::notation::format_position(&self.state)
+		);
+		self.moves.push(action);
+		self.positions.push(self.state.hash());
+		if let Some(clocks) = &mut self.clocks {
+			clocks.end_turn(player);
+		}
+		self.broadcast(actmsg::GameMessageDetails::Move {
+			mv,
+			ply: self.played_plies() as u32,
+			player,
+		});
+		self.broadcast_clocks();
+
+		// Playing a move implicitly declines the draw offered by the opponent
+		if self.draw_offer == Some(player.opponent()) {
+			self.draw_offer = None;
+			self.broadcast(actmsg::GameMessageDetails::DrawDeclined {
+				user_seat: player.seat(),
+			});
+		}
+		// A pending takeback request is obsolete as soon as a new move is played
+		if let Some(requester) = self.takeback_request.take() {
+			self.broadcast(actmsg::GameMessageDetails::TakebackDeclined {
+				user_seat: requester.opponent().seat(),
+			});
+		}
+		if let Some(result) = self.rules.outcome(&self.state) {
+			self.finish(result);
+		} else if self.repetitions() >= REPETITION_LIMIT {
+			self.finish(rules::GameResult::draw(rules::EndReason::Repetition));
+		}
+		Ok(())
+	}
+
+	// Number of times the current position has been reached
+	fn repetitions(&self) -> usize {
+		let hash = self.state.hash();
+		self.positions.iter().filter(|position| **position == hash).count()
+	}
+
+	// Number of plies to cancel to take back the last move of the player
+	fn takeback_plies(&self, player: rules::Player) -> usize {
+		if self.state.side_to_move() == player {
+			2
+		} else {
+			1
+		}
+	}
+
+	// Number of moves played since the start position
+	fn played_plies(&self) -> usize {
+		self.moves
+			.iter()
+			.filter(|action| matches!(action, rules::Action::Move(_)))
+			.count()
+	}
+
+	fn request_takeback(&mut self, player: rules::Player) -> StdResult<(), u32> {
+		if self.takeback_request.is_some() || self.played_plies() < self.takeback_plies(player) {
+			return Err(game_error::ILLEGAL_MOVE);
+		}
+		self.takeback_request = Some(player);
+		self.broadcast(actmsg::GameMessageDetails::TakebackRequested {
+			user_seat: player.seat(),
+		});
+		Ok(())
+	}
+
+	fn answer_takeback(&mut self, player: rules::Player, accept: bool) -> StdResult<(), u32> {
+		if self.takeback_request != Some(player.opponent()) {
+			return Err(game_error::NO_TAKEBACK_REQUEST);
+		}
+		self.takeback_request = None;
+		if !accept {
+			self.broadcast(actmsg::GameMessageDetails::TakebackDeclined {
+				user_seat: player.seat(),
+			});
+			return Ok(());
+		}
+
+		if !self.charge_clock() {
+			self.flag_fall();
+			return Err(game_error::TIME_OUT);
+		}
+
+		// The game is running, so the last actions of the log are all moves
+		let plies = self.takeback_plies(player.opponent());
+		self.moves.truncate(self.moves.len() - plies);
+		self.positions.truncate(self.positions.len() - plies);
+		self.state = match self.rules.replay(&self.start_position(), &self.moves) {
+			Ok(state) => state,
+			Err(e) => {
+				log::error!("Unable to replay the moves of game {}: {}", self.name, e);
+				return Err(game_error::ILLEGAL_MOVE);
+			}
+		};
+		self.broadcast(actmsg::GameMessageDetails::Rollback { plies: plies as u32 });
+		self.broadcast_clocks();
+		Ok(())
+	}
+
+	fn offer_draw(&mut self, player: rules::Player) -> StdResult<(), u32> {
+		if self.draw_offer.is_some() {
+			return Err(game_error::ILLEGAL_MOVE);
+		}
+		self.draw_offer = Some(player);
+		self.broadcast(actmsg::GameMessageDetails::DrawOffered {
+			user_seat: player.seat(),
+		});
+		Ok(())
+	}
+
+	fn answer_draw(&mut self, player: rules::Player, accept: bool) -> StdResult<(), u32> {
+		if self.draw_offer != Some(player.opponent()) {
+			return Err(game_error::NO_DRAW_OFFER);
+		}
+		self.draw_offer = None;
+		if accept {
+			self.finish(rules::GameResult::draw(rules::EndReason::Agreement));
+		} else {
+			self.broadcast(actmsg::GameMessageDetails::DrawDeclined {
+				user_seat: player.seat(),
+			});
+		}
+		Ok(())
+	}
 }
 
 impl Handler<actmsg::GameAction> for Game {
 	type Result = actmsg::GameActionResponse;
 
 	fn handle(&mut self, msg: actmsg::GameAction, _: &mut Context<Self>) -> Self::Result {
-		unimplemented!("HERE !!!")
+		let player = match self.running_player(msg.user) {
+			Ok(player) => player,
+			Err(reason) => return actmsg::GameActionResponse::Illegal { reason },
+		};
+		let res = match msg.action {
+			rules::Action::Move(_) => self.play(player, msg.action),
+			rules::Action::Resign => {
+				self.finish(rules::GameResult::win(player.opponent(), rules::EndReason::Resignation));
+				Ok(())
+			}
+			rules::Action::OfferDraw => self.offer_draw(player),
+			rules::Action::AcceptDraw => self.answer_draw(player, true),
+			rules::Action::DeclineDraw => self.answer_draw(player, false),
+			rules::Action::RequestTakeback => self.request_takeback(player),
+			rules::Action::AcceptTakeback => self.answer_takeback(player, true),
+			rules::Action::DeclineTakeback => self.answer_takeback(player, false),
+			rules::Action::Init | rules::Action::Finished(_) => Err(game_error::ILLEGAL_MOVE),
+		};
+		match res {
+			Ok(()) => actmsg::GameActionResponse::Ok,
+			Err(reason) => actmsg::GameActionResponse::Illegal { reason },
+		}
 	}
 }
 
 impl Handler<actmsg::JoinGame> for Game {
-	type Result = actmsg::GameJoined;
+	type Result = Option<actmsg::GameJoined>;
 
 	fn handle(&mut self, msg: actmsg::JoinGame, ctx: &mut Context<Self>) -> Self::Result {
+		if !msg.spectate && self.choose_seat(msg.user_uid) == rules::UserRole::Observer {
+			return None;
+		}
 		let username = msg.user_name.clone();
 
 		// Add client to client list and define user role
@@ -230,6 +530,17 @@ impl Handler<actmsg::JoinGame> for Game {
 					log::warning!("Unable to send message to client: {}", e);
 				}
 			}
+
+			// And give the current clocks to the new client
+			if let (Some(details), Some(client)) = (this.clocks_details(), this.clients.get(&msg.client_id)) {
+				let clocks_msg = actmsg::GameMessage {
+					game_id: this.id,
+					details,
+				};
+				if let Err(e) = client.do_send(clocks_msg) {
+					log::warning!("Unable to send message to client: {}", e);
+				}
+			}
 		});
 
 		// Init the game and tell all game clients that the game has been inited
@@ -240,12 +551,59 @@ impl Handler<actmsg::JoinGame> for Game {
 		}
 
 		// Return the current game info
-		actmsg::GameJoined {
+		Some(actmsg::GameJoined {
 			game_name: self.name.clone(),
 			user_role: seat,
 			seat_1_username: self.seat_1_username.clone(),
 			seat_2_username: self.seat_2_username.clone(),
 			moves: self.moves.clone(),
+			result: self.result,
+			config: self.config,
+			position: self.position.clone(),
+		})
+	}
+}
+
+impl Handler<actmsg::AskGameRecord> for Game {
+	type Result = actmsg::GameRecord;
+
+	fn handle(&mut self, _: actmsg::AskGameRecord, _: &mut Context<Self>) -> Self::Result {
+		let mut record = rules::GameRecord::from_actions(&self.name, &self.date, &self.moves);
+		record.seat_1 = self.seat_1_username.clone();
+		record.seat_2 = self.seat_2_username.clone();
+		record.variant = self.config.variant();
+		let start = self.start_position();
+		if start != rules::GameState::new() {
+			record.position = Some(start);
+		}
+		actmsg::GameRecord { record }
+	}
+}
+
+impl Handler<actmsg::AskPosition> for Game {
+	type Result = actmsg::Position;
+
+	fn handle(&mut self, _: actmsg::AskPosition, _: &mut Context<Self>) -> Self::Result {
+		actmsg::Position {
+			state: self.state.clone(),
+		}
+	}
+}
+
+impl Handler<actmsg::AskGameMoves> for Game {
+	type Result = actmsg::GameMoves;
+
+	fn handle(&mut self, msg: actmsg::AskGameMoves, _: &mut Context<Self>) -> Self::Result {
+		if let rules::RuleSet::Plugin(_) = self.config.rule_set {
+			return actmsg::GameMoves::UnsupportedRules(self.config.rule_set);
+		}
+		// Finished games only, except for the observers of an unrated game
+		if self.result.is_none() && !self.config.allows_analysis(self.get_user_role(msg.user_uid)) {
+			return actmsg::GameMoves::NotAllowed;
+		}
+		actmsg::GameMoves::Available {
+			start: self.start_position(),
+			actions: self.moves.clone(),
 		}
 	}
 }
@@ -272,3 +630,87 @@ impl Handler<actmsg::Disconnect> for Game {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::plugins::Plugins;
+	use actix::System;
+
+	// Client of the tests, the game messages are dropped
+	struct Sink;
+
+	impl Actor for Sink {
+		type Context = Context<Self>;
+	}
+
+	impl Handler<actmsg::GameMessage> for Sink {
+		type Result = ();
+
+		fn handle(&mut self, _: actmsg::GameMessage, _: &mut Context<Self>) -> Self::Result {}
+	}
+
+	fn join(spectate: bool) -> actmsg::JoinGame {
+		actmsg::JoinGame {
+			user_uid: Uuid::new_v4(),
+			user_name: "user".to_owned(),
+			client_id: Uuid::new_v4(),
+			client_addr: Sink.start().recipient(),
+			spectate,
+		}
+	}
+
+	#[test]
+	fn spectating_needs_the_capability() {
+		System::new("test").block_on(async {
+			let lobby = lobby::Lobby::new(Plugins::empty(), None).start();
+			let game = Game::new(lobby, rules::GameConfig::default(), &rules::Standard, None).start();
+			// Peers without the capability still take the free seats
+			for _ in 0..2 {
+				let joined = game.send(join(false)).await.unwrap();
+				assert!(joined.map_or(false, |joined| joined.user_role != rules::UserRole::Observer));
+			}
+			assert!(game.send(join(false)).await.unwrap().is_none());
+			let joined = game.send(join(true)).await.unwrap();
+			assert_eq!(joined.map(|joined| joined.user_role), Some(rules::UserRole::Observer));
+		});
+	}
+
+	#[test]
+	fn moves_of_running_games_for_observers_of_unrated_games() {
+		System::new("test").block_on(async {
+			for &rated in [true, false].iter() {
+				let lobby = lobby::Lobby::new(Plugins::empty(), None).start();
+				let config = rules::GameConfig {
+					rated,
+					..rules::GameConfig::default()
+				};
+				let game = Game::new(lobby, config, &rules::Standard, None).start();
+				let (seat_1, observer) = (join(true), join(true));
+				let (seat_1_uid, observer_uid) = (seat_1.user_uid, observer.user_uid);
+				game.send(seat_1).await.unwrap();
+				game.send(join(true)).await.unwrap();
+				game.send(observer).await.unwrap();
+				// The game starts right after the second player joined
+				actix::clock::delay_for(Duration::from_millis(10)).await;
+				let ask = |user_uid| game.send(actmsg::AskGameMoves { user_uid });
+
+				let moves = ask(observer_uid).await.unwrap();
+				assert_eq!(matches!(moves, actmsg::GameMoves::Available { .. }), !rated);
+				let moves = ask(seat_1_uid).await.unwrap();
+				assert!(matches!(moves, actmsg::GameMoves::NotAllowed));
+
+				let resign = actmsg::GameAction {
+					action: rules::Action::Resign,
+					user: Some(seat_1_uid),
+				};
+				assert!(matches!(
+					game.send(resign).await.unwrap(),
+					actmsg::GameActionResponse::Ok
+				));
+				let moves = ask(seat_1_uid).await.unwrap();
+				assert!(matches!(moves, actmsg::GameMoves::Available { .. }));
+			}
+		});
+	}
+}
@@ -2,13 +2,18 @@ use crate::actor_msg as actmsg;
 use crate::error::*;
 use crate::game;
 use crate::log;
+use crate::plugins::Plugins;
+use ygame_game as rules;
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::iter::Map;
 use std::ops::Deref;
+use std::path::PathBuf;
+use std::result::Result as StdResult;
 use std::time::{Duration, Instant};
 
 use actix::prelude::*;
+use actix_web::web;
 use uuid::Uuid;
 
 const SESSION_DURATION: Duration = Duration::from_secs(3600 * 24 * 30);
@@ -20,6 +25,8 @@ pub struct Lobby {
 	clients_by_users: BTreeMap<Uuid, BTreeSet<Uuid>>, //Client ids by user uid
 	user_by_client: BTreeMap<Uuid, Uuid>,      //user id by client id
 	games: BTreeMap<Uuid, (game::Info, Addr<game::Game>)>, //games by game_id
+	plugins: Plugins,
+	records_dir: Option<PathBuf>, // where finished games are saved for the opening book
 }
 
 impl Actor for Lobby {
@@ -33,16 +40,47 @@ impl Actor for Lobby {
 }
 
 impl Lobby {
-	pub fn new() -> Lobby {
+	pub fn new(plugins: Plugins, records_dir: Option<PathBuf>) -> Lobby {
 		Lobby {
 			sessions: BTreeMap::new(),
 			clients: BTreeMap::new(),
 			clients_by_users: BTreeMap::new(),
 			user_by_client: BTreeMap::new(),
 			games: BTreeMap::new(),
+			plugins,
+			records_dir,
 		}
 	}
 
+	/// Write the record of a finished game as `{game id}.ygn` in the records directory
+	fn save_record(&self, game_id: Uuid, game_addr: &Addr<game::Game>, ctx: &mut Context<Self>) {
+		let path = match &self.records_dir {
+			Some(dir) => dir.join(format!("{}.ygn", game_id)),
+			None => return,
+		};
+		game_addr
+			.send(actmsg::AskGameRecord {})
+			.into_actor(self)
+			.then(move |res, lobby, ctx| {
+				match res {
+					Ok(actmsg::GameRecord { record }) => {
+						// Written on the blocking thread pool, a slow disk must not hold the lobby
+						let text = record.to_string();
+						let write_path = path.clone();
+						let write = web::block(move || std::fs::write(write_path, text));
+						ctx.spawn(write.into_actor(lobby).map(move |res, _, _| {
+							if let Err(e) = res {
+								log::error!("Unable to write game record {}: {}", path.display(), e);
+							}
+						}));
+					}
+					Err(e) => log::error!("Unable to get the record of game {}: {}", game_id, e),
+				}
+				fut::ready(())
+			})
+			.spawn(ctx);
+	}
+
 	fn clean_old_sessions(&mut self) {
 		let now = Instant::now();
 		self.sessions.retain(move |_, (_, timeout)| timeout.deref() > &now);
@@ -118,36 +156,67 @@ impl Handler<actmsg::AskGameList> for Lobby {
 
 	fn handle(&mut self, _: actmsg::AskGameList, _: &mut Context<Self>) -> Self::Result {
 		actmsg::GameList {
-			list: self.games.values().map(|(info, _)| info.clone()).collect(),
+			list: self
+				.games
+				.values()
+				.filter(|(info, _)| !info.config.private)
+				.map(|(info, _)| info.clone())
+				.collect(),
+		}
+	}
+}
+
+impl Handler<actmsg::AskRuleSets> for Lobby {
+	type Result = actmsg::RuleSets;
+
+	fn handle(&mut self, _: actmsg::AskRuleSets, _: &mut Context<Self>) -> Self::Result {
+		actmsg::RuleSets {
+			list: self.plugins.rule_sets(),
 		}
 	}
 }
 
 impl Handler<actmsg::CreateGame> for Lobby {
-	type Result = actmsg::GameCreated;
+	type Result = StdResult<actmsg::GameCreated, rules::ConfigError>;
 
 	fn handle(&mut self, msg: actmsg::CreateGame, ctx: &mut Context<Self>) -> Self::Result {
-		let mut game = game::Game::new(ctx.address());
+		let game_rules = self
+			.plugins
+			.rules(msg.config.rule_set)
+			.ok_or(rules::ConfigError::UnknownRuleSet(msg.config.rule_set))?;
+		let mut config = msg.config;
+		config.validate()?;
+		game_rules.validate(&config)?;
+		if let Some(position) = &msg.position {
+			game_rules.validate_position(&config, position)?;
+			config.first_player = position.side_to_move();
+		}
+		let mut game = game::Game::new(ctx.address(), config, game_rules, msg.position.clone());
+		log::info!("Game {} created with the {} rules", game.name, config.variant());
 		let user_seat = game.client_join(msg.user_uid, msg.user_name.as_str(), msg.client_id, msg.client_addr);
 		let game_id = game.id;
 		let game_name = game.name.clone();
 		let game_info = game.info();
 		let game_addr = game.start();
 		self.games.insert(game_id, (game_info.clone(), game_addr.clone()));
-		ctx.run_later(Duration::from_millis(1), |this, _| {
-			let msg = actmsg::LobbyMessage::NewGame(game_info);
-			for (_, client) in &this.clients {
-				if let Err(e) = client.do_send(msg.clone()) {
-					log::warning!("Unable to send message to client: {}", e);
+		if !config.private {
+			ctx.run_later(Duration::from_millis(1), |this, _| {
+				let msg = actmsg::LobbyMessage::NewGame(game_info);
+				for (_, client) in &this.clients {
+					if let Err(e) = client.do_send(msg.clone()) {
+						log::warning!("Unable to send message to client: {}", e);
+					}
 				}
-			}
-		});
-		actmsg::GameCreated {
+			});
+		}
+		Ok(actmsg::GameCreated {
 			game_uid: game_id,
 			game_name: game_name,
 			game_addr: game_addr.recipient(),
 			user_seat,
-		}
+			config,
+			position: msg.position,
+		})
 	}
 }
 
@@ -179,6 +248,36 @@ impl Handler<actmsg::GameClosed> for Lobby {
 	}
 }
 
+impl Handler<actmsg::GameInfoChanged> for Lobby {
+	type Result = ();
+
+	fn handle(&mut self, msg: actmsg::GameInfoChanged, ctx: &mut Context<Self>) -> Self::Result {
+		let just_finished = match self.games.get(&msg.info.id) {
+			Some((info, addr))
+				if info.status != game::Status::Finished && msg.info.status == game::Status::Finished =>
+			{
+				Some(addr.clone())
+			}
+			_ => None,
+		};
+		if let Some(addr) = just_finished {
+			self.save_record(msg.info.id, &addr, ctx);
+		}
+		if let Some((info, _)) = self.games.get_mut(&msg.info.id) {
+			*info = msg.info.clone();
+			if info.config.private {
+				return;
+			}
+			let msg = actmsg::LobbyMessage::GameStatusChanged(msg.info);
+			for (_, client) in &self.clients {
+				if let Err(e) = client.do_send(msg.clone()) {
+					log::warning!("Unable to send message to client: {}", e);
+				}
+			}
+		}
+	}
+}
+
 impl Handler<actmsg::Connect> for Lobby {
 	type Result = ();
 
@@ -6,9 +6,11 @@ mod error;
 mod game;
 mod lobby;
 mod log;
+mod plugins;
 mod user;
 mod utils;
 
+use crate::actor_msg as actmsg;
 use actix::{Actor, Addr};
 use actix_web::http::StatusCode;
 use actix_web::{web, App, Error, HttpRequest, HttpResponse, HttpServer};
@@ -18,6 +20,8 @@ use lobby::Lobby;
 use std::net::IpAddr;
 use std::result::Result as StdResult;
 use std::str::FromStr;
+use uuid::Uuid;
+use ygame_game as rules;
 
 type Result<T> = StdResult<T, crate::error::Error>;
 
@@ -27,11 +31,41 @@ async fn index(req: HttpRequest, stream: web::Payload, srv: web::Data<Addr<Lobby
 	ws::start(client, &req, stream)
 }
 
+/// Current position of a game, for thumbnails and link previews
+async fn board_svg(game_id: web::Path<String>, srv: web::Data<Addr<Lobby>>) -> actix_web::Result<HttpResponse> {
+	let game_id = match Uuid::parse_str(&game_id) {
+		Ok(game_id) => game_id,
+		Err(_) => return Ok(not_found_response()),
+	};
+	let game = match srv
+		.send(actmsg::GetGame {
+			game_id,
+			client_addr: None,
+		})
+		.await
+	{
+		Ok(actmsg::GameFound::Success { game }) => game,
+		_ => return Ok(not_found_response()),
+	};
+	match game.send(actmsg::AskPosition {}).await {
+		Ok(actmsg::Position { state }) => Ok(HttpResponse::Ok()
+			.content_type("image/svg+xml")
+			.header("Cache-Control", "no-cache")
+			.body(rules::render::svg(state.board()))),
+		// The game was closed in the meantime
+		Err(_) => Ok(not_found_response()),
+	}
+}
+
 async fn not_found(req: HttpRequest) -> actix_web::Result<HttpResponse> {
 	log::debug!("404 not found request {}", req.path());
-	Ok(HttpResponse::build(StatusCode::NOT_FOUND)
+	Ok(not_found_response())
+}
+
+fn not_found_response() -> HttpResponse {
+	HttpResponse::build(StatusCode::NOT_FOUND)
 		.content_type("text/html; charset=utf-8")
-		.body("<h1>Error 404</h1>"))
+		.body("<h1>Error 404</h1>")
 }
 
 #[actix_web::main]
@@ -62,6 +96,22 @@ async fn main() -> std::io::Result<()> {
 				.takes_value(true)
 				.default_value("127.0.0.1"),
 		)
+		.arg(
+			clap::Arg::with_name("plugins")
+				.long("plugins")
+				.value_name("DIR")
+				.help("Directory of the rule set plugins (or YGAME_PLUGINS env)")
+				.takes_value(true)
+				.default_value("plugins"),
+		)
+		.arg(
+			clap::Arg::with_name("records")
+				.long("records")
+				.value_name("DIR")
+				.help("Directory where finished games are recorded (or YGAME_RECORDS env)")
+				.takes_value(true)
+				.default_value("records"),
+		)
 		.arg(
 			clap::Arg::with_name("v")
 				.short("v")
@@ -108,10 +158,35 @@ async fn main() -> std::io::Result<()> {
 	let bind_address: String = format!("{}:{}", address, port);
 	log::info!("Listening to {}...", bind_address);
 
-	let lobby = Lobby::new().start();
+	let plugins_dir = match matches.occurrences_of("plugins") > 0 {
+		true => matches.value_of("plugins").unwrap().to_string(),
+		false => match std::env::var("YGAME_PLUGINS") {
+			Ok(val) => val,
+			Err(_e) => matches.value_of("plugins").unwrap().to_string(),
+		},
+	};
+	let plugins = plugins::Plugins::load_dir(std::path::Path::new(&plugins_dir));
+
+	let records_dir = match matches.occurrences_of("records") > 0 {
+		true => matches.value_of("records").unwrap().to_string(),
+		false => match std::env::var("YGAME_RECORDS") {
+			Ok(val) => val,
+			Err(_e) => matches.value_of("records").unwrap().to_string(),
+		},
+	};
+	let records_dir = match std::fs::create_dir_all(&records_dir) {
+		Ok(()) => Some(std::path::PathBuf::from(records_dir)),
+		Err(e) => {
+			log::error!("Finished games will not be recorded in {}: {}", records_dir, e);
+			None
+		}
+	};
+
+	let lobby = Lobby::new(plugins, records_dir).start();
 	let server_result = HttpServer::new(move || {
 		App::new()
 			.route("/websocket", web::get().to(index))
+			.route("/games/{id}/board.svg", web::get().to(board_svg))
 			.data(lobby.clone())
 			.default_service(web::route().to(not_found))
 	})
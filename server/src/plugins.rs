@@ -0,0 +1,416 @@
+//! Rule sets loaded from WebAssembly modules, see `ygame_game::plugin` for the interface of the modules
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+#[cfg(feature = "plugins")]
+use std::{fs, path::PathBuf, sync::Arc, sync::Mutex};
+
+#[cfg(feature = "plugins")]
+use wasmer::{
+	imports, wasmparser::Operator, CompilerConfig, Cranelift, Instance, Memory, Module, NativeFunc, RuntimeError,
+	Store, Universal, WasmTypeList,
+};
+#[cfg(feature = "plugins")]
+use wasmer_middlewares::metering::{self, Metering, MeteringPoints};
+
+#[cfg(feature = "plugins")]
+use crate::error::*;
+use crate::log;
+#[cfg(feature = "plugins")]
+use crate::{Result, StdResult};
+use ygame_game as rules;
+#[cfg(feature = "plugins")]
+use ygame_game::{notation, plugin};
+
+// Operators a plugin may execute for one call, far more than any rule set needs
+#[cfg(feature = "plugins")]
+const CALL_BUDGET: u64 = 50_000_000;
+
+/// Plugins loaded at startup, they live as long as the server
+pub struct Plugins {
+	loaded: BTreeMap<rules::PluginId, &'static dyn rules::Rules>,
+}
+
+impl Plugins {
+	pub fn empty() -> Plugins {
+		Plugins {
+			loaded: BTreeMap::new(),
+		}
+	}
+
+	/// Load every `.wasm` module of the directory, the ones which fail to load are logged and skipped
+	#[cfg(feature = "plugins")]
+	pub fn load_dir(dir: &Path) -> Plugins {
+		let mut plugins = Plugins::empty();
+		let entries = match fs::read_dir(dir) {
+			Ok(entries) => entries,
+			Err(e) => {
+				log::info!("No plugin loaded from {}: {}", dir.display(), e);
+				return plugins;
+			}
+		};
+		for entry in entries.flatten() {
+			let path = entry.path();
+			if path.extension().map_or(true, |ext| ext != "wasm") {
+				continue;
+			}
+			match WasmRules::load(&path) {
+				Ok(wasm_rules) => plugins.add(wasm_rules),
+				Err(e) => log::error!("{}", e),
+			}
+		}
+		plugins
+	}
+
+	/// The server is built without a WebAssembly runtime, only the built-in rule sets are available
+	#[cfg(not(feature = "plugins"))]
+	pub fn load_dir(dir: &Path) -> Plugins {
+		log::info!(
+			"No plugin loaded from {}: the server is built without the plugins feature",
+			dir.display()
+		);
+		Plugins::empty()
+	}
+
+	#[cfg(feature = "plugins")]
+	fn add(&mut self, wasm_rules: WasmRules) {
+		if self.loaded.contains_key(&wasm_rules.id) {
+			log::error!(
+				"Plugin {} ignored, the {} rule set is already loaded",
+				wasm_rules.path.display(),
+				wasm_rules.id
+			);
+			return;
+		}
+		log::info!(
+			"Loaded the {} rule set from {}",
+			wasm_rules.id,
+			wasm_rules.path.display()
+		);
+		self.loaded.insert(wasm_rules.id, Box::leak(Box::new(wasm_rules)));
+	}
+
+	/// Rules of a built-in rule set or of a loaded plugin
+	pub fn rules(&self, rule_set: rules::RuleSet) -> Option<&'static dyn rules::Rules> {
+		match rule_set {
+			rules::RuleSet::Plugin(id) => self.loaded.get(&id).copied(),
+			_ => rule_set.rules(),
+		}
+	}
+
+	pub fn rule_sets(&self) -> Vec<rules::RuleSet> {
+		let mut list = rules::RuleSet::BUILTIN.to_vec();
+		list.extend(self.loaded.keys().map(|id| rules::RuleSet::Plugin(*id)));
+		list
+	}
+}
+
+impl fmt::Debug for Plugins {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.loaded.keys()).finish()
+	}
+}
+
+/// Rules implemented by a wasm module
+///
+/// A module failing at runtime is a bug of the plugin: it is logged, and the move or configuration is refused.
+#[cfg(feature = "plugins")]
+pub struct WasmRules {
+	id: rules::PluginId,
+	path: PathBuf,
+	runtime: Mutex<Runtime>, // calls share the memory of the module
+}
+
+#[cfg(feature = "plugins")]
+impl WasmRules {
+	fn load(path: &Path) -> Result<WasmRules> {
+		let load_error = |details: String| Error::ServerError {
+			details: format!("Unable to load plugin {}: {}", path.display(), details),
+			source: None,
+			error_code: None,
+		};
+		let bytes = fs::read(path).map_err(|e| load_error(e.to_string()))?;
+		// A plugin stuck in a loop must not block the games using it
+		let mut compiler = Cranelift::default();
+		compiler.push_middleware(Arc::new(Metering::new(CALL_BUDGET, |_: &Operator| 1)));
+		let store = Store::new(&Universal::new(compiler).engine());
+		let module = Module::new(&store, bytes).map_err(|e| load_error(e.to_string()))?;
+		let instance = Instance::new(&module, &imports! {}).map_err(|e| load_error(e.to_string()))?;
+		let memory = instance
+			.exports
+			.get_memory("memory")
+			.map_err(|e| load_error(e.to_string()))?
+			.clone();
+		let runtime = Runtime { instance, memory };
+		let id = runtime.id()?;
+		let id = match id.parse() {
+			Ok(rules::RuleSet::Plugin(id)) => id,
+			_ => return Err(load_error(format!("invalid rule set id '{}'", id))),
+		};
+		Ok(WasmRules {
+			id,
+			path: path.to_owned(),
+			runtime: Mutex::new(runtime),
+		})
+	}
+
+	fn call(&self, function: &str, input: &str) -> Result<String> {
+		match self.runtime.lock() {
+			Ok(runtime) => runtime.call(function, input),
+			Err(_) => Err(Error::ServerError {
+				details: format!("Plugin {} is unusable after a panic", self.id),
+				source: None,
+				error_code: None,
+			}),
+		}
+	}
+
+	fn parse_position(&self, text: &str) -> Option<rules::GameState> {
+		match notation::parse_position(text) {
+			Ok(state) => Some(state),
+			Err(e) => {
+				log::error!("Plugin {} returned the invalid position '{}': {}", self.id, text, e);
+				None
+			}
+		}
+	}
+}
+
+#[cfg(feature = "plugins")]
+impl fmt::Debug for WasmRules {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "WasmRules({}, {})", self.id, self.path.display())
+	}
+}
+
+#[cfg(feature = "plugins")]
+impl rules::Rules for WasmRules {
+	fn validate(&self, config: &rules::GameConfig) -> StdResult<(), rules::ConfigError> {
+		match self.call("ygame_validate", &plugin::format_config(config)) {
+			Ok(reason) if reason.is_empty() => Ok(()),
+			Ok(reason) => {
+				log::debug!("Plugin {} refused a configuration: {}", self.id, reason);
+				Err(rules::ConfigError::Refused(config.rule_set))
+			}
+			Err(e) => {
+				log::error!("{}", e);
+				Err(rules::ConfigError::Refused(config.rule_set))
+			}
+		}
+	}
+
+	fn initial_state(&self, config: &rules::GameConfig) -> rules::GameState {
+		let state = match self.call("ygame_initial_state", &plugin::format_config(config)) {
+			Ok(text) => self.parse_position(&text),
+			Err(e) => {
+				log::error!("{}", e);
+				None
+			}
+		};
+		// The game must start anyway, from the standard position
+		state.unwrap_or_else(|| rules::Standard.initial_state(config))
+	}
+
+	fn legal_moves(&self, state: &rules::GameState) -> Vec<rules::Move> {
+		match self.call("ygame_legal_moves", &notation::format_position(state)) {
+			Ok(text) => text.split_whitespace().filter_map(|mv| mv.parse().ok()).collect(),
+			Err(e) => {
+				log::error!("{}", e);
+				Vec::new()
+			}
+		}
+	}
+
+	fn apply(&self, state: &mut rules::GameState, mv: &rules::Move) -> StdResult<(), rules::IllegalMove> {
+		let input = format!("{}\n{}", notation::format_position(state), mv);
+		let new_state = match self.call("ygame_apply", &input) {
+			Ok(text) if text.is_empty() => None,
+			Ok(text) => self.parse_position(&text),
+			Err(e) => {
+				log::error!("{}", e);
+				None
+			}
+		};
+		match new_state {
+			Some(new_state) => {
+				*state = new_state;
+				Ok(())
+			}
+			None => Err(rules::IllegalMove::Rejected(*mv)),
+		}
+	}
+
+	fn outcome(&self, state: &rules::GameState) -> Option<rules::GameResult> {
+		let text = match self.call("ygame_outcome", &notation::format_position(state)) {
+			Ok(text) => text,
+			Err(e) => {
+				log::error!("{}", e);
+				return None;
+			}
+		};
+		match plugin::parse_outcome(&text) {
+			Ok(outcome) => outcome,
+			Err(e) => {
+				log::error!("Plugin {} returned the invalid result '{}': {}", self.id, text, e);
+				None
+			}
+		}
+	}
+}
+
+// Instance of a module, and the memory used to exchange text with it
+#[cfg(feature = "plugins")]
+struct Runtime {
+	instance: Instance,
+	memory: Memory,
+}
+
+#[cfg(feature = "plugins")]
+impl Runtime {
+	fn id(&self) -> Result<String> {
+		metering::set_remaining_points(&self.instance, CALL_BUDGET);
+		let packed = self
+			.function::<(), u64>("ygame_id")?
+			.call()
+			.map_err(|e| self.trap("ygame_id", e))?;
+		self.read(packed)
+	}
+
+	fn call(&self, function: &str, input: &str) -> Result<String> {
+		metering::set_remaining_points(&self.instance, CALL_BUDGET);
+		let len = input.len() as u32;
+		let ptr = self
+			.function::<u32, u32>("ygame_alloc")?
+			.call(len)
+			.map_err(|e| self.trap("ygame_alloc", e))?;
+		self.write(ptr, input.as_bytes())?;
+		let packed = self
+			.function::<(u32, u32), u64>(function)?
+			.call(ptr, len)
+			.map_err(|e| self.trap(function, e))?;
+		self.read(packed)
+	}
+
+	fn function<Args: WasmTypeList, Rets: WasmTypeList>(&self, name: &str) -> Result<NativeFunc<Args, Rets>> {
+		self.instance
+			.exports
+			.get_native_function(name)
+			.map_err(|e| Runtime::error(name, e))
+	}
+
+	fn write(&self, ptr: u32, bytes: &[u8]) -> Result<()> {
+		let view = self.memory.view::<u8>();
+		let start = ptr as usize;
+		if start + bytes.len() > view.len() {
+			return Err(Runtime::error("ygame_alloc", "buffer outside of the memory"));
+		}
+		for (cell, byte) in view[start..start + bytes.len()].iter().zip(bytes) {
+			cell.set(*byte);
+		}
+		Ok(())
+	}
+
+	// Read and release an output of the module
+	fn read(&self, packed: u64) -> Result<String> {
+		let (ptr, len) = ((packed >> 32) as u32, packed as u32);
+		let view = self.memory.view::<u8>();
+		let (start, end) = (ptr as usize, ptr as usize + len as usize);
+		if end > view.len() {
+			return Err(Runtime::error("output", "text outside of the memory"));
+		}
+		let bytes: Vec<u8> = view[start..end].iter().map(|cell| cell.get()).collect();
+		self.function::<(u32, u32), ()>("ygame_free")?
+			.call(ptr, len)
+			.map_err(|e| self.trap("ygame_free", e))?;
+		String::from_utf8(bytes).map_err(|e| Runtime::error("output", e))
+	}
+
+	// A trap is also how the metering stops a call over its budget
+	fn trap(&self, function: &str, e: RuntimeError) -> Error {
+		match metering::get_remaining_points(&self.instance) {
+			MeteringPoints::Exhausted => Runtime::error(function, "exceeded its computation budget"),
+			MeteringPoints::Remaining(_) => Runtime::error(function, e),
+		}
+	}
+
+	fn error(function: &str, e: impl fmt::Display) -> Error {
+		Error::ServerError {
+			details: format!("Plugin call to {} failed: {}", function, e),
+			source: None,
+			error_code: None,
+		}
+	}
+}
+
+#[cfg(all(test, feature = "plugins"))]
+mod tests {
+	use super::*;
+	use std::process::Command;
+	use ygame_game::Rules;
+
+	// Empty directory of the test, removed by a previous run
+	fn test_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("ygame-plugins-{}-{}", name, std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		dir
+	}
+
+	#[test]
+	fn sample_plugin() {
+		let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+		let target_dir = workspace.join("target").join("plugin-tests");
+		let status = Command::new(env!("CARGO"))
+			.current_dir(&workspace)
+			.args(["build", "-p", "ygame-plugin-misere", "--release"])
+			.args(["--target", "wasm32-unknown-unknown", "--target-dir"])
+			.arg(&target_dir)
+			.status()
+			.unwrap();
+		assert!(status.success());
+		let dir = test_dir("misere");
+		let module = target_dir.join("wasm32-unknown-unknown/release/ygame_plugin_misere.wasm");
+		fs::copy(module, dir.join("misere.wasm")).unwrap();
+
+		let plugins = Plugins::load_dir(&dir);
+		let misere: rules::RuleSet = "misere".parse().unwrap();
+		assert_eq!(plugins.rule_sets().last(), Some(&misere));
+		let wasm_rules = plugins.rules(misere).unwrap();
+		let config = rules::GameConfig {
+			rule_set: misere,
+			..rules::GameConfig::default()
+		};
+		assert_eq!(wasm_rules.validate(&config), Ok(()));
+		let start = wasm_rules.initial_state(&config);
+		assert_eq!(start, rules::Standard.initial_state(&config));
+		assert_eq!(wasm_rules.legal_moves(&start), rules::Standard.legal_moves(&start));
+		assert_eq!(wasm_rules.outcome(&start), None);
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn endless_call() {
+		let module = wasmer::wat2wasm(
+			br#"(module
+				(memory (export "memory") 1)
+				(data (i32.const 16) "spin")
+				(func (export "ygame_id") (result i64) (i64.const 0x0000001000000004))
+				(func (export "ygame_alloc") (param i32) (result i32) (i32.const 64))
+				(func (export "ygame_free") (param i32 i32))
+				(func (export "ygame_validate") (param i32 i32) (result i64) (i64.const 0x0000001000000000))
+				(func (export "ygame_legal_moves") (param i32 i32) (result i64) (loop (br 0)) (unreachable)))"#,
+		)
+		.unwrap();
+		let dir = test_dir("spin");
+		let path = dir.join("spin.wasm");
+		fs::write(&path, module).unwrap();
+
+		let wasm_rules = WasmRules::load(&path).unwrap();
+		let e = wasm_rules.call("ygame_legal_moves", "7/7/7/7/7/7/7 x").unwrap_err();
+		assert!(e.to_string().contains("exceeded its computation budget"), "{}", e);
+		// The budget is renewed for the next call
+		assert_eq!(wasm_rules.call("ygame_validate", "7 x").unwrap(), "");
+		fs::remove_dir_all(dir).unwrap();
+	}
+}